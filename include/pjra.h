@@ -0,0 +1,47 @@
+/* C interface of python-json-read-adapter, enabled with the `ffi` feature. */
+#ifndef PJRA_H
+#define PJRA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PJRA_OK 0
+#define PJRA_ERR_INVALID_ARGUMENT 1
+#define PJRA_ERR_UNEXPECTED_EOF 2
+#define PJRA_ERR_BUDGET_EXCEEDED 3
+#define PJRA_ERR_OTHER 4
+
+#define PJRA_UNLIMITED SIZE_MAX
+#define PJRA_NO_OFFSET UINT64_MAX
+
+typedef struct pjra_report {
+    size_t nan;
+    size_t pos_inf;
+    size_t neg_inf;
+    uint64_t bytes_processed;
+    uint64_t error_offset;
+} pjra_report;
+
+/* Translates a complete document in place.  `report` may be NULL. */
+int pjra_translate(uint8_t *ptr, size_t len, size_t max_replacements,
+                   pjra_report *report);
+
+/* Streaming translation in caller provided storage.  A translator must be
+   destroyed before its storage is released or initialized again. */
+size_t pjra_translator_size(void);
+size_t pjra_translator_align(void);
+int pjra_translator_init(void *mem, size_t size, size_t max_replacements);
+int pjra_translator_destroy(void *mem);
+int pjra_translator_feed(void *mem, uint8_t *ptr, size_t len);
+int pjra_translator_finish(void *mem);
+int pjra_translator_report(void *mem, pjra_report *report);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,232 @@
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::diff::push_pointer_segment;
+use crate::options::Options;
+use crate::translator::{TokenKind, TranslateStats, Translator};
+
+enum Frame {
+    Object { key: String, in_key: bool },
+    Array { index: usize },
+}
+
+/// Tracks the JSON pointer of the current position in a document.
+#[derive(Default)]
+struct PathTracker {
+    offset: u64,
+    frames: Vec<Frame>,
+    in_string: bool,
+    escaped: bool,
+    key: Option<Vec<u8>>,
+}
+
+impl PathTracker {
+    fn advance(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.offset += 1;
+            if self.in_string {
+                if let Some(ref mut key) = self.key {
+                    key.push(byte);
+                }
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    self.finish_key();
+                }
+                continue;
+            }
+            match (byte, self.frames.last_mut()) {
+                (b'"', Some(Frame::Object { in_key: true, .. })) => {
+                    self.in_string = true;
+                    self.key = Some(vec![b'"']);
+                }
+                (b'"', _) => self.in_string = true,
+                (b'{', _) => self.frames.push(Frame::Object {
+                    key: String::new(),
+                    in_key: true,
+                }),
+                (b'[', _) => self.frames.push(Frame::Array { index: 0 }),
+                (b'}', _) | (b']', _) => {
+                    self.frames.pop();
+                }
+                (b':', Some(Frame::Object { in_key, .. })) => *in_key = false,
+                (b',', Some(Frame::Object { in_key, .. })) => *in_key = true,
+                (b',', Some(Frame::Array { index })) => *index += 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn finish_key(&mut self) {
+        let raw = match self.key.take() {
+            Some(raw) => raw,
+            None => return,
+        };
+        if let Some(Frame::Object { key, .. }) = self.frames.last_mut() {
+            *key = serde_json::from_slice(&raw)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&raw).into_owned());
+        }
+    }
+
+    fn path(&self) -> String {
+        self.frames
+            .iter()
+            .fold(String::new(), |path, frame| match frame {
+                Frame::Object { key, .. } => push_pointer_segment(&path, key),
+                Frame::Array { index } => push_pointer_segment(&path, &index.to_string()),
+            })
+    }
+}
+
+/// Writes a JSON lines audit log of all replacements.
+///
+/// Every rewritten token is recorded as a line with the time of the
+/// replacement, an identifier of the source document, the JSON pointer of the
+/// value and the kind of token:
+///
+/// ```text
+/// {"ts":1700000000.25,"source":"upload-42","offset":7,"path":"/x","kind":"nan"}
+/// ```
+///
+/// Documents are translated through the log with `translate`, which can be
+/// called repeatedly to translate a document chunk by chunk.  A log is meant
+/// for a single document but the sink can be reused with `into_inner`.
+///
+/// ```
+/// # use python_json_read_adapter::AuditLog;
+/// let mut log = AuditLog::new(Vec::new(), "upload-42");
+/// let mut json = br#"{"x": NaN}"#.to_vec();
+/// log.translate_slice(&mut json).unwrap();
+/// let lines = String::from_utf8(log.into_inner()).unwrap();
+/// assert!(lines.contains(r#""path":"/x","kind":"nan""#));
+/// ```
+pub struct AuditLog<W> {
+    sink: W,
+    source: String,
+    translator: Translator,
+    tracker: PathTracker,
+}
+
+impl<W: Write> AuditLog<W> {
+    /// Creates an audit log for the document identified by `source`.
+    pub fn new<S: Into<String>>(sink: W, source: S) -> AuditLog<W> {
+        AuditLog::with_options(sink, source, Options::default())
+    }
+
+    /// Creates an audit log that translates with the given options.
+    pub fn with_options<S: Into<String>>(sink: W, source: S, options: Options) -> AuditLog<W> {
+        AuditLog {
+            sink,
+            source: source.into(),
+            translator: Translator::with_options(options),
+            tracker: PathTracker::default(),
+        }
+    }
+
+    /// Translates the next chunk of the document in place and logs it.
+    ///
+    /// Translation errors are returned as IO errors that wrap the `Error`.
+    /// Replacements that happened before the error are still logged.
+    pub fn translate(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+        let mut replacements = Vec::new();
+        let rv = self
+            .translator
+            .feed_with(bytes, |kind, offset| replacements.push((kind, offset)));
+        let base = self.tracker.offset;
+        let mut consumed = 0;
+        for (kind, offset) in replacements {
+            // tokens never contain structural bytes, so the path is the same
+            // for every byte of a token, even if it started in an earlier
+            // chunk that the tracker has already passed.
+            let upto = (offset.saturating_sub(base) as usize).max(consumed);
+            self.tracker.advance(&bytes[consumed..upto]);
+            consumed = upto;
+            self.write_entry(kind, offset)?;
+        }
+        self.tracker.advance(&bytes[consumed..]);
+        rv.map_err(io::Error::from)
+    }
+
+    /// Translates a complete document in place and logs it.
+    pub fn translate_slice(&mut self, bytes: &mut [u8]) -> io::Result<TranslateStats> {
+        self.translate(bytes)?;
+        self.translator.verify()?;
+        Ok(self.translator.stats())
+    }
+
+    /// Returns the counts of the tokens that were rewritten so far.
+    pub fn stats(&self) -> TranslateStats {
+        self.translator.stats()
+    }
+
+    /// Returns a reference to the sink.
+    pub fn get_ref(&self) -> &W {
+        &self.sink
+    }
+
+    /// Unwraps the log and returns the sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+
+    fn write_entry(&mut self, kind: TokenKind, offset: u64) -> io::Result<()> {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        let kind = match kind {
+            TokenKind::NaN => "nan",
+            TokenKind::PosInfinity => "pos_inf",
+            TokenKind::NegInfinity => "neg_inf",
+            TokenKind::BigInt => "big_int",
+        };
+        writeln!(
+            self.sink,
+            r#"{{"ts":{},"source":{},"offset":{},"path":{},"kind":"{}"}}"#,
+            ts,
+            serde_json::to_string(&self.source)?,
+            offset,
+            serde_json::to_string(&self.tracker.path())?,
+            kind,
+        )
+    }
+}
+
+#[test]
+fn test_audit_log() {
+    let mut log = AuditLog::new(Vec::new(), "test");
+    let mut json = br#"{"a": [1, NaN], "b~/": {"c": -Infinity}, "NaN": Inf"#.to_vec();
+    for chunk in json.chunks_mut(5) {
+        log.translate(chunk).unwrap();
+    }
+    log.translate(&mut b"inity}".to_vec()).unwrap();
+    assert_eq!(log.stats().total(), 3);
+    let entries: Vec<serde_json::Value> = String::from_utf8(log.into_inner())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let paths: Vec<_> = entries
+        .iter()
+        .map(|e| e["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(paths, vec!["/a/1", "/b~0~1/c", "/NaN"]);
+    assert_eq!(entries[1]["kind"], "neg_inf");
+    assert_eq!(entries[1]["source"], "test");
+    assert!(entries[2]["ts"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+fn test_audit_log_budget() {
+    let options = Options::new().max_replacements(1);
+    let mut log = AuditLog::with_options(Vec::new(), "test", options);
+    let mut json = b"[NaN, NaN, NaN]".to_vec();
+    assert!(log.translate_slice(&mut json).is_err());
+    assert_eq!(
+        String::from_utf8(log.into_inner()).unwrap().lines().count(),
+        2
+    );
+}
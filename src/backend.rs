@@ -0,0 +1,174 @@
+// the scanners are called from the translator and must not panic either.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::indexing_slicing,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::arithmetic_side_effects
+    )
+)]
+
+use crate::tables;
+
+/// Chunks smaller than this are scanned with the scalar backend.
+const SWAR_THRESHOLD: usize = 64;
+
+/// The bytes in `tables::INITIAL_INTERESTING`.
+pub(crate) const INITIAL_NEEDLES: &[u8] = b"NI\"";
+/// The bytes in `tables::QUOTED_INTERESTING`.
+pub(crate) const QUOTED_NEEDLES: &[u8] = b"\"\\";
+
+/// The backend used to skip over bytes that need no translation.
+///
+/// Most of a document is made up of bytes that the translator passes through
+/// unchanged.  The backends differ in how they find the next byte that needs
+/// attention; they all produce the same output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Picks a backend for every chunk based on its size.
+    #[default]
+    Auto,
+    /// Looks at one byte at a time.  This has the lowest setup cost.
+    Scalar,
+    /// Looks at eight bytes at a time, which is faster on long runs of
+    /// uninteresting bytes.
+    Swar,
+    /// Uses the vectorized searches of the `memchr` crate, which is fastest
+    /// on long strings.  This requires the `memchr` feature.
+    #[cfg(feature = "memchr")]
+    Memchr,
+    /// Uses SSE2 or AVX2 on x86_64, picked at runtime.  Other targets fall
+    /// back to the scalar backend and `Auto` only picks it on x86_64.  This
+    /// requires the `simd` feature.
+    #[cfg(feature = "simd")]
+    Simd,
+}
+
+impl Backend {
+    /// Resolves `Auto` to a concrete backend for a chunk of `len` bytes.
+    pub(crate) fn resolve(self, len: usize) -> Backend {
+        match self {
+            #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+            Backend::Auto if len >= SWAR_THRESHOLD => Backend::Simd,
+            #[cfg(feature = "memchr")]
+            Backend::Auto if len >= SWAR_THRESHOLD => Backend::Memchr,
+            Backend::Auto if len >= SWAR_THRESHOLD => Backend::Swar,
+            Backend::Auto => Backend::Scalar,
+            other => other,
+        }
+    }
+
+    /// Returns the number of leading bytes that need no translation.
+    ///
+    /// `quoted` selects whether the bytes are scanned as the inside of a
+    /// string or not.
+    pub(crate) fn skip(self, bytes: &[u8], quoted: bool) -> usize {
+        match self.resolve(bytes.len()) {
+            #[cfg(feature = "memchr")]
+            Backend::Memchr => skip_memchr(bytes, quoted),
+            #[cfg(feature = "simd")]
+            Backend::Simd => crate::vectorized::skip(bytes, quoted),
+            Backend::Swar => skip_swar(bytes, quoted),
+            _ => skip_scalar(bytes, quoted),
+        }
+    }
+}
+
+fn skip_scalar(bytes: &[u8], quoted: bool) -> usize {
+    let classes = if quoted {
+        tables::QUOTED_INTERESTING
+    } else {
+        tables::INITIAL_INTERESTING
+    };
+    bytes
+        .iter()
+        .position(|&b| tables::is(b, classes))
+        .unwrap_or(bytes.len())
+}
+
+const LO: u64 = 0x0101_0101_0101_0101;
+const HI: u64 = 0x8080_8080_8080_8080;
+
+/// Returns a mask with the high bit set in the lowest byte equal to `needle`.
+///
+/// Bits above the first match can be wrong, which is fine as only the lowest
+/// set bit is used.
+fn match_mask(word: u64, needle: u8) -> u64 {
+    let x = word ^ LO.wrapping_mul(u64::from(needle));
+    x.wrapping_sub(LO) & !x & HI
+}
+
+fn skip_swar(bytes: &[u8], quoted: bool) -> usize {
+    let needles = if quoted {
+        QUOTED_NEEDLES
+    } else {
+        INITIAL_NEEDLES
+    };
+    let mut chunks = bytes.chunks_exact(8);
+    let mut offset = 0usize;
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let word = u64::from_le_bytes(word);
+        let mask = needles
+            .iter()
+            .fold(0, |mask, &needle| mask | match_mask(word, needle));
+        if mask != 0 {
+            return offset.saturating_add((mask.trailing_zeros() / 8) as usize);
+        }
+        offset = offset.saturating_add(8);
+    }
+    offset.saturating_add(skip_scalar(chunks.remainder(), quoted))
+}
+
+#[cfg(feature = "memchr")]
+fn skip_memchr(bytes: &[u8], quoted: bool) -> usize {
+    if quoted {
+        memchr::memchr2(b'"', b'\\', bytes)
+    } else {
+        memchr::memchr3(b'N', b'I', b'"', bytes)
+    }
+    .unwrap_or(bytes.len())
+}
+
+#[test]
+fn test_needles_match_tables() {
+    for b in 0..=255u8 {
+        assert_eq!(
+            INITIAL_NEEDLES.contains(&b),
+            tables::is(b, tables::INITIAL_INTERESTING)
+        );
+        assert_eq!(
+            QUOTED_NEEDLES.contains(&b),
+            tables::is(b, tables::QUOTED_INTERESTING)
+        );
+    }
+}
+
+#[test]
+fn test_backends_agree() {
+    let doc = br#"{"a": [1, 2, 3], "long key without anything": NaN, "s": "x\"y", "z": Infinity}"#;
+    for start in 0..doc.len() {
+        for &quoted in &[false, true] {
+            let rest = &doc[start..];
+            assert_eq!(
+                skip_swar(rest, quoted),
+                skip_scalar(rest, quoted),
+                "{} {}",
+                start,
+                quoted
+            );
+            #[cfg(feature = "memchr")]
+            assert_eq!(
+                skip_memchr(rest, quoted),
+                skip_scalar(rest, quoted),
+                "{} {}",
+                start,
+                quoted
+            );
+        }
+    }
+}
@@ -0,0 +1,615 @@
+//! An HTTP reverse proxy that sanitizes Python JSON bodies.
+//!
+//! The proxy accepts HTTP/1.1 requests, forwards them to a single upstream
+//! server and translates `NaN` and `Infinity` in JSON request and response
+//! bodies on the way.  It serves as a reference integration of this crate and
+//! is usable as a small ops tool in front of legacy Python services.
+//!
+//! ```text
+//! pjra-proxy --listen 127.0.0.1:8080 --upstream 127.0.0.1:5000 \
+//!     --max-replacements 1000 --max-body-bytes 10485760
+//! ```
+//!
+//! The translation is configured with `--reject`, `--skip-prefix N`,
+//! `--case-insensitive`, `--plus-infinity` and `--reject-binary` which map to
+//! the `Options` of the same names.
+//!
+//! Prometheus style metrics are served at `/__pjra/metrics`.  This is a
+//! blocking server built on `std::net` and does not use the async adapters:
+//! bodies are read fully into memory and every connection handles a single
+//! request.  A fixed number of worker threads (`--workers`, 64 by default)
+//! accepts connections, socket reads and writes time out after
+//! `--timeout-secs` seconds.
+use std::env;
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use python_json_read_adapter::{translate_slice_with_options, Options, TranslateStats};
+
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+#[derive(Debug)]
+struct Config {
+    listen: String,
+    upstream: String,
+    options: Options,
+    max_body_bytes: u64,
+    workers: usize,
+    timeout: Duration,
+}
+
+#[derive(Default)]
+struct Metrics {
+    requests: AtomicU64,
+    rejected: AtomicU64,
+    upstream_errors: AtomicU64,
+    nan: AtomicU64,
+    pos_inf: AtomicU64,
+    neg_inf: AtomicU64,
+}
+
+impl Metrics {
+    fn record(&self, stats: TranslateStats) {
+        self.nan.fetch_add(stats.nan as u64, Ordering::Relaxed);
+        self.pos_inf
+            .fetch_add(stats.pos_inf as u64, Ordering::Relaxed);
+        self.neg_inf
+            .fetch_add(stats.neg_inf as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self) -> String {
+        let get = |x: &AtomicU64| x.load(Ordering::Relaxed);
+        format!(
+            "# TYPE pjra_requests_total counter\n\
+             pjra_requests_total {}\n\
+             # TYPE pjra_rejected_total counter\n\
+             pjra_rejected_total {}\n\
+             # TYPE pjra_upstream_errors_total counter\n\
+             pjra_upstream_errors_total {}\n\
+             # TYPE pjra_replacements_total counter\n\
+             pjra_replacements_total{{kind=\"nan\"}} {}\n\
+             pjra_replacements_total{{kind=\"pos_inf\"}} {}\n\
+             pjra_replacements_total{{kind=\"neg_inf\"}} {}\n",
+            get(&self.requests),
+            get(&self.rejected),
+            get(&self.upstream_errors),
+            get(&self.nan),
+            get(&self.pos_inf),
+            get(&self.neg_inf),
+        )
+    }
+}
+
+/// The head of a request or response.
+struct Head {
+    start_line: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Head {
+    /// Returns the status code of a response head.
+    fn status(&self) -> Option<u16> {
+        self.start_line.split(' ').nth(1)?.parse().ok()
+    }
+
+    /// Checks if a response to a request with this head has a body.
+    fn response_has_body(&self, response: &Head) -> bool {
+        !self.start_line.starts_with("HEAD ")
+            && !matches!(response.status(), Some(100..=199) | Some(204) | Some(304))
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    }
+
+    fn is_json(&self) -> bool {
+        self.header("content-type").is_some_and(|value| {
+            let mime = value.split(';').next().unwrap_or("").trim();
+            mime.eq_ignore_ascii_case("application/json") || mime.ends_with("+json")
+        })
+    }
+
+    /// Checks if the body is compressed, for instance with `gzip`.
+    fn is_encoded(&self) -> bool {
+        self.header("content-encoding")
+            .is_some_and(|value| !value.is_empty() && !value.eq_ignore_ascii_case("identity"))
+    }
+
+    fn is_chunked(&self) -> bool {
+        self.header("transfer-encoding")
+            .is_some_and(|value| value.to_ascii_lowercase().contains("chunked"))
+    }
+
+    fn content_length(&self) -> io::Result<Option<u64>> {
+        match self.header("content-length") {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| invalid_data("invalid content-length")),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the head for a body of `body_len` bytes.
+    ///
+    /// Without a body the framing headers are passed on unchanged as they
+    /// describe the body a `HEAD` request would have gotten.
+    fn write_to<W: Write>(&self, w: &mut W, body_len: Option<usize>) -> io::Result<()> {
+        write!(w, "{}\r\n", self.start_line)?;
+        for (key, value) in &self.headers {
+            if (body_len.is_some()
+                && (key.eq_ignore_ascii_case("content-length")
+                    || key.eq_ignore_ascii_case("transfer-encoding")))
+                || key.eq_ignore_ascii_case("connection")
+            {
+                continue;
+            }
+            write!(w, "{}: {}\r\n", key, value)?;
+        }
+        if let Some(body_len) = body_len {
+            write!(w, "content-length: {}\r\n", body_len)?;
+        }
+        write!(w, "connection: close\r\n\r\n")
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// The error for bodies over `--max-body-bytes`.
+#[derive(Debug)]
+struct BodyTooLarge;
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("body too large")
+    }
+}
+
+impl error::Error for BodyTooLarge {}
+
+fn body_too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, BodyTooLarge)
+}
+
+fn is_body_too_large(err: &io::Error) -> bool {
+    err.get_ref()
+        .is_some_and(|inner| inner.is::<BodyTooLarge>())
+}
+
+fn read_line<R: BufRead>(rdr: &mut R, budget: &mut usize) -> io::Result<String> {
+    let mut line = Vec::new();
+    (&mut *rdr)
+        .take(*budget as u64)
+        .read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        return Err(invalid_data("header too large or connection closed"));
+    }
+    *budget -= line.len();
+    let line = String::from_utf8(line).map_err(|_| invalid_data("header is not UTF-8"))?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+/// Reads the final head of a response, skipping interim `1xx` responses.
+///
+/// `101 Switching Protocols` is final but cannot be proxied as the proxy
+/// only speaks HTTP.
+fn read_response_head<R: BufRead>(rdr: &mut R) -> io::Result<Head> {
+    loop {
+        let head = read_head(rdr)?;
+        match head.status() {
+            Some(101) => return Err(invalid_data("protocol upgrades are not supported")),
+            Some(100..=199) => continue,
+            _ => return Ok(head),
+        }
+    }
+}
+
+fn read_head<R: BufRead>(rdr: &mut R) -> io::Result<Head> {
+    let mut budget = MAX_HEAD_BYTES;
+    let start_line = read_line(rdr, &mut budget)?;
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(rdr, &mut budget)?;
+        if line.is_empty() {
+            break;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid_data("malformed header"))?;
+        headers.push((key.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(Head {
+        start_line,
+        headers,
+    })
+}
+
+fn read_limited<R: Read>(rdr: &mut R, len: u64, max: u64, body: &mut Vec<u8>) -> io::Result<()> {
+    if len > max.saturating_sub(body.len() as u64) {
+        return Err(body_too_large());
+    }
+    let read = rdr.take(len).read_to_end(body)?;
+    if (read as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "body ended early",
+        ));
+    }
+    Ok(())
+}
+
+fn read_body<R: BufRead>(
+    rdr: &mut R,
+    head: &Head,
+    max: u64,
+    until_eof: bool,
+) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    if head.is_chunked() {
+        loop {
+            let mut budget = MAX_HEAD_BYTES;
+            let line = read_line(rdr, &mut budget)?;
+            let size = line.split(';').next().unwrap_or("").trim();
+            let size =
+                u64::from_str_radix(size, 16).map_err(|_| invalid_data("invalid chunk size"))?;
+            if size == 0 {
+                // skip trailers
+                while !read_line(rdr, &mut budget)?.is_empty() {}
+                break;
+            }
+            read_limited(rdr, size, max, &mut body)?;
+            read_line(rdr, &mut budget)?;
+        }
+    } else if let Some(len) = head.content_length()? {
+        read_limited(rdr, len, max, &mut body)?;
+    } else if until_eof {
+        rdr.take(max.saturating_add(1)).read_to_end(&mut body)?;
+        if body.len() as u64 > max {
+            return Err(body_too_large());
+        }
+    }
+    Ok(body)
+}
+
+/// Translates a JSON body in place.
+///
+/// Compressed bodies are passed through untouched.
+fn sanitize(head: &Head, body: &mut [u8], config: &Config, metrics: &Metrics) -> io::Result<()> {
+    if !head.is_json() || head.is_encoded() {
+        return Ok(());
+    }
+    let stats = translate_slice_with_options(body, &config.options)?;
+    metrics.record(stats);
+    Ok(())
+}
+
+fn respond<W: Write>(w: &mut W, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        w,
+        "HTTP/1.1 {}\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    w.write_all(body)?;
+    w.flush()
+}
+
+fn connect_upstream(config: &Config) -> io::Result<TcpStream> {
+    let upstream = TcpStream::connect(&config.upstream)?;
+    upstream.set_read_timeout(Some(config.timeout))?;
+    upstream.set_write_timeout(Some(config.timeout))?;
+    Ok(upstream)
+}
+
+fn handle(client: TcpStream, config: &Config, metrics: &Metrics) -> io::Result<()> {
+    client.set_read_timeout(Some(config.timeout))?;
+    client.set_write_timeout(Some(config.timeout))?;
+    let mut rdr = BufReader::new(client.try_clone()?);
+    let mut client = client;
+    let head = read_head(&mut rdr)?;
+    metrics.requests.fetch_add(1, Ordering::Relaxed);
+
+    if head.start_line.starts_with("GET /__pjra/metrics ") {
+        let body = metrics.render();
+        return respond(
+            &mut client,
+            "200 OK",
+            "text/plain; version=0.0.4",
+            body.as_bytes(),
+        );
+    }
+
+    let mut body = match read_body(&mut rdr, &head, config.max_body_bytes, false) {
+        Ok(body) => body,
+        Err(err) => {
+            metrics.rejected.fetch_add(1, Ordering::Relaxed);
+            let msg = format!("{}\n", err);
+            let status = if is_body_too_large(&err) {
+                "413 Payload Too Large"
+            } else {
+                "400 Bad Request"
+            };
+            return respond(&mut client, status, "text/plain", msg.as_bytes());
+        }
+    };
+    if let Err(err) = sanitize(&head, &mut body, config, metrics) {
+        metrics.rejected.fetch_add(1, Ordering::Relaxed);
+        let msg = format!("{}\n", err);
+        return respond(
+            &mut client,
+            "422 Unprocessable Entity",
+            "text/plain",
+            msg.as_bytes(),
+        );
+    }
+
+    let response = connect_upstream(config).and_then(|mut upstream| {
+        head.write_to(&mut upstream, Some(body.len()))?;
+        upstream.write_all(&body)?;
+        upstream.flush()?;
+        let mut rdr = BufReader::new(upstream);
+        let response = read_response_head(&mut rdr)?;
+        if !head.response_has_body(&response) {
+            return Ok((response, None));
+        }
+        let mut body = read_body(&mut rdr, &response, config.max_body_bytes, true)?;
+        sanitize(&response, &mut body, config, metrics)?;
+        Ok((response, Some(body)))
+    });
+
+    match response {
+        Ok((head, body)) => {
+            head.write_to(&mut client, body.as_ref().map(Vec::len))?;
+            if let Some(body) = body {
+                client.write_all(&body)?;
+            }
+            client.flush()
+        }
+        Err(err) => {
+            metrics.upstream_errors.fetch_add(1, Ordering::Relaxed);
+            let msg = format!("{}\n", err);
+            respond(&mut client, "502 Bad Gateway", "text/plain", msg.as_bytes())
+        }
+    }
+}
+
+fn parse_args() -> Result<Config, String> {
+    let mut config = Config {
+        listen: "127.0.0.1:8080".into(),
+        upstream: String::new(),
+        options: Options::new(),
+        max_body_bytes: 10 * 1024 * 1024,
+        workers: 64,
+        timeout: Duration::from_secs(30),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--listen" => config.listen = value()?,
+            "--upstream" => config.upstream = value()?,
+            "--max-replacements" => {
+                let max = value()?.parse().map_err(|_| "invalid --max-replacements")?;
+                config.options = config.options.max_replacements(max);
+            }
+            "--skip-prefix" => {
+                let max = value()?.parse().map_err(|_| "invalid --skip-prefix")?;
+                config.options = config.options.skip_prefix(max);
+            }
+            "--reject" => config.options = config.options.reject(true),
+            "--case-insensitive" => config.options = config.options.case_insensitive(true),
+            "--plus-infinity" => config.options = config.options.plus_infinity(true),
+            "--reject-binary" => config.options = config.options.reject_binary(true),
+            "--max-body-bytes" => {
+                config.max_body_bytes = value()?.parse().map_err(|_| "invalid --max-body-bytes")?
+            }
+            "--workers" => match value()?.parse() {
+                Ok(workers) if workers > 0 => config.workers = workers,
+                _ => return Err("invalid --workers".into()),
+            },
+            "--timeout-secs" => {
+                config.timeout =
+                    Duration::from_secs(value()?.parse().map_err(|_| "invalid --timeout-secs")?)
+            }
+            "--help" | "-h" => {
+                return Err(
+                    "usage: pjra-proxy --upstream HOST:PORT [--listen ADDR:PORT] \
+                            [--max-replacements N] [--skip-prefix N] [--reject] \
+                            [--case-insensitive] [--plus-infinity] [--reject-binary] \
+                            [--max-body-bytes N] [--workers N] [--timeout-secs N]"
+                        .into(),
+                )
+            }
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+    if config.upstream.is_empty() {
+        return Err("--upstream is required".into());
+    }
+    Ok(config)
+}
+
+fn main() {
+    let config = match parse_args() {
+        Ok(config) => Arc::new(config),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+    let listener = match TcpListener::bind(&config.listen) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("could not listen on {}: {}", config.listen, err);
+            process::exit(1);
+        }
+    };
+    eprintln!("proxying {} -> {}", config.listen, config.upstream);
+    let metrics = Arc::new(Metrics::default());
+    let workers: Vec<_> = (0..config.workers)
+        .map(|_| {
+            let listener = listener.try_clone()?;
+            let config = config.clone();
+            let metrics = metrics.clone();
+            Ok(thread::spawn(move || {
+                for client in listener.incoming() {
+                    let client = match client {
+                        Ok(client) => client,
+                        Err(err) => {
+                            eprintln!("accept failed: {}", err);
+                            continue;
+                        }
+                    };
+                    if let Err(err) = handle(client, &config, &metrics) {
+                        eprintln!("request failed: {}", err);
+                    }
+                }
+            }))
+        })
+        .collect::<io::Result<_>>()
+        .unwrap_or_else(|err| {
+            eprintln!("could not start workers: {}", err);
+            process::exit(1);
+        });
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+#[cfg(test)]
+fn parse_head(text: &str) -> Head {
+    read_head(&mut text.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_read_head() {
+    let mut rdr = &b"POST /x HTTP/1.1\r\nHost: a\r\nContent-Type: application/json\r\n\r\n{}"[..];
+    let head = read_head(&mut rdr).unwrap();
+    assert_eq!(head.start_line, "POST /x HTTP/1.1");
+    assert_eq!(head.header("host"), Some("a"));
+    assert!(head.is_json());
+    assert_eq!(rdr, b"{}");
+
+    assert!(!head.is_encoded());
+    let head = parse_head("HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n");
+    assert!(head.is_encoded());
+    let head = parse_head("HTTP/1.1 200 OK\r\nContent-Encoding: identity\r\n\r\n");
+    assert!(!head.is_encoded());
+
+    assert!(read_head(&mut &b"GET / HTTP/1.1\r\nHost: a\r\n"[..]).is_err());
+    assert!(read_head(&mut &b"GET / HTTP/1.1\r\nbroken\r\n\r\n"[..]).is_err());
+    let huge = format!(
+        "GET / HTTP/1.1\r\nx: {}\r\n\r\n",
+        "a".repeat(MAX_HEAD_BYTES)
+    );
+    assert!(read_head(&mut huge.as_bytes()).is_err());
+}
+
+#[test]
+fn test_read_response_head() {
+    let mut rdr = &b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: x\r\n\r\n\
+                    HTTP/1.1 200 OK\r\n\r\n"[..];
+    assert_eq!(read_response_head(&mut rdr).unwrap().status(), Some(200));
+    let mut rdr = &b"HTTP/1.1 101 Switching Protocols\r\n\r\n"[..];
+    assert!(read_response_head(&mut rdr).is_err());
+}
+
+#[test]
+fn test_read_body() {
+    let head = parse_head("POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\n");
+    let mut rdr = &b"[NaN]rest"[..];
+    assert_eq!(read_body(&mut rdr, &head, 100, false).unwrap(), b"[NaN]");
+    assert_eq!(rdr, b"rest");
+    let err = read_body(&mut &b"[NaN]"[..], &head, 4, false).unwrap_err();
+    assert!(is_body_too_large(&err));
+    let err = read_body(&mut &b"[Na"[..], &head, 100, false).unwrap_err();
+    assert!(!is_body_too_large(&err));
+
+    let head = parse_head("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n");
+    let chunked = b"3;ext=1\r\n[Na\r\n2\r\nN]\r\n0\r\nTrailer: x\r\n\r\n";
+    assert_eq!(
+        read_body(&mut &chunked[..], &head, 100, false).unwrap(),
+        b"[NaN]"
+    );
+    let err = read_body(&mut &chunked[..], &head, 4, false).unwrap_err();
+    assert!(is_body_too_large(&err));
+    let err = read_body(&mut &b"zz\r\n"[..], &head, 100, false).unwrap_err();
+    assert!(!is_body_too_large(&err));
+
+    let head = parse_head("HTTP/1.1 200 OK\r\n\r\n");
+    assert_eq!(
+        read_body(&mut &b"[1]"[..], &head, 100, true).unwrap(),
+        b"[1]"
+    );
+    assert_eq!(read_body(&mut &b"[1]"[..], &head, 100, false).unwrap(), b"");
+    let err = read_body(&mut &b"[1]"[..], &head, 2, true).unwrap_err();
+    assert!(is_body_too_large(&err));
+
+    let head = parse_head("POST / HTTP/1.1\r\nContent-Length: x\r\n\r\n");
+    let err = read_body(&mut &b""[..], &head, 100, false).unwrap_err();
+    assert!(!is_body_too_large(&err));
+}
+
+#[test]
+fn test_response_has_body() {
+    let get = parse_head("GET / HTTP/1.1\r\n\r\n");
+    let head = parse_head("HEAD / HTTP/1.1\r\n\r\n");
+    let ok = parse_head("HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n");
+    assert!(get.response_has_body(&ok));
+    assert!(!head.response_has_body(&ok));
+    for status in &["204 No Content", "304 Not Modified", "102 Processing"] {
+        let response = parse_head(&format!("HTTP/1.1 {}\r\n\r\n", status));
+        assert!(!get.response_has_body(&response));
+    }
+
+    // the framing headers of a body-less response are kept
+    let mut out = Vec::new();
+    ok.write_to(&mut out, None).unwrap();
+    assert_eq!(
+        out,
+        b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\nconnection: close\r\n\r\n"
+    );
+    let mut out = Vec::new();
+    ok.write_to(&mut out, Some(3)).unwrap();
+    assert_eq!(
+        out,
+        b"HTTP/1.1 200 OK\r\ncontent-length: 3\r\nconnection: close\r\n\r\n"
+    );
+}
+
+#[test]
+fn test_sanitize() {
+    let config = Config {
+        listen: String::new(),
+        upstream: String::new(),
+        options: Options::new().reject(true),
+        max_body_bytes: 100,
+        workers: 1,
+        timeout: Duration::from_secs(1),
+    };
+    let metrics = Metrics::default();
+    let json = parse_head("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n");
+    assert!(sanitize(&json, &mut b"[NaN]".to_vec(), &config, &metrics).is_err());
+    let gzip = parse_head(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Encoding: gzip\r\n\r\n",
+    );
+    let mut body = b"[NaN]".to_vec();
+    sanitize(&gzip, &mut body, &config, &metrics).unwrap();
+    assert_eq!(body, b"[NaN]");
+}
@@ -0,0 +1,168 @@
+use serde_json::Value;
+
+use crate::de::from_slice_lossless;
+use crate::float::PyFloat;
+use crate::translate_slice;
+use crate::value::PyValue;
+
+pub(crate) fn parse_translated(bytes: &[u8]) -> serde_json::Result<Value> {
+    let mut buf = bytes.to_vec();
+    translate_slice(&mut buf[..]);
+    serde_json::from_slice(&buf)
+}
+
+fn write_canonical(value: &PyValue, out: &mut Vec<u8>) -> serde_json::Result<()> {
+    match *value {
+        PyValue::Array(ref items) => {
+            out.push(b'[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(b']');
+        }
+        PyValue::Object(ref map) => {
+            out.push(b'{');
+            for (idx, (key, value)) in map.iter().enumerate() {
+                if idx > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.push(b':');
+                write_canonical(value, out)?;
+            }
+            out.push(b'}');
+        }
+        // negative zero compares equal to zero, so it gets the same form
+        PyValue::Number(ref num) if num.as_f64() == Some(0.0) && num.is_f64() => {
+            out.extend_from_slice(b"0.0");
+        }
+        PyValue::NonFinite(value) => out.extend_from_slice(PyFloat(value).to_string().as_bytes()),
+        ref other => serde_json::to_writer(&mut *out, other)?,
+    }
+    Ok(())
+}
+
+/// Checks if two Python JSON documents are semantically equal.
+///
+/// Both documents are parsed without losing non-finite floats.  The
+/// resulting values are then compared structurally which means that
+/// formatting and key order do not matter.  `NaN` compares equal to `NaN`,
+/// but not to `0.0` or to the infinities.
+pub fn semantically_equal(a: &[u8], b: &[u8]) -> serde_json::Result<bool> {
+    Ok(cache_key(a)? == cache_key(b)?)
+}
+
+/// Translates a document in place and returns its canonical form.
+///
+/// The canonical form is minified Python JSON with object keys sorted that
+/// keeps `NaN`, `Infinity` and `-Infinity`.  It is taken from the document
+/// before the translation.  Documents that are `semantically_equal` have the
+/// same canonical form.
+pub fn canonicalize(bytes: &mut [u8]) -> serde_json::Result<Vec<u8>> {
+    let rv = canonical_form(bytes)?;
+    translate_slice(bytes);
+    Ok(rv)
+}
+
+fn canonical_form(bytes: &[u8]) -> serde_json::Result<Vec<u8>> {
+    let value: PyValue = from_slice_lossless(bytes)?;
+    let mut rv = Vec::with_capacity(bytes.len());
+    write_canonical(&value, &mut rv)?;
+    Ok(rv)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Translates a document in place and returns a stable hash of it.
+///
+/// The hash is the 64-bit FNV-1a hash of the canonical form of the document
+/// as returned by `canonicalize`.  It does not depend on the platform or the
+/// Rust version and can thus be persisted for caching and deduplication.
+pub fn structural_hash(bytes: &mut [u8]) -> serde_json::Result<u64> {
+    Ok(fnv1a(&canonicalize(bytes)?))
+}
+
+/// Returns a cache key for the body of a response.
+///
+/// The body is left untouched; the key is its canonical form as returned by
+/// `canonicalize`.  Bodies that only differ in formatting or key order map to
+/// the same key, which makes this useful for HTTP caches in front of Python
+/// services.  As the canonical form keeps non-finite floats a body with
+/// `NaN` gets a different key than one with `0.0`.
+pub fn cache_key(body: &[u8]) -> serde_json::Result<Vec<u8>> {
+    canonical_form(body)
+}
+
+/// Returns a weak entity tag for the body of a response.
+///
+/// The tag is the quoted hex form of the `structural_hash` of the body and
+/// can be sent as `ETag` header.  It is weak (`W/"..."`) as bodies that only
+/// differ in formatting or key order get the same tag.
+pub fn etag(body: &[u8]) -> serde_json::Result<String> {
+    Ok(format!("W/\"{:016x}\"", fnv1a(&canonical_form(body)?)))
+}
+
+#[test]
+fn test_semantically_equal() {
+    assert!(semantically_equal(
+        br#"{"a": NaN, "b": [1, Infinity]}"#,
+        br#"{"b":[1,Infinity],"a":NaN}"#
+    )
+    .unwrap());
+    assert!(!semantically_equal(br#"{"a": NaN}"#, br#"{"a": 1.0}"#).unwrap());
+    assert!(!semantically_equal(b"[NaN]", b"[0.0]").unwrap());
+    assert!(!semantically_equal(b"[Infinity]", b"[-Infinity]").unwrap());
+    assert!(semantically_equal(b"[NaN, -0.0]", b"[ NaN , 0.0 ]").unwrap());
+    assert!(semantically_equal(br#"[NaN"#, b"[]").is_err());
+}
+
+#[test]
+fn test_canonicalize() {
+    let mut json = br#"{"b": [1, -Infinity, "x"], "a": {"d": NaN, "c": null}}"#.to_vec();
+    assert_eq!(
+        String::from_utf8(canonicalize(&mut json).unwrap()).unwrap(),
+        r#"{"a":{"c":null,"d":NaN},"b":[1,-Infinity,"x"]}"#
+    );
+    assert_eq!(
+        &json[..],
+        br#"{"b": [1, -0.0     , "x"], "a": {"d": 0.0, "c": null}}"#
+    );
+}
+
+#[test]
+fn test_structural_hash() {
+    let mut a = br#"{"a": NaN, "b": [1, Infinity]}"#.to_vec();
+    let mut b = br#"{"b":[1,Infinity],"a":NaN}"#.to_vec();
+    let mut c = br#"{"b":[2,Infinity],"a":NaN}"#.to_vec();
+    let mut d = br#"{"b":[1,Infinity],"a":0.0}"#.to_vec();
+    let hash = structural_hash(&mut a).unwrap();
+    assert_eq!(hash, structural_hash(&mut b).unwrap());
+    assert_ne!(hash, structural_hash(&mut c).unwrap());
+    assert_ne!(hash, structural_hash(&mut d).unwrap());
+    assert_eq!(
+        structural_hash(&mut b"[]".to_vec()).unwrap(),
+        0x0961_2b07_b5ec_b5a5
+    );
+}
+
+#[test]
+fn test_cache_key() {
+    let body = br#"{"b": [1, NaN], "a": null}"#;
+    assert_eq!(cache_key(body).unwrap(), br#"{"a":null,"b":[1,NaN]}"#);
+    assert_eq!(
+        cache_key(br#"{"a":null,"b":[1,NaN]}"#).unwrap(),
+        cache_key(body).unwrap()
+    );
+    assert_ne!(cache_key(b"[NaN]").unwrap(), cache_key(b"[0.0]").unwrap());
+    assert_ne!(etag(b"[NaN]").unwrap(), etag(b"[0.0]").unwrap());
+    assert_eq!(etag(b"[NaN]").unwrap(), etag(b"[ NaN ]").unwrap());
+    assert_eq!(etag(b"[]").unwrap(), "W/\"09612b07b5ecb5a5\"");
+    assert!(cache_key(b"[NaN").is_err());
+}
@@ -0,0 +1,176 @@
+use std::io;
+use std::marker::PhantomData;
+
+use bytes::BytesMut;
+use serde_self::de;
+use tokio_util::codec::Decoder;
+
+use crate::ndjson::{LineEndingStats, LineScanner, LineSeparators};
+use crate::translate_slice;
+
+/// A tokio codec that decodes newline-delimited Python JSON.
+///
+/// Every line is treated as an independent document: the translation state
+/// is reset at the start of each line so that a malformed record cannot
+/// affect the following ones.  Blank lines are skipped.  By default records
+/// are separated by `\n` and `\r\n`, other line endings can be enabled with
+/// `line_separators`.
+pub struct PythonJsonLinesDecoder<T> {
+    scanner: LineScanner,
+    separators: LineSeparators,
+    line_endings: LineEndingStats,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PythonJsonLinesDecoder<T> {
+    /// Creates a new decoder.
+    pub fn new() -> PythonJsonLinesDecoder<T> {
+        PythonJsonLinesDecoder {
+            scanner: LineScanner::default(),
+            separators: LineSeparators::default(),
+            line_endings: LineEndingStats::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Configures which line endings separate records.
+    pub fn line_separators(mut self, separators: LineSeparators) -> PythonJsonLinesDecoder<T> {
+        self.separators = separators;
+        self
+    }
+
+    /// Returns the counts of the line endings seen so far.
+    ///
+    /// This can be used to detect streams with mixed line endings.
+    pub fn line_endings(&self) -> LineEndingStats {
+        self.line_endings
+    }
+
+    fn next_line(&mut self, src: &mut BytesMut, eof: bool) -> Option<BytesMut> {
+        let line = self.scanner.next_line(src, self.separators, eof)?;
+        self.line_endings.record(line.ending);
+        let mut rv = src.split_to(line.len + line.ending_len);
+        rv.truncate(line.len);
+        Some(rv)
+    }
+
+    fn decode_line(&mut self, line: &mut [u8]) -> io::Result<Option<T>>
+    where
+        T: de::DeserializeOwned,
+    {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+        translate_slice(line);
+        Ok(Some(serde_json::from_slice(line)?))
+    }
+}
+
+impl<T> Default for PythonJsonLinesDecoder<T> {
+    fn default() -> PythonJsonLinesDecoder<T> {
+        PythonJsonLinesDecoder::new()
+    }
+}
+
+impl<T> Clone for PythonJsonLinesDecoder<T> {
+    fn clone(&self) -> PythonJsonLinesDecoder<T> {
+        PythonJsonLinesDecoder {
+            scanner: self.scanner.clone(),
+            separators: self.separators,
+            line_endings: self.line_endings,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for PythonJsonLinesDecoder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("PythonJsonLinesDecoder")
+            .field("separators", &self.separators)
+            .field("line_endings", &self.line_endings)
+            .finish()
+    }
+}
+
+impl<T: de::DeserializeOwned> Decoder for PythonJsonLinesDecoder<T> {
+    type Item = T;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<T>> {
+        while let Some(mut line) = self.next_line(src, false) {
+            if let Some(item) = self.decode_line(&mut line)? {
+                return Ok(Some(item));
+            }
+        }
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<T>> {
+        while let Some(mut line) = self.next_line(src, true) {
+            if let Some(item) = self.decode_line(&mut line)? {
+                return Ok(Some(item));
+            }
+        }
+        let mut line = src.split();
+        self.scanner = LineScanner::default();
+        self.decode_line(&mut line)
+    }
+}
+
+#[test]
+fn test_decode_lines() {
+    let mut decoder = PythonJsonLinesDecoder::<serde_json::Value>::new();
+    let mut buf = BytesMut::from(&b"[NaN, 1]\r\n\n{\"a\": -Infin"[..]);
+    assert_eq!(
+        decoder.decode(&mut buf).unwrap(),
+        Some(serde_json::json!([0.0, 1]))
+    );
+    assert_eq!(decoder.decode(&mut buf).unwrap(), None);
+    buf.extend_from_slice(b"ity}\n[Infinity]");
+    assert_eq!(
+        decoder.decode(&mut buf).unwrap(),
+        Some(serde_json::json!({"a": -0.0}))
+    );
+    assert_eq!(decoder.decode(&mut buf).unwrap(), None);
+    assert_eq!(
+        decoder.decode_eof(&mut buf).unwrap(),
+        Some(serde_json::json!([0.0]))
+    );
+    assert_eq!(decoder.decode_eof(&mut buf).unwrap(), None);
+}
+
+#[test]
+fn test_decode_lines_resets_state() {
+    let mut decoder = PythonJsonLinesDecoder::<serde_json::Value>::new();
+    let mut buf = BytesMut::from(&b"\"unterminated\n[NaN]\n"[..]);
+    assert!(decoder.decode(&mut buf).is_err());
+    assert_eq!(
+        decoder.decode(&mut buf).unwrap(),
+        Some(serde_json::json!([0.0]))
+    );
+}
+
+#[test]
+fn test_decode_line_separators() {
+    let mut decoder =
+        PythonJsonLinesDecoder::<serde_json::Value>::new().line_separators(LineSeparators::all());
+    let mut buf = BytesMut::from("[NaN]\r[\"\u{2028}\"]\u{2029}[1]\r\n[2]\r".as_bytes());
+    let mut rv = Vec::new();
+    while let Some(item) = decoder.decode_eof(&mut buf).unwrap() {
+        rv.push(item);
+    }
+    assert_eq!(
+        rv,
+        vec![
+            serde_json::json!([0.0]),
+            serde_json::json!(["\u{2028}"]),
+            serde_json::json!([1]),
+            serde_json::json!([2]),
+        ]
+    );
+    let stats = decoder.line_endings();
+    assert_eq!(stats.cr, 2);
+    assert_eq!(stats.paragraph_separator, 1);
+    assert_eq!(stats.crlf, 1);
+    assert!(stats.is_mixed());
+}
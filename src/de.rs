@@ -0,0 +1,641 @@
+use std::io;
+use std::str;
+
+use serde_self::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde_self::forward_to_deserialize_any;
+
+const MAX_DEPTH: usize = 128;
+
+/// A JSON deserializer that understands the Python tokens natively.
+///
+/// Instead of rewriting `NaN` and `Infinity` into `0.0` this parses them
+/// into `f64::NAN`, `f64::INFINITY` and `f64::NEG_INFINITY` so that the
+/// values survive deserialization.  Apart from the additional tokens it
+/// accepts the same documents as `serde_json`.
+///
+/// ```
+/// # use python_json_read_adapter::from_slice_lossless;
+/// let rv: Vec<f64> = from_slice_lossless(b"[1.5, NaN, -Infinity]").unwrap();
+/// assert_eq!(rv[0], 1.5);
+/// assert!(rv[1].is_nan());
+/// assert_eq!(rv[2], f64::NEG_INFINITY);
+/// ```
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+    pos: usize,
+    depth: usize,
+    scratch: Vec<u8>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer for the given bytes.
+    pub fn from_slice(input: &'de [u8]) -> Deserializer<'de> {
+        Deserializer {
+            input,
+            pos: 0,
+            depth: MAX_DEPTH,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Creates a deserializer for the given string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Deserializer<'de> {
+        Deserializer::from_slice(input.as_bytes())
+    }
+
+    /// Checks that only whitespace is left in the input.
+    pub fn end(&mut self) -> serde_json::Result<()> {
+        match self.peek() {
+            Some(_) => Err(self.error("trailing characters")),
+            None => Ok(()),
+        }
+    }
+
+    fn error(&self, msg: &str) -> serde_json::Error {
+        de::Error::custom(format_args!("{} at offset {}", msg, self.pos))
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(&b) = self.input.get(self.pos) {
+            if !matches!(b, b' ' | b'\t' | b'\n' | b'\r') {
+                return Some(b);
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn expect(&mut self, byte: u8, msg: &str) -> serde_json::Result<()> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else if self.peek().is_none() {
+            Err(self.error("EOF while parsing a value"))
+        } else {
+            Err(self.error(msg))
+        }
+    }
+
+    fn expect_ident(&mut self, ident: &[u8]) -> serde_json::Result<()> {
+        if self.input[self.pos..].starts_with(ident) {
+            self.pos += ident.len();
+            Ok(())
+        } else {
+            Err(self.error("expected value"))
+        }
+    }
+
+    fn enter(&mut self) -> serde_json::Result<()> {
+        self.depth = self
+            .depth
+            .checked_sub(1)
+            .ok_or_else(|| self.error("recursion limit exceeded"))?;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth += 1;
+    }
+
+    fn parse_number<V: Visitor<'de>>(&mut self, visitor: V) -> serde_json::Result<V::Value> {
+        let start = self.pos;
+        let negative = self.input.get(self.pos) == Some(&b'-');
+        if negative {
+            self.pos += 1;
+        }
+        match self.input.get(self.pos) {
+            Some(b'I') => {
+                self.expect_ident(b"Infinity")?;
+                return visitor.visit_f64(if negative {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                });
+            }
+            Some(b'N') => {
+                self.expect_ident(b"NaN")?;
+                // C libraries write the sign of negative NaN payloads
+                return visitor.visit_f64(if negative { -f64::NAN } else { f64::NAN });
+            }
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => {
+                self.skip_digits();
+            }
+            _ => return Err(self.error("invalid number")),
+        }
+        let mut is_float = false;
+        if self.input.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            is_float = true;
+            if self.skip_digits() == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        if matches!(self.input.get(self.pos), Some(b'e') | Some(b'E')) {
+            self.pos += 1;
+            is_float = true;
+            if matches!(self.input.get(self.pos), Some(b'+') | Some(b'-')) {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        // the number only consists of ASCII bytes
+        let text = str::from_utf8(&self.input[start..self.pos]).unwrap_or_default();
+        if !is_float {
+            if negative {
+                if let Ok(value) = text.parse::<i64>() {
+                    return visitor.visit_i64(value);
+                }
+            } else if let Ok(value) = text.parse::<u64>() {
+                return visitor.visit_u64(value);
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(value) if value.is_finite() => visitor.visit_f64(value),
+            _ => Err(self.error("number out of range")),
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let count = self.input[self.pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        self.pos += count;
+        count
+    }
+
+    /// Parses a string, borrowing from the input if it has no escapes.
+    fn parse_str(&mut self) -> serde_json::Result<Reference<'de, '_>> {
+        self.expect(b'"', "expected string")?;
+        let start = self.pos;
+        self.scratch.clear();
+        let mut chunk_start = start;
+        loop {
+            let byte = match self.input.get(self.pos) {
+                Some(&byte) => byte,
+                None => return Err(self.error("EOF while parsing a string")),
+            };
+            match byte {
+                b'"' => {
+                    let input = self.input;
+                    let chunk = &input[chunk_start..self.pos];
+                    self.pos += 1;
+                    if chunk_start == start {
+                        return self.to_str(chunk).map(Reference::Borrowed);
+                    }
+                    self.scratch.extend_from_slice(chunk);
+                    let scratch = &self.scratch;
+                    return match str::from_utf8(scratch) {
+                        Ok(s) => Ok(Reference::Copied(s)),
+                        Err(_) => Err(self.error("invalid unicode in string")),
+                    };
+                }
+                b'\\' => {
+                    let chunk = &self.input[chunk_start..self.pos];
+                    self.scratch.extend_from_slice(chunk);
+                    self.pos += 1;
+                    self.parse_escape()?;
+                    chunk_start = self.pos;
+                }
+                0x00..=0x1f => return Err(self.error("control character in string")),
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn to_str(&self, bytes: &'de [u8]) -> serde_json::Result<&'de str> {
+        str::from_utf8(bytes).map_err(|_| self.error("invalid unicode in string"))
+    }
+
+    fn parse_escape(&mut self) -> serde_json::Result<()> {
+        let byte = match self.input.get(self.pos) {
+            Some(&byte) => byte,
+            None => return Err(self.error("EOF while parsing a string")),
+        };
+        self.pos += 1;
+        let decoded = match byte {
+            b'"' => b'"',
+            b'\\' => b'\\',
+            b'/' => b'/',
+            b'b' => b'\x08',
+            b'f' => b'\x0c',
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'u' => {
+                let c = match self.parse_hex4()? {
+                    high @ 0xd800..=0xdbff => {
+                        if !self.input[self.pos..].starts_with(b"\\u") {
+                            return Err(self.error("lone leading surrogate in hex escape"));
+                        }
+                        self.pos += 2;
+                        let low = self.parse_hex4()?;
+                        if !(0xdc00..=0xdfff).contains(&low) {
+                            return Err(self.error("lone leading surrogate in hex escape"));
+                        }
+                        let c = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                        char::from_u32(c)
+                    }
+                    code => char::from_u32(code),
+                };
+                let c = c.ok_or_else(|| self.error("invalid unicode code point"))?;
+                self.scratch
+                    .extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                return Ok(());
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.scratch.push(decoded);
+        Ok(())
+    }
+
+    fn parse_hex4(&mut self) -> serde_json::Result<u32> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .and_then(|digits| str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+}
+
+enum Reference<'de, 's> {
+    Borrowed(&'de str),
+    Copied(&'s str),
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> serde_json::Result<V::Value> {
+        match self.peek() {
+            None => Err(self.error("EOF while parsing a value")),
+            Some(b'n') => {
+                self.expect_ident(b"null")?;
+                visitor.visit_unit()
+            }
+            Some(b't') => {
+                self.expect_ident(b"true")?;
+                visitor.visit_bool(true)
+            }
+            Some(b'f') => {
+                self.expect_ident(b"false")?;
+                visitor.visit_bool(false)
+            }
+            Some(b'"') => match self.parse_str()? {
+                Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+                Reference::Copied(s) => visitor.visit_str(s),
+            },
+            Some(b'[') => {
+                self.pos += 1;
+                self.enter()?;
+                let rv = visitor.visit_seq(SeqAccess {
+                    de: self,
+                    first: true,
+                });
+                self.leave();
+                let rv = rv?;
+                self.expect(b']', "trailing elements in array")?;
+                Ok(rv)
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                self.enter()?;
+                let rv = visitor.visit_map(MapAccess {
+                    de: self,
+                    first: true,
+                });
+                self.leave();
+                let rv = rv?;
+                self.expect(b'}', "trailing entries in object")?;
+                Ok(rv)
+            }
+            Some(_) => self.parse_number(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> serde_json::Result<V::Value> {
+        if self.peek() == Some(b'n') {
+            self.expect_ident(b"null")?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        match self.peek() {
+            Some(b'"') => {
+                let variant = match self.parse_str()? {
+                    Reference::Borrowed(s) => s.to_string(),
+                    Reference::Copied(s) => s.to_string(),
+                };
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                self.enter()?;
+                let rv = visitor.visit_enum(VariantAccess { de: self });
+                self.leave();
+                let rv = rv?;
+                self.expect(b'}', "expected `}` after enum variant")?;
+                Ok(rv)
+            }
+            _ => Err(self.error("expected enum")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct SeqAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
+    type Error = serde_json::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> serde_json::Result<Option<T::Value>> {
+        if self.de.peek() == Some(b']') {
+            return Ok(None);
+        }
+        if !std::mem::replace(&mut self.first, false) {
+            self.de.expect(b',', "expected `,` or `]`")?;
+            if self.de.peek() == Some(b']') {
+                return Err(self.de.error("trailing comma"));
+            }
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct MapAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for MapAccess<'a, 'de> {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> serde_json::Result<Option<K::Value>> {
+        if self.de.peek() == Some(b'}') {
+            return Ok(None);
+        }
+        if !std::mem::replace(&mut self.first, false) {
+            self.de.expect(b',', "expected `,` or `}`")?;
+            if self.de.peek() == Some(b'}') {
+                return Err(self.de.error("trailing comma"));
+            }
+        }
+        if self.de.peek() != Some(b'"') {
+            return Err(self.de.error("key must be a string"));
+        }
+        seed.deserialize(MapKey { de: &mut *self.de }).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> serde_json::Result<V::Value> {
+        self.de.expect(b':', "expected `:`")?;
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// Deserializes object keys, parsing numbers out of the key if needed.
+struct MapKey<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+macro_rules! deserialize_key_number {
+    ($($method:ident => $visit:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> serde_json::Result<V::Value> {
+                let rv = match self.de.parse_str()? {
+                    Reference::Borrowed(s) => s.parse(),
+                    Reference::Copied(s) => s.parse(),
+                };
+                match rv {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(self.de.error("invalid number in key")),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for MapKey<'a, 'de> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> serde_json::Result<V::Value> {
+        match self.de.parse_str()? {
+            Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Reference::Copied(s) => visitor.visit_str(s),
+        }
+    }
+
+    deserialize_key_number! {
+        deserialize_i8 => visit_i8;
+        deserialize_i16 => visit_i16;
+        deserialize_i32 => visit_i32;
+        deserialize_i64 => visit_i64;
+        deserialize_u8 => visit_u8;
+        deserialize_u16 => visit_u16;
+        deserialize_u32 => visit_u32;
+        deserialize_u64 => visit_u64;
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        de::Deserializer::deserialize_enum(&mut *self.de, name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct VariantAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for VariantAccess<'a, 'de> {
+    type Error = serde_json::Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> serde_json::Result<(V::Value, Self)> {
+        let variant = seed.deserialize(MapKey { de: &mut *self.de })?;
+        self.de.expect(b':', "expected `:`")?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for VariantAccess<'a, 'de> {
+    type Error = serde_json::Error;
+
+    fn unit_variant(self) -> serde_json::Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> serde_json::Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// Deserialize an instance of type `T` from bytes of Python JSON.
+///
+/// Unlike `from_slice` this does not rewrite the input but parses `NaN`,
+/// `Infinity` and `-Infinity` into the corresponding float values.
+pub fn from_slice_lossless<'a, T>(v: &'a [u8]) -> serde_json::Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::from_slice(v);
+    let rv = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(rv)
+}
+
+/// Deserialize an instance of type `T` from a string of Python JSON.
+///
+/// This works like `from_slice_lossless` but for strings.
+pub fn from_str_lossless<'a, T>(v: &'a str) -> serde_json::Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    from_slice_lossless(v.as_bytes())
+}
+
+/// Deserialize an instance of type `T` from an IO stream of Python JSON.
+///
+/// This works like `from_slice_lossless` but reads the whole stream into
+/// memory first.
+pub fn from_reader_lossless<R, T>(mut rdr: R) -> serde_json::Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut buf = Vec::new();
+    rdr.read_to_end(&mut buf).map_err(serde_json::Error::io)?;
+    from_slice_lossless(&buf)
+}
+
+#[test]
+fn test_lossless_values() {
+    let rv: serde_json::Value =
+        from_str_lossless(r#"{"a": [1, -2, 3.5e1, true, null], "b\n": "xé😀"}"#).unwrap();
+    assert_eq!(
+        rv,
+        serde_json::json!({"a": [1, -2, 35.0, true, null], "b\n": "xé😀"})
+    );
+
+    let rv: std::collections::BTreeMap<String, f64> =
+        from_slice_lossless(br#"{"nan": NaN, "inf": Infinity, "ninf": -Infinity, "x": 1}"#)
+            .unwrap();
+    assert!(rv["nan"].is_nan());
+    assert_eq!(rv["inf"], f64::INFINITY);
+    assert_eq!(rv["ninf"], f64::NEG_INFINITY);
+    assert_eq!(rv["x"], 1.0);
+
+    let rv: f64 = from_slice_lossless(b"-NaN").unwrap();
+    assert!(rv.is_nan() && rv.is_sign_negative());
+}
+
+#[test]
+fn test_lossless_borrowed_and_keys() {
+    let rv: std::collections::BTreeMap<u32, (&str, Option<f32>)> =
+        from_slice_lossless(br#"{"1": ["a", null], "2": ["b", Infinity]}"#).unwrap();
+    assert_eq!(rv[&1], ("a", None));
+    assert_eq!(rv[&2], ("b", Some(f32::INFINITY)));
+}
+
+#[test]
+fn test_lossless_errors() {
+    for doc in &[
+        &b"[1,]"[..],
+        b"[1 2]",
+        b"{\"a\" 1}",
+        b"{1: 2}",
+        b"01",
+        b"--NaN",
+        b"Infin",
+        b"\"\x01\"",
+        b"[1] x",
+        b"[",
+        b"1e999",
+    ] {
+        assert!(
+            from_slice_lossless::<serde_json::Value>(doc).is_err(),
+            "{:?}",
+            String::from_utf8_lossy(doc)
+        );
+    }
+    let deep = "[".repeat(200) + &"]".repeat(200);
+    assert!(from_str_lossless::<serde_json::Value>(&deep).is_err());
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+
+use crate::de::from_slice_lossless;
+use crate::value::PyValue;
+
+pub(crate) fn push_pointer_segment(path: &str, segment: &str) -> String {
+    let mut rv = String::with_capacity(path.len() + segment.len() + 1);
+    rv.push_str(path);
+    rv.push('/');
+    for c in segment.chars() {
+        match c {
+            '~' => rv.push_str("~0"),
+            '/' => rv.push_str("~1"),
+            c => rv.push(c),
+        }
+    }
+    rv
+}
+
+fn diff_values(a: &PyValue, b: &PyValue, path: &str, rv: &mut Vec<String>) {
+    match (a, b) {
+        (PyValue::Object(a), PyValue::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let child = push_pointer_segment(path, key);
+                match (a.get(key.as_str()), b.get(key.as_str())) {
+                    (Some(a), Some(b)) => diff_values(a, b, &child, rv),
+                    _ => rv.push(child),
+                }
+            }
+        }
+        (PyValue::Array(a), PyValue::Array(b)) => {
+            for idx in 0..a.len().max(b.len()) {
+                let child = push_pointer_segment(path, &idx.to_string());
+                match (a.get(idx), b.get(idx)) {
+                    (Some(a), Some(b)) => diff_values(a, b, &child, rv),
+                    _ => rv.push(child),
+                }
+            }
+        }
+        // `NaN` is not equal to itself but the documents agree
+        (PyValue::NonFinite(a), PyValue::NonFinite(b)) if a.is_nan() && b.is_nan() => {}
+        (a, b) => {
+            if a != b {
+                rv.push(path.to_string());
+            }
+        }
+    }
+}
+
+/// Returns the JSON pointers of all values that differ between two documents.
+///
+/// Both documents are parsed with `from_slice_lossless` so that `NaN` and
+/// `Infinity` are told apart from `0.0`.  The paths are the most specific
+/// ones possible: if only a single value inside a nested object changed, only
+/// the path to that value is reported.  Keys that only exist in one of the
+/// documents and array items beyond the end of the shorter array are reported
+/// individually.  If the root values differ in type the empty pointer is
+/// returned which refers to the whole document.
+pub fn changed_paths(a: &[u8], b: &[u8]) -> serde_json::Result<Vec<String>> {
+    let mut rv = Vec::new();
+    let a: PyValue = from_slice_lossless(a)?;
+    let b: PyValue = from_slice_lossless(b)?;
+    diff_values(&a, &b, "", &mut rv);
+    Ok(rv)
+}
+
+#[test]
+fn test_changed_paths() {
+    let a = br#"{"a": {"b": NaN, "c": 1}, "d": [1, 2, 3], "a/b": 1, "x": true}"#;
+    let b = br#"{"a": {"b": 0.0, "c": 2}, "d": [1, 4], "a/b": 2, "y": true}"#;
+    assert_eq!(
+        changed_paths(a, b).unwrap(),
+        vec!["/a/b", "/a/c", "/a~1b", "/d/1", "/d/2", "/x", "/y"]
+    );
+    assert!(changed_paths(a, a).unwrap().is_empty());
+    assert_eq!(
+        changed_paths(b"[NaN, Infinity]", b"[NaN, -Infinity]").unwrap(),
+        vec!["/1"]
+    );
+    assert_eq!(changed_paths(b"[]", b"{}").unwrap(), vec![""]);
+}
@@ -0,0 +1,234 @@
+use std::ops::Range;
+
+use crate::error::{Error, ErrorKind};
+use crate::lexer::{unescape, PyJsonLexer, Token};
+
+const MAX_DEPTH: usize = 128;
+
+/// Controls what `resolve_duplicate_keys` does with duplicate object keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DuplicateKeyPolicy {
+    /// Fails with `ErrorKind::DuplicateKey` at the first duplicate key.
+    Error,
+    /// Keeps the first entry and removes all later ones.
+    KeepFirst,
+    /// Keeps the value of the last entry at the position of the first one,
+    /// which is what Python's `json` module does.
+    KeepLast,
+}
+
+struct Member {
+    key: String,
+    // the separator in front of the member
+    gap: Range<usize>,
+    text: Vec<u8>,
+}
+
+struct Resolver<'a> {
+    policy: DuplicateKeyPolicy,
+    input: &'a [u8],
+    lexer: PyJsonLexer<'a>,
+    depth: usize,
+    out: Vec<u8>,
+}
+
+impl<'a> Resolver<'a> {
+    fn next(&mut self) -> Result<(Token<'a>, Range<usize>), Error> {
+        match self.lexer.next() {
+            Some(rv) => rv,
+            None => Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                self.input.len() as u64,
+            )),
+        }
+    }
+
+    /// Writes the value that starts with `token` and returns its end.
+    fn value(&mut self, token: Token<'a>, span: Range<usize>) -> Result<usize, Error> {
+        match token {
+            Token::BeginObject => self.object(span),
+            Token::BeginArray => self.array(span),
+            Token::Null
+            | Token::Bool(_)
+            | Token::Number(_)
+            | Token::BigInt(_)
+            | Token::NonFinite(_)
+            | Token::String(_) => {
+                self.out.extend_from_slice(&self.input[span.clone()]);
+                Ok(span.end)
+            }
+            _ => Err(Error::new(ErrorKind::InvalidToken, span.start as u64)),
+        }
+    }
+
+    fn enter(&mut self, span: &Range<usize>) -> Result<(), Error> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(Error::new(ErrorKind::LimitExceeded, span.start as u64));
+        }
+        Ok(())
+    }
+
+    fn array(&mut self, span: Range<usize>) -> Result<usize, Error> {
+        self.enter(&span)?;
+        self.out.push(b'[');
+        let mut end = span.end;
+        let (mut token, mut span) = self.next()?;
+        while token != Token::EndArray {
+            self.out.extend_from_slice(&self.input[end..span.start]);
+            end = self.value(token, span)?;
+            let (next, next_span) = self.next()?;
+            match next {
+                Token::Comma => {
+                    (token, span) = self.next()?;
+                    if token == Token::EndArray {
+                        return Err(Error::new(ErrorKind::InvalidToken, span.start as u64));
+                    }
+                }
+                Token::EndArray => (token, span) = (next, next_span),
+                _ => return Err(Error::new(ErrorKind::InvalidToken, next_span.start as u64)),
+            }
+        }
+        self.out.extend_from_slice(&self.input[end..span.end]);
+        self.depth -= 1;
+        Ok(span.end)
+    }
+
+    fn object(&mut self, span: Range<usize>) -> Result<usize, Error> {
+        self.enter(&span)?;
+        let mut members: Vec<Member> = Vec::new();
+        let mut end = span.end;
+        let mut first = true;
+        loop {
+            let (token, key_span) = self.next()?;
+            let raw = match token {
+                Token::EndObject if first => {
+                    self.out
+                        .extend_from_slice(&self.input[span.start..key_span.end]);
+                    self.depth -= 1;
+                    return Ok(key_span.end);
+                }
+                Token::String(raw) => raw,
+                _ => return Err(Error::new(ErrorKind::InvalidToken, key_span.start as u64)),
+            };
+            first = false;
+            let key = unescape(raw, key_span.start + 1)?.into_owned();
+            let (colon, colon_span) = self.next()?;
+            if colon != Token::Colon {
+                return Err(Error::new(ErrorKind::InvalidToken, colon_span.start as u64));
+            }
+            let (token, value_span) = self.next()?;
+            let outer = std::mem::take(&mut self.out);
+            self.out
+                .extend_from_slice(&self.input[key_span.start..value_span.start]);
+            let value_end = self.value(token, value_span)?;
+            let text = std::mem::replace(&mut self.out, outer);
+            let gap = end..key_span.start;
+            end = value_end;
+            match members.iter().position(|member| member.key == key) {
+                None => members.push(Member { key, gap, text }),
+                Some(_) if self.policy == DuplicateKeyPolicy::Error => {
+                    return Err(Error::new(ErrorKind::DuplicateKey, key_span.start as u64));
+                }
+                Some(idx) => {
+                    if self.policy == DuplicateKeyPolicy::KeepLast {
+                        members[idx].text = text;
+                    }
+                }
+            }
+            let (token, sep_span) = self.next()?;
+            match token {
+                Token::Comma => {}
+                Token::EndObject => {
+                    self.out.push(b'{');
+                    for member in &members {
+                        self.out.extend_from_slice(&self.input[member.gap.clone()]);
+                        self.out.extend_from_slice(&member.text);
+                    }
+                    self.out.extend_from_slice(&self.input[end..sep_span.end]);
+                    self.depth -= 1;
+                    return Ok(sep_span.end);
+                }
+                _ => return Err(Error::new(ErrorKind::InvalidToken, sep_span.start as u64)),
+            }
+        }
+    }
+}
+
+/// Detects or removes duplicate keys in the objects of a document.
+///
+/// Python's `json` module silently keeps the last value of a duplicate key
+/// while other parsers might keep the first one or fail.  This makes the
+/// behavior explicit: with `DuplicateKeyPolicy::Error` an error is returned
+/// at the first duplicate key, otherwise a new document is returned with
+/// all but one of the entries for every key removed.  Documents without
+/// duplicates are returned unchanged.  The whole document is validated.
+///
+/// ```
+/// # use python_json_read_adapter::{resolve_duplicate_keys, DuplicateKeyPolicy};
+/// let json = br#"{"a": 1, "b": NaN, "a": 2}"#;
+/// let rv = resolve_duplicate_keys(json, DuplicateKeyPolicy::KeepLast).unwrap();
+/// assert_eq!(rv, br#"{"a": 2, "b": NaN}"#);
+/// let err = resolve_duplicate_keys(json, DuplicateKeyPolicy::Error).unwrap_err();
+/// assert_eq!(err.offset(), Some(19));
+/// ```
+pub fn resolve_duplicate_keys(bytes: &[u8], policy: DuplicateKeyPolicy) -> Result<Vec<u8>, Error> {
+    resolve(bytes, policy).map_err(|err| err.with_source(bytes))
+}
+
+fn resolve(bytes: &[u8], policy: DuplicateKeyPolicy) -> Result<Vec<u8>, Error> {
+    let mut resolver = Resolver {
+        policy,
+        input: bytes,
+        lexer: PyJsonLexer::new(bytes),
+        depth: 0,
+        out: Vec::with_capacity(bytes.len()),
+    };
+    let (token, span) = resolver.next()?;
+    resolver.out.extend_from_slice(&bytes[..span.start]);
+    let end = resolver.value(token, span)?;
+    if let Some(rv) = resolver.lexer.next() {
+        let (_, span) = rv?;
+        return Err(Error::new(ErrorKind::InvalidToken, span.start as u64));
+    }
+    resolver.out.extend_from_slice(&bytes[end..]);
+    Ok(resolver.out)
+}
+
+#[test]
+fn test_resolve_duplicate_keys() {
+    let json = br#" {"a": 1, "b": {"x": [], "x": {"a": 1, "a\u0000": 2}}, "a": [{"c": 1, "c": 2}], "d": 4} "#;
+    assert_eq!(
+        resolve_duplicate_keys(json, DuplicateKeyPolicy::KeepFirst).unwrap(),
+        &br#" {"a": 1, "b": {"x": []}, "d": 4} "#[..]
+    );
+    assert_eq!(
+        resolve_duplicate_keys(json, DuplicateKeyPolicy::KeepLast).unwrap(),
+        &br#" {"a": [{"c": 2}], "b": {"x": {"a": 1, "a\u0000": 2}}, "d": 4} "#[..]
+    );
+    let err = resolve_duplicate_keys(json, DuplicateKeyPolicy::Error).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+    assert_eq!(err.offset(), Some(25));
+
+    let json = br#"[{}, [], {"a": [1, {"b": 2}]}, NaN]"#;
+    for &policy in &[
+        DuplicateKeyPolicy::Error,
+        DuplicateKeyPolicy::KeepFirst,
+        DuplicateKeyPolicy::KeepLast,
+    ] {
+        assert_eq!(resolve_duplicate_keys(json, policy).unwrap(), &json[..]);
+    }
+
+    let err = |json: &[u8]| {
+        let err = resolve_duplicate_keys(json, DuplicateKeyPolicy::KeepFirst).unwrap_err();
+        (err.kind(), err.offset().unwrap())
+    };
+    assert_eq!(err(b"{\"a\": 1,}"), (ErrorKind::InvalidToken, 8));
+    assert_eq!(err(b"{\"a\" 1}"), (ErrorKind::InvalidToken, 5));
+    assert_eq!(err(b"[1,]"), (ErrorKind::InvalidToken, 3));
+    assert_eq!(err(b"[1 2]"), (ErrorKind::InvalidToken, 3));
+    assert_eq!(err(b"[1] 2"), (ErrorKind::InvalidToken, 4));
+    assert_eq!(err(b"{\"a\": [1"), (ErrorKind::UnexpectedEof, 8));
+    assert_eq!(err(&[b'['; 200]), (ErrorKind::LimitExceeded, 128));
+}
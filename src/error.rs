@@ -0,0 +1,304 @@
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::io;
+
+use crate::translator::TokenKind;
+
+/// Describes the kind of a translation error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The document ended in the middle of a token.
+    UnexpectedEof,
+    /// A literal replacement was rejected.
+    InvalidReplacement,
+    /// The document exceeded the configured size limit.
+    LimitExceeded,
+    /// The document contained more replacements than allowed.
+    BudgetExceeded,
+    /// No document start was found within the allowed prefix.
+    PrefixTooLong,
+    /// The document contained a byte sequence that is not a valid token.
+    InvalidToken,
+    /// An object contained the same key more than once.
+    DuplicateKey,
+    /// The document contained binary data outside of strings.
+    BinaryData,
+    /// The document contained a token that the options reject.
+    RejectedToken,
+    /// The input was empty or only contained whitespace.
+    EmptyDocument,
+}
+
+impl ErrorKind {
+    fn description(self) -> &'static str {
+        match self {
+            ErrorKind::UnexpectedEof => "unexpected end of document inside of token",
+            ErrorKind::InvalidReplacement => "invalid literal replacement",
+            ErrorKind::LimitExceeded => "document exceeds size limit",
+            ErrorKind::BudgetExceeded => "document exceeds replacement budget",
+            ErrorKind::PrefixTooLong => "no document start within allowed prefix",
+            ErrorKind::InvalidToken => "invalid token",
+            ErrorKind::DuplicateKey => "duplicate object key",
+            ErrorKind::BinaryData => "binary data",
+            ErrorKind::RejectedToken => "rejected token",
+            ErrorKind::EmptyDocument => "empty document",
+        }
+    }
+}
+
+/// The number of characters shown on either side of the error.
+const SNIPPET_CONTEXT: usize = 30;
+
+/// The rendered region of the document around an error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Snippet {
+    line: usize,
+    column: usize,
+    text: String,
+    caret: usize,
+}
+
+impl Snippet {
+    fn new(source: &[u8], offset: usize) -> Snippet {
+        let line_start = source[..offset]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |idx| idx + 1);
+        let line_end = source[offset..]
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+            .map_or(source.len(), |idx| offset + idx);
+        let before: Vec<char> = String::from_utf8_lossy(&source[line_start..offset])
+            .chars()
+            .collect();
+        let after: Vec<char> = String::from_utf8_lossy(&source[offset..line_end])
+            .chars()
+            .collect();
+        let mut text = String::new();
+        if before.len() > SNIPPET_CONTEXT {
+            text.push_str("...");
+        }
+        let visible = &before[before.len().saturating_sub(SNIPPET_CONTEXT)..];
+        text.extend(visible.iter().map(|&c| printable(c)));
+        let caret = text.chars().count();
+        text.extend(after.iter().take(SNIPPET_CONTEXT).map(|&c| printable(c)));
+        if after.len() > SNIPPET_CONTEXT {
+            text.push_str("...");
+        }
+        Snippet {
+            line: source[..line_start].iter().filter(|&&b| b == b'\n').count() + 1,
+            column: before.len() + 1,
+            text,
+            caret,
+        }
+    }
+}
+
+fn printable(c: char) -> char {
+    if c.is_control() {
+        '?'
+    } else {
+        c
+    }
+}
+
+/// An error that happened during translation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<u64>,
+    detail: Option<&'static str>,
+    token: Option<TokenKind>,
+    snippet: Option<Box<Snippet>>,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, offset: u64) -> Error {
+        Error {
+            kind,
+            offset: Some(offset),
+            detail: None,
+            token: None,
+            snippet: None,
+        }
+    }
+
+    pub(crate) fn with_detail(kind: ErrorKind, detail: &'static str) -> Error {
+        Error {
+            kind,
+            offset: None,
+            detail: Some(detail),
+            token: None,
+            snippet: None,
+        }
+    }
+
+    pub(crate) fn rejected(offset: u64, token: TokenKind) -> Error {
+        Error {
+            kind: ErrorKind::RejectedToken,
+            offset: Some(offset),
+            detail: Some(token.spelling()),
+            token: Some(token),
+            snippet: None,
+        }
+    }
+
+    /// Attaches a snippet of the document to the error.
+    ///
+    /// `source` has to be the document the error's offset refers to.  The
+    /// snippet shows the line and column of the error and the region of the
+    /// line around it with a caret under the offending byte.  It becomes part
+    /// of the `Display` output.  The functions that operate on slices attach
+    /// the snippet themselves.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{translate_slice_with_options, Options};
+    /// let mut json = b"{\n  \"a\": NaN\n}".to_vec();
+    /// let err = translate_slice_with_options(&mut json, &Options::new().reject(true))
+    ///     .unwrap_err();
+    /// assert_eq!(err.line(), Some(2));
+    /// assert_eq!(err.column(), Some(8));
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "rejected token: NaN at offset 9\n --> line 2, column 8\n  |\n2 |   \"a\": NaN\n  |        ^"
+    /// );
+    /// ```
+    pub fn with_source(mut self, source: &[u8]) -> Error {
+        if let Some(offset) = self.offset.and_then(|x| usize::try_from(x).ok()) {
+            if offset <= source.len() {
+                self.snippet = Some(Box::new(Snippet::new(source, offset)));
+            }
+        }
+        self
+    }
+
+    /// Returns the line of the error if a snippet is attached.
+    ///
+    /// Lines are counted from one.
+    pub fn line(&self) -> Option<usize> {
+        self.snippet.as_ref().map(|snippet| snippet.line)
+    }
+
+    /// Returns the column of the error if a snippet is attached.
+    ///
+    /// Columns are counted in characters from one.
+    pub fn column(&self) -> Option<usize> {
+        self.snippet.as_ref().map(|snippet| snippet.column)
+    }
+
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns additional information about the error.
+    ///
+    /// This is the text shown in the `Display` output.  To tell which token
+    /// was rejected use `token_kind`.
+    pub fn detail(&self) -> Option<&'static str> {
+        self.detail
+    }
+
+    /// Returns the kind of token that was rejected.
+    ///
+    /// This is set for `ErrorKind::RejectedToken` only.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{translate_slice_with_options, Options, TokenKind};
+    /// let options = Options::new().reject(true);
+    /// let mut json = b"[1, 123456789012345678901]".to_vec();
+    /// let err = translate_slice_with_options(&mut json, &options).unwrap_err();
+    /// assert_eq!(err.token_kind(), Some(TokenKind::BigInt));
+    /// ```
+    pub fn token_kind(&self) -> Option<TokenKind> {
+        self.token
+    }
+
+    /// Returns the byte offset in the document where the error happened.
+    ///
+    /// Errors that are not related to a document (like invalid
+    /// configuration) do not have an offset.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind.description())?;
+        if let Some(detail) = self.detail {
+            write!(f, ": {}", detail)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        if let Some(ref snippet) = self.snippet {
+            let number = snippet.line.to_string();
+            let pad = " ".repeat(number.len());
+            write!(
+                f,
+                "\n{} --> line {}, column {}\n{} |\n{} | {}\n{} | {}^",
+                &pad[1..],
+                snippet.line,
+                snippet.column,
+                pad,
+                number,
+                snippet.text,
+                pad,
+                " ".repeat(snippet.caret)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        let kind = match err.kind {
+            ErrorKind::UnexpectedEof | ErrorKind::EmptyDocument => io::ErrorKind::UnexpectedEof,
+            ErrorKind::InvalidReplacement => io::ErrorKind::InvalidInput,
+            ErrorKind::LimitExceeded
+            | ErrorKind::BudgetExceeded
+            | ErrorKind::PrefixTooLong
+            | ErrorKind::InvalidToken
+            | ErrorKind::DuplicateKey
+            | ErrorKind::BinaryData
+            | ErrorKind::RejectedToken => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+#[test]
+fn test_error_snippet() {
+    let source = "[1,\n \u{e9}\t\"x\", 2]".as_bytes();
+    let err = Error::new(ErrorKind::InvalidToken, 9).with_source(source);
+    assert_eq!((err.line(), err.column()), (Some(2), Some(5)));
+    assert_eq!(
+        err.to_string(),
+        "invalid token at offset 9\n --> line 2, column 5\n  |\n2 |  \u{e9}?\"x\", 2]\n  |     ^"
+    );
+
+    let mut source = vec![b' '; 100];
+    source.extend_from_slice(b"Nax");
+    source.extend_from_slice(&[b' '; 100]);
+    let err = Error::new(ErrorKind::InvalidToken, 100).with_source(&source);
+    let rendered = err.to_string();
+    let lines: Vec<_> = rendered.lines().collect();
+    assert_eq!(lines[3].len(), 4 + 3 + 30 + 30 + 3);
+    assert!(lines[3].contains("Nax"));
+    assert_eq!(lines[4].find('^'), lines[3].find('N'));
+
+    let err = Error::new(ErrorKind::UnexpectedEof, 3).with_source(b"[1,");
+    assert_eq!(err.column(), Some(4));
+    assert_eq!(
+        Error::new(ErrorKind::UnexpectedEof, 4)
+            .with_source(b"[1,")
+            .line(),
+        None
+    );
+}
@@ -0,0 +1,232 @@
+use crate::error::{Error, ErrorKind};
+use crate::lexer::{unescape, NonFinite, PyJsonLexer, Token};
+
+/// Receives the events of `parse_events`.
+///
+/// All methods do nothing by default so handlers only need to implement
+/// the events they are interested in.
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called at the start of an object.
+    fn begin_object(&mut self) {}
+    /// Called at the end of an object.
+    fn end_object(&mut self) {}
+    /// Called at the start of an array.
+    fn begin_array(&mut self) {}
+    /// Called at the end of an array.
+    fn end_array(&mut self) {}
+    /// Called for every key of an object before its value.
+    fn key(&mut self, key: &str) {}
+    /// Called for `null`.
+    fn null(&mut self) {}
+    /// Called for `true` and `false`.
+    fn bool(&mut self, value: bool) {}
+    /// Called for finite numbers with their text, which may be a big integer.
+    fn number(&mut self, text: &str) {}
+    /// Called for `NaN`, `Infinity` and `-Infinity`.
+    fn non_finite(&mut self, value: NonFinite) {}
+    /// Called for strings that are not keys.
+    fn string(&mut self, value: &str) {}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Expect {
+    Value,
+    ValueOrEnd,
+    Key,
+    KeyOrEnd,
+    Colon,
+    CommaOrEnd,
+    Done,
+}
+
+/// Parses a Python JSON document and reports its contents to a handler.
+///
+/// This is a push parser: instead of building a value it reports the
+/// structure of the document as it goes, which makes it possible to pick a
+/// few fields out of huge documents without holding them in memory.  The
+/// whole document is validated; on error the handler might already have
+/// seen some events.
+///
+/// ```
+/// # use python_json_read_adapter::{parse_events, Handler};
+/// #[derive(Default)]
+/// struct Keys(Vec<String>);
+///
+/// impl Handler for Keys {
+///     fn key(&mut self, key: &str) {
+///         self.0.push(key.to_string());
+///     }
+/// }
+///
+/// let mut keys = Keys::default();
+/// parse_events(br#"{"a": NaN, "b": {"c": [Infinity]}}"#, &mut keys).unwrap();
+/// assert_eq!(keys.0, vec!["a", "b", "c"]);
+/// ```
+pub fn parse_events<H: Handler + ?Sized>(bytes: &[u8], handler: &mut H) -> Result<(), Error> {
+    parse(bytes, handler).map_err(|err| err.with_source(bytes))
+}
+
+fn parse<H: Handler + ?Sized>(bytes: &[u8], handler: &mut H) -> Result<(), Error> {
+    // true for objects, false for arrays
+    let mut stack = Vec::new();
+    let mut expect = Expect::Value;
+    for rv in PyJsonLexer::new(bytes) {
+        let (token, span) = rv?;
+        let invalid = || Error::new(ErrorKind::InvalidToken, span.start as u64);
+        let in_object = stack.last().copied();
+        expect = match (expect, token) {
+            (Expect::Value | Expect::ValueOrEnd, Token::BeginObject) => {
+                handler.begin_object();
+                stack.push(true);
+                Expect::KeyOrEnd
+            }
+            (Expect::Value | Expect::ValueOrEnd, Token::BeginArray) => {
+                handler.begin_array();
+                stack.push(false);
+                Expect::ValueOrEnd
+            }
+            (Expect::Value | Expect::ValueOrEnd, scalar) => {
+                match scalar {
+                    Token::Null => handler.null(),
+                    Token::Bool(value) => handler.bool(value),
+                    Token::Number(text) | Token::BigInt(text) => {
+                        // numbers only consist of ASCII bytes
+                        handler.number(std::str::from_utf8(text).unwrap_or_default())
+                    }
+                    Token::NonFinite(value) => handler.non_finite(value),
+                    Token::String(raw) => handler.string(&unescape(raw, span.start + 1)?),
+                    Token::EndArray if expect == Expect::ValueOrEnd => {
+                        stack.pop();
+                        handler.end_array();
+                    }
+                    _ => return Err(invalid()),
+                }
+                after_value(&stack)
+            }
+            (Expect::Key | Expect::KeyOrEnd, Token::String(raw)) => {
+                handler.key(&unescape(raw, span.start + 1)?);
+                Expect::Colon
+            }
+            (Expect::KeyOrEnd, Token::EndObject) | (Expect::CommaOrEnd, Token::EndObject)
+                if in_object == Some(true) =>
+            {
+                stack.pop();
+                handler.end_object();
+                after_value(&stack)
+            }
+            (Expect::CommaOrEnd, Token::EndArray) if in_object == Some(false) => {
+                stack.pop();
+                handler.end_array();
+                after_value(&stack)
+            }
+            (Expect::Colon, Token::Colon) => Expect::Value,
+            (Expect::CommaOrEnd, Token::Comma) if in_object == Some(true) => Expect::Key,
+            (Expect::CommaOrEnd, Token::Comma) => Expect::Value,
+            _ => return Err(invalid()),
+        };
+    }
+    if expect == Expect::Done {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::UnexpectedEof, bytes.len() as u64))
+    }
+}
+
+fn after_value(stack: &[bool]) -> Expect {
+    if stack.is_empty() {
+        Expect::Done
+    } else {
+        Expect::CommaOrEnd
+    }
+}
+
+#[test]
+fn test_parse_events() {
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Handler for Recorder {
+        fn begin_object(&mut self) {
+            self.0.push("{".into());
+        }
+        fn end_object(&mut self) {
+            self.0.push("}".into());
+        }
+        fn begin_array(&mut self) {
+            self.0.push("[".into());
+        }
+        fn end_array(&mut self) {
+            self.0.push("]".into());
+        }
+        fn key(&mut self, key: &str) {
+            self.0.push(format!("key {}", key));
+        }
+        fn null(&mut self) {
+            self.0.push("null".into());
+        }
+        fn bool(&mut self, value: bool) {
+            self.0.push(value.to_string());
+        }
+        fn number(&mut self, text: &str) {
+            self.0.push(format!("number {}", text));
+        }
+        fn non_finite(&mut self, value: NonFinite) {
+            self.0.push(format!("{:?}", value));
+        }
+        fn string(&mut self, value: &str) {
+            self.0.push(format!("string {}", value));
+        }
+    }
+
+    let mut recorder = Recorder::default();
+    parse_events(
+        r#"{"a\n": [NaN, -Infinity, [], {}], "b": [1e400, 123456789012345678901234, "é"], "c": null, "d": false}"#
+            .as_bytes(),
+        &mut recorder,
+    )
+    .unwrap();
+    assert_eq!(
+        recorder.0,
+        vec![
+            "{",
+            "key a\n",
+            "[",
+            "NaN",
+            "NegInf",
+            "[",
+            "]",
+            "{",
+            "}",
+            "]",
+            "key b",
+            "[",
+            "number 1e400",
+            "number 123456789012345678901234",
+            "string é",
+            "]",
+            "key c",
+            "null",
+            "key d",
+            "false",
+            "}",
+        ]
+    );
+
+    let mut recorder = Recorder::default();
+    parse_events(b" Infinity ", &mut recorder).unwrap();
+    assert_eq!(recorder.0, vec!["PosInf"]);
+
+    let err = |json: &[u8]| {
+        let err = parse_events(json, &mut Recorder::default()).unwrap_err();
+        (err.kind(), err.offset().unwrap())
+    };
+    assert_eq!(err(b"[1 2]"), (ErrorKind::InvalidToken, 3));
+    assert_eq!(err(b"[1,]"), (ErrorKind::InvalidToken, 3));
+    assert_eq!(err(b"{\"a\" 1}"), (ErrorKind::InvalidToken, 5));
+    assert_eq!(err(b"{1: 2}"), (ErrorKind::InvalidToken, 1));
+    assert_eq!(err(b"[1}"), (ErrorKind::InvalidToken, 2));
+    assert_eq!(err(b"[] []"), (ErrorKind::InvalidToken, 3));
+    assert_eq!(err(b"{\"a\": [NaN"), (ErrorKind::UnexpectedEof, 10));
+    assert_eq!(err(b""), (ErrorKind::UnexpectedEof, 0));
+}
@@ -0,0 +1,159 @@
+use crate::translator::Replacement;
+
+/// How close a rewritten token has to be to count as near a failure.
+const NEAR_DISTANCE: usize = 16;
+
+/// Tells how a parse failure relates to the rewritten tokens.
+///
+/// This is returned by `explain` and its variants.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Explanation {
+    /// The failure is in the bytes of a rewritten token.
+    InReplacement(Replacement),
+    /// A rewritten token ends or starts close to the failure.
+    NearReplacement(Replacement),
+    /// No rewritten token is close to the failure.
+    Unrelated,
+}
+
+/// Checks if a parse failure at `offset` is at or near a rewritten token.
+///
+/// `replacements` is the report of `translate_slice_with_report` for the
+/// document.  This helps to tell if the translation could have caused the
+/// failure: if the result is `Explanation::Unrelated` the document is broken
+/// somewhere else.
+///
+/// ```
+/// # use python_json_read_adapter::{explain, translate_slice_with_report, Explanation, Options};
+/// let mut json = br#"{"a": NaN "b": 1, "c": tru}"#.to_vec();
+/// let report = translate_slice_with_report(&mut json, &Options::new()).unwrap();
+/// assert_eq!(explain(&report, 10), Explanation::NearReplacement(report[0]));
+/// assert_eq!(explain(&report, 26), Explanation::Unrelated);
+/// ```
+pub fn explain(replacements: &[Replacement], offset: usize) -> Explanation {
+    let idx = replacements.partition_point(|replacement| replacement.start <= offset);
+    let before = idx.checked_sub(1).map(|idx| replacements[idx]);
+    if let Some(replacement) = before {
+        if offset < replacement.end {
+            return Explanation::InReplacement(replacement);
+        }
+    }
+    let distance_before = before.map(|replacement| offset - replacement.end);
+    let distance_after = replacements
+        .get(idx)
+        .map(|replacement| replacement.start - offset);
+    let nearest = match (distance_before, distance_after) {
+        (Some(before_dist), Some(after_dist)) if after_dist < before_dist => {
+            Some((replacements[idx], after_dist))
+        }
+        (Some(dist), _) => before.map(|replacement| (replacement, dist)),
+        (None, Some(dist)) => Some((replacements[idx], dist)),
+        (None, None) => None,
+    };
+    match nearest {
+        Some((replacement, dist)) if dist <= NEAR_DISTANCE => {
+            Explanation::NearReplacement(replacement)
+        }
+        _ => Explanation::Unrelated,
+    }
+}
+
+/// Like `explain` but takes the line and column of the failure.
+///
+/// Lines and columns are counted from one and columns in bytes like in the
+/// errors of `serde_json`.  As the translation does not move any bytes it
+/// does not matter if `document` is the original or the translated one.
+pub fn explain_line_column(
+    document: &[u8],
+    replacements: &[Replacement],
+    line: usize,
+    column: usize,
+) -> Explanation {
+    let line_start = if line <= 1 {
+        0
+    } else {
+        match document
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+            .nth(line - 2)
+        {
+            Some((idx, _)) => idx + 1,
+            None => return Explanation::Unrelated,
+        }
+    };
+    explain(replacements, line_start + column.saturating_sub(1))
+}
+
+/// Like `explain` but takes the error of `serde_json`.
+///
+/// Errors that do not carry a position, like IO errors, are unrelated.
+///
+/// ```
+/// # use python_json_read_adapter::{explain_serde_error, translate_slice_with_report, Explanation, Options};
+/// let mut json = b"[\n  -Infinity -\n]".to_vec();
+/// let report = translate_slice_with_report(&mut json, &Options::new()).unwrap();
+/// let err = serde_json::from_slice::<serde_json::Value>(&json).unwrap_err();
+/// assert_eq!(
+///     explain_serde_error(&json, &report, &err),
+///     Explanation::NearReplacement(report[0])
+/// );
+/// ```
+#[cfg(feature = "serde")]
+pub fn explain_serde_error(
+    document: &[u8],
+    replacements: &[Replacement],
+    err: &serde_json::Error,
+) -> Explanation {
+    if err.line() == 0 {
+        return Explanation::Unrelated;
+    }
+    explain_line_column(document, replacements, err.line(), err.column())
+}
+
+#[test]
+fn test_explain() {
+    use crate::translator::TokenKind;
+
+    let replacement = |start, end| Replacement {
+        kind: TokenKind::NaN,
+        start,
+        end,
+    };
+    let report = [replacement(10, 13), replacement(50, 53)];
+    assert_eq!(explain(&report, 0), Explanation::NearReplacement(report[0]));
+    assert_eq!(explain(&report, 10), Explanation::InReplacement(report[0]));
+    assert_eq!(explain(&report, 12), Explanation::InReplacement(report[0]));
+    assert_eq!(
+        explain(&report, 13),
+        Explanation::NearReplacement(report[0])
+    );
+    assert_eq!(
+        explain(&report, 29),
+        Explanation::NearReplacement(report[0])
+    );
+    assert_eq!(explain(&report, 30), Explanation::Unrelated);
+    assert_eq!(
+        explain(&report, 34),
+        Explanation::NearReplacement(report[1])
+    );
+    assert_eq!(explain(&report, 52), Explanation::InReplacement(report[1]));
+    assert_eq!(explain(&report, 70), Explanation::Unrelated);
+    assert_eq!(explain(&[], 0), Explanation::Unrelated);
+
+    let document = b"[\n1,\n  NaN]";
+    let report = [replacement(7, 10)];
+    assert_eq!(
+        explain_line_column(document, &report, 3, 3),
+        Explanation::InReplacement(report[0])
+    );
+    assert_eq!(
+        explain_line_column(document, &report, 1, 1),
+        Explanation::NearReplacement(report[0])
+    );
+    assert_eq!(
+        explain_line_column(document, &report, 4, 1),
+        Explanation::Unrelated
+    );
+}
@@ -0,0 +1,279 @@
+//! A C ABI for the translator.
+//!
+//! All functions operate on memory owned by the caller.  Pointers must be
+//! valid for the lengths passed along with them.
+//! The matching declarations are in `include/pjra.h`.  To link the crate
+//! into a C or C++ program build it as a static library:
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type staticlib
+//! ```
+#![allow(clippy::missing_safety_doc)]
+
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use std::slice;
+
+use crate::error::{Error, ErrorKind};
+use crate::options::Options;
+use crate::translator::Translator;
+
+/// The operation succeeded.
+pub const PJRA_OK: c_int = 0;
+/// A pointer was null or the translator storage was too small or misaligned.
+pub const PJRA_ERR_INVALID_ARGUMENT: c_int = 1;
+/// The document ended in the middle of a token.
+pub const PJRA_ERR_UNEXPECTED_EOF: c_int = 2;
+/// The document contained more replacements than allowed.
+pub const PJRA_ERR_BUDGET_EXCEEDED: c_int = 3;
+/// Any other translation error.
+pub const PJRA_ERR_OTHER: c_int = 4;
+
+/// Passed as `max_replacements` to disable the replacement budget.
+pub const PJRA_UNLIMITED: usize = usize::MAX;
+/// The `error_offset` of a report without an error.
+pub const PJRA_NO_OFFSET: u64 = u64::MAX;
+
+/// The outcome of a translation as seen by C.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PjraReport {
+    /// The number of `NaN` tokens.
+    pub nan: usize,
+    /// The number of `Infinity` tokens.
+    pub pos_inf: usize,
+    /// The number of `-Infinity` tokens.
+    pub neg_inf: usize,
+    /// The number of bytes translated.
+    pub bytes_processed: u64,
+    /// The offset of the error or `PJRA_NO_OFFSET`.
+    pub error_offset: u64,
+}
+
+fn options(max_replacements: usize) -> Options {
+    if max_replacements == PJRA_UNLIMITED {
+        Options::new()
+    } else {
+        Options::new().max_replacements(max_replacements)
+    }
+}
+
+fn status(rv: &Result<(), Error>) -> c_int {
+    match rv {
+        Ok(()) => PJRA_OK,
+        Err(err) => match err.kind() {
+            ErrorKind::UnexpectedEof => PJRA_ERR_UNEXPECTED_EOF,
+            ErrorKind::BudgetExceeded => PJRA_ERR_BUDGET_EXCEEDED,
+            _ => PJRA_ERR_OTHER,
+        },
+    }
+}
+
+fn report(translator: &Translator, rv: &Result<(), Error>) -> PjraReport {
+    let stats = translator.stats();
+    PjraReport {
+        nan: stats.nan,
+        pos_inf: stats.pos_inf,
+        neg_inf: stats.neg_inf,
+        bytes_processed: translator.offset(),
+        error_offset: rv
+            .as_ref()
+            .err()
+            .and_then(Error::offset)
+            .unwrap_or(PJRA_NO_OFFSET),
+    }
+}
+
+unsafe fn span<'a>(ptr: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    if len == 0 {
+        Some(&mut [])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts_mut(ptr, len))
+    }
+}
+
+/// Translates a complete document of `len` bytes at `ptr` in place.
+///
+/// If `report` is not null the counts of the replacements and the offset of
+/// an error are written to it.  `ptr` may be null if `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn pjra_translate(
+    ptr: *mut u8,
+    len: usize,
+    max_replacements: usize,
+    report_out: *mut PjraReport,
+) -> c_int {
+    let bytes = match span(ptr, len) {
+        Some(bytes) => bytes,
+        None => return PJRA_ERR_INVALID_ARGUMENT,
+    };
+    let mut translator = Translator::with_options(options(max_replacements));
+    let rv = translator.feed(bytes).and_then(|_| translator.verify());
+    if !report_out.is_null() {
+        ptr::write(report_out, report(&translator, &rv));
+    }
+    status(&rv)
+}
+
+/// Returns the number of bytes needed to store a translator.
+#[no_mangle]
+pub extern "C" fn pjra_translator_size() -> usize {
+    mem::size_of::<Translator>()
+}
+
+/// Returns the alignment required for the storage of a translator.
+#[no_mangle]
+pub extern "C" fn pjra_translator_align() -> usize {
+    mem::align_of::<Translator>()
+}
+
+unsafe fn translator<'a>(mem: *mut c_void) -> Option<&'a mut Translator> {
+    (mem as *mut Translator).as_mut()
+}
+
+/// Initializes a translator in caller provided storage.
+///
+/// The storage must be at least `pjra_translator_size()` bytes large and
+/// aligned to `pjra_translator_align()`.  A translator can hold allocations,
+/// so it has to be destroyed with `pjra_translator_destroy` before the
+/// storage is released or initialized again.
+#[no_mangle]
+pub unsafe extern "C" fn pjra_translator_init(
+    mem: *mut c_void,
+    size: usize,
+    max_replacements: usize,
+) -> c_int {
+    if mem.is_null()
+        || size < pjra_translator_size()
+        || mem.align_offset(pjra_translator_align()) != 0
+    {
+        return PJRA_ERR_INVALID_ARGUMENT;
+    }
+    ptr::write(
+        mem as *mut Translator,
+        Translator::with_options(options(max_replacements)),
+    );
+    PJRA_OK
+}
+
+/// Destroys a translator initialized with `pjra_translator_init`.
+///
+/// Afterwards the storage can be released or initialized again.  Destroying
+/// a translator twice is undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn pjra_translator_destroy(mem: *mut c_void) -> c_int {
+    if mem.is_null() {
+        return PJRA_ERR_INVALID_ARGUMENT;
+    }
+    ptr::drop_in_place(mem as *mut Translator);
+    PJRA_OK
+}
+
+/// Translates the next chunk of a document in place.
+///
+/// Once an error was returned all further calls return the same error.
+#[no_mangle]
+pub unsafe extern "C" fn pjra_translator_feed(mem: *mut c_void, ptr: *mut u8, len: usize) -> c_int {
+    match (translator(mem), span(ptr, len)) {
+        (Some(translator), Some(bytes)) => status(&translator.feed(bytes)),
+        _ => PJRA_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Checks that the document does not end in the middle of a token.
+#[no_mangle]
+pub unsafe extern "C" fn pjra_translator_finish(mem: *mut c_void) -> c_int {
+    match translator(mem) {
+        Some(translator) => status(&translator.verify()),
+        None => PJRA_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Writes the current state of a translator to `report_out`.
+#[no_mangle]
+pub unsafe extern "C" fn pjra_translator_report(
+    mem: *mut c_void,
+    report_out: *mut PjraReport,
+) -> c_int {
+    let translator = match translator(mem) {
+        Some(translator) => translator,
+        None => return PJRA_ERR_INVALID_ARGUMENT,
+    };
+    if report_out.is_null() {
+        return PJRA_ERR_INVALID_ARGUMENT;
+    }
+    // feeding an empty chunk returns the sticky error, if there is one
+    let rv = translator.feed(&mut []);
+    ptr::write(report_out, report(translator, &rv));
+    PJRA_OK
+}
+
+#[test]
+fn test_translate_span() {
+    let mut json = b"[NaN, -Infinity, Inf".to_vec();
+    let mut report = PjraReport::default();
+    let rv = unsafe { pjra_translate(json.as_mut_ptr(), json.len(), PJRA_UNLIMITED, &mut report) };
+    assert_eq!(rv, PJRA_ERR_UNEXPECTED_EOF);
+    assert_eq!(&json[..], b"[0.0, -0.0     , 0.0");
+    assert_eq!(report.nan, 1);
+    assert_eq!(report.neg_inf, 1);
+    assert_eq!(report.error_offset, 20);
+
+    let mut json = b"[NaN, NaN]".to_vec();
+    let rv = unsafe { pjra_translate(json.as_mut_ptr(), json.len(), 1, &mut report) };
+    assert_eq!(rv, PJRA_ERR_BUDGET_EXCEEDED);
+    assert_eq!(report.error_offset, 6);
+
+    let rv = unsafe { pjra_translate(ptr::null_mut(), 0, 0, ptr::null_mut()) };
+    assert_eq!(rv, PJRA_OK);
+    let rv = unsafe { pjra_translate(ptr::null_mut(), 1, 0, ptr::null_mut()) };
+    assert_eq!(rv, PJRA_ERR_INVALID_ARGUMENT);
+}
+
+#[test]
+fn test_translator_storage() {
+    let mut storage = [0u64; 32];
+    assert!(pjra_translator_size() <= mem::size_of_val(&storage));
+    let mem = storage.as_mut_ptr() as *mut c_void;
+    unsafe {
+        assert_eq!(
+            pjra_translator_init(mem, 1, PJRA_UNLIMITED),
+            PJRA_ERR_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            pjra_translator_init(mem, mem::size_of_val(&storage), PJRA_UNLIMITED),
+            PJRA_OK
+        );
+        let mut first = *b"[Na";
+        let mut second = *b"N]";
+        assert_eq!(pjra_translator_feed(mem, first.as_mut_ptr(), 3), PJRA_OK);
+        assert_eq!(pjra_translator_feed(mem, second.as_mut_ptr(), 2), PJRA_OK);
+        assert_eq!(pjra_translator_finish(mem), PJRA_OK);
+        assert_eq!(&first, b"[0.");
+        assert_eq!(&second, b"0]");
+        let mut report = PjraReport::default();
+        assert_eq!(pjra_translator_report(mem, &mut report), PJRA_OK);
+        assert_eq!(report.nan, 1);
+        assert_eq!(report.bytes_processed, 5);
+        assert_eq!(report.error_offset, PJRA_NO_OFFSET);
+        assert_eq!(pjra_translator_destroy(mem), PJRA_OK);
+
+        assert_eq!(
+            pjra_translator_init(mem, mem::size_of_val(&storage), 0),
+            PJRA_OK
+        );
+        let mut json = *b"[NaN]";
+        assert_eq!(
+            pjra_translator_feed(mem, json.as_mut_ptr(), 5),
+            PJRA_ERR_BUDGET_EXCEEDED
+        );
+        assert_eq!(pjra_translator_destroy(mem), PJRA_OK);
+        assert_eq!(
+            pjra_translator_destroy(ptr::null_mut()),
+            PJRA_ERR_INVALID_ARGUMENT
+        );
+    }
+}
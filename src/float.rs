@@ -0,0 +1,78 @@
+use std::fmt;
+
+use serde_self::de::{Deserialize, Deserializer};
+use serde_self::ser::{Serialize, Serializer};
+
+/// A float that round-trips non-finite values.
+///
+/// This is a drop-in field type for data exchanged with Python.  It
+/// serializes like a `f64`, so together with `to_string_python` non-finite
+/// values are written as `NaN`, `Infinity` and `-Infinity`.  On
+/// deserialization it accepts numbers as well as the strings used by
+/// `non_finite_as_string`, and together with `from_slice_lossless` the
+/// Python tokens deserialize into the actual non-finite values.
+///
+/// ```
+/// # use python_json_read_adapter::{from_slice_lossless, to_string_python, PyFloat};
+/// let values: Vec<PyFloat> = from_slice_lossless(b"[1.5, NaN, -Infinity]").unwrap();
+/// assert!(values[1].0.is_nan());
+/// assert_eq!(to_string_python(&values).unwrap(), "[1.5,NaN,-Infinity]");
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct PyFloat(pub f64);
+
+impl From<f64> for PyFloat {
+    fn from(value: f64) -> PyFloat {
+        PyFloat(value)
+    }
+}
+
+impl From<PyFloat> for f64 {
+    fn from(value: PyFloat) -> f64 {
+        value.0
+    }
+}
+
+impl fmt::Display for PyFloat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_nan() {
+            f.write_str("NaN")
+        } else if self.0.is_infinite() && self.0 > 0.0 {
+            f.write_str("Infinity")
+        } else if self.0.is_infinite() {
+            f.write_str("-Infinity")
+        } else {
+            fmt::Display::fmt(&self.0, f)
+        }
+    }
+}
+
+impl Serialize for PyFloat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for PyFloat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<PyFloat, D::Error> {
+        crate::non_finite_as_string::deserialize(deserializer).map(PyFloat)
+    }
+}
+
+#[test]
+fn test_py_float_roundtrip() {
+    let values = vec![
+        PyFloat(f64::INFINITY),
+        PyFloat(-2.5),
+        PyFloat(f64::NEG_INFINITY),
+    ];
+    let json = crate::to_string_python(&values).unwrap();
+    assert_eq!(json, "[Infinity,-2.5,-Infinity]");
+    let rv: Vec<PyFloat> = crate::from_str_lossless(&json).unwrap();
+    assert_eq!(rv, values);
+    let rv: Vec<PyFloat> = serde_json::from_str(r#"["NaN", 1, "-Infinity"]"#).unwrap();
+    assert!(rv[0].0.is_nan());
+    assert_eq!(&rv[1..], &[PyFloat(1.0), PyFloat(f64::NEG_INFINITY)]);
+    assert_eq!(PyFloat(f64::NAN).to_string(), "NaN");
+    assert_eq!(PyFloat(0.5).to_string(), "0.5");
+}
@@ -0,0 +1,80 @@
+use std::io;
+
+use serde_self::de;
+
+use crate::{from_slice_with_options, lenient_to_json, LenientOptions, Options};
+
+/// Deserialize an instance of type `T` from bytes of JSON5 text.
+///
+/// The document is repaired with `LenientOptions::json5` into a new buffer
+/// which is then translated like Python JSON, so `NaN`, `Infinity` and
+/// `+Infinity` become `0.0`.
+///
+/// ```
+/// # use python_json_read_adapter::from_slice_json5;
+/// let rv: Vec<f64> = from_slice_json5(b"[0x10, .5, +Infinity, /* x */ NaN,]").unwrap();
+/// assert_eq!(rv, vec![16.0, 0.5, 0.0, 0.0]);
+/// ```
+pub fn from_slice_json5<T>(v: &[u8]) -> serde_json::Result<T>
+where
+    T: de::DeserializeOwned,
+{
+    let mut json = lenient_to_json(v, &LenientOptions::json5());
+    from_slice_with_options(&mut json, &Options::new().plus_infinity(true))
+}
+
+/// Deserialize an instance of type `T` from an IO stream of JSON5.
+///
+/// This works like `from_slice_json5` but reads the whole document into
+/// memory first.
+pub fn from_reader_json5<R, T>(mut rdr: R) -> serde_json::Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut buf = Vec::new();
+    rdr.read_to_end(&mut buf).map_err(serde_json::Error::io)?;
+    from_slice_json5(&buf)
+}
+
+#[test]
+fn test_from_slice_json5() {
+    let input = r#"// config
+{
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use "double quotes" here',
+  lineBreaks: "Look, Mom! \
+No \\n's!",
+  hexadecimal: 0xdecaf,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  trailingComma: 'in objects', andIn: ['arrays',],
+  "backwardsCompatible": "with JSON",
+  /* non-finite */ inf: +Infinity,
+  escapes: "it\'s \x41\v\0",
+  é: 1,
+}
+"#;
+    let rv: serde_json::Value = from_slice_json5(input.as_bytes()).unwrap();
+    assert_eq!(
+        rv,
+        serde_json::json!({
+            "unquoted": "and you can quote me on that",
+            "singleQuotes": "I can use \"double quotes\" here",
+            "lineBreaks": "Look, Mom! No \\n's!",
+            "hexadecimal": 912559,
+            "leadingDecimalPoint": 0.8675309,
+            "andTrailing": 8675309.0,
+            "positiveSign": 1,
+            "trailingComma": "in objects",
+            "andIn": ["arrays"],
+            "backwardsCompatible": "with JSON",
+            "inf": 0.0,
+            "escapes": "it's A\u{b}\0",
+            "é": 1,
+        })
+    );
+    let rv: Vec<u32> = from_reader_json5(&b"[1, 0x2,]"[..]).unwrap();
+    assert_eq!(rv, vec![1, 2]);
+    assert!(from_slice_json5::<Vec<u32>>(b"[1,,]").is_err());
+}
@@ -0,0 +1,795 @@
+/// Options that control `lenient_to_json`.
+///
+/// Every repair is disabled by default and has to be enabled explicitly.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LenientOptions {
+    escape_control_chars: bool,
+    escape_newlines: bool,
+    undefined: Option<String>,
+    quote_keys: bool,
+    hex_numbers: bool,
+    normalize_numbers: bool,
+    bare_decimals: bool,
+    trailing_commas: bool,
+    comments: bool,
+    single_quotes: bool,
+    line_continuations: bool,
+    json5_escapes: bool,
+}
+
+impl LenientOptions {
+    /// Creates the default options.
+    pub fn new() -> LenientOptions {
+        LenientOptions::default()
+    }
+
+    /// Creates options that accept JSON5.
+    ///
+    /// This enables all repairs that are needed to turn JSON5 into Python
+    /// JSON.  `+Infinity` is left for the translation with
+    /// `Options::plus_infinity`.  Keys are quoted if they are made of ASCII
+    /// identifier characters or non-ASCII characters, escapes in identifiers
+    /// are not supported.
+    pub fn json5() -> LenientOptions {
+        LenientOptions::new()
+            .quote_keys(true)
+            .single_quotes(true)
+            .line_continuations(true)
+            .comments(true)
+            .trailing_commas(true)
+            .hex_numbers(true)
+            .normalize_numbers(true)
+            .bare_decimals(true)
+            .json5_escapes(true)
+    }
+
+    /// Escapes raw control characters inside of strings.
+    ///
+    /// Hand built JSON sometimes contains literal tabs, newlines or other
+    /// control characters in strings which JSON does not allow.  With this
+    /// enabled newlines, carriage returns and tabs are written as `\n`, `\r`
+    /// and `\t` and all other control characters as `\u00XX`.
+    pub fn escape_control_chars(mut self, yes: bool) -> LenientOptions {
+        self.escape_control_chars = yes;
+        self
+    }
+
+    /// Escapes raw newlines and tabs inside of strings.
+    ///
+    /// This is a narrower version of `escape_control_chars` for strings that
+    /// span multiple lines, such as log messages with embedded tracebacks.
+    /// Other control characters are left alone.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().escape_newlines(true);
+    /// let json = lenient_to_json(b"{\"tb\": \"Traceback:\n\tline 1\"}", &options);
+    /// assert_eq!(json, br#"{"tb": "Traceback:\n\tline 1"}"#);
+    /// ```
+    pub fn escape_newlines(mut self, yes: bool) -> LenientOptions {
+        self.escape_newlines = yes;
+        self
+    }
+
+    /// Replaces the JavaScript `undefined` token.
+    ///
+    /// JSON-ish output of JavaScript tooling sometimes leaks `undefined`.
+    /// With this set the token is replaced with the given literal, which is
+    /// inserted as is and usually is `null`.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().undefined_as("null");
+    /// let json = lenient_to_json(br#"{"a": undefined, "b": "undefined"}"#, &options);
+    /// assert_eq!(json, br#"{"a": null, "b": "undefined"}"#);
+    /// ```
+    pub fn undefined_as(mut self, replacement: &str) -> LenientOptions {
+        self.undefined = Some(replacement.to_string());
+        self
+    }
+
+    /// Quotes bare identifiers that are used as object keys.
+    ///
+    /// JavaScript object literals and some templated output write keys
+    /// without quotes.  With this enabled every identifier that is followed
+    /// by a colon is put in quotes, including keywords like `true`.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().quote_keys(true);
+    /// let json = lenient_to_json(br#"{foo: 1, $bar_2 : true}"#, &options);
+    /// assert_eq!(json, br#"{"foo": 1, "$bar_2" : true}"#);
+    /// ```
+    pub fn quote_keys(mut self, yes: bool) -> LenientOptions {
+        self.quote_keys = yes;
+        self
+    }
+
+    /// Converts hexadecimal integers like `0x1F` to decimal.
+    ///
+    /// JSON5 and Python reprs of some types allow hex literals.  Numbers that
+    /// do not fit into 128 bits are left alone.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().hex_numbers(true);
+    /// assert_eq!(lenient_to_json(b"[0x1F, -0XfF]", &options), b"[31, -255]");
+    /// ```
+    pub fn hex_numbers(mut self, yes: bool) -> LenientOptions {
+        self.hex_numbers = yes;
+        self
+    }
+
+    /// Removes leading plus signs and leading zeros from numbers.
+    ///
+    /// Sloppy templating produces numbers like `+5` or `0123` which JSON
+    /// does not allow.  With this enabled they are written as `5` and `123`.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().normalize_numbers(true);
+    /// assert_eq!(lenient_to_json(b"[+5, -007, 0.5]", &options), b"[5, -7, 0.5]");
+    /// ```
+    pub fn normalize_numbers(mut self, yes: bool) -> LenientOptions {
+        self.normalize_numbers = yes;
+        self
+    }
+
+    /// Completes decimals that lack digits before or after the point.
+    ///
+    /// JSON5 and some Python string formatting write `.5` and `5.`.  With
+    /// this enabled they are written as `0.5` and `5.0`.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().bare_decimals(true);
+    /// assert_eq!(lenient_to_json(b"[.5, -5., 5.e3]", &options), b"[0.5, -5.0, 5.0e3]");
+    /// ```
+    pub fn bare_decimals(mut self, yes: bool) -> LenientOptions {
+        self.bare_decimals = yes;
+        self
+    }
+
+    /// Removes trailing commas in arrays and objects.
+    ///
+    /// Hand edited fixture files often have a comma after the last item.
+    /// With this enabled such commas are replaced with a space so that the
+    /// positions in the document stay the same.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().trailing_commas(true);
+    /// assert_eq!(lenient_to_json(b"{\"a\": [1, 2,],}", &options), b"{\"a\": [1, 2 ] }");
+    /// ```
+    pub fn trailing_commas(mut self, yes: bool) -> LenientOptions {
+        self.trailing_commas = yes;
+        self
+    }
+
+    /// Strips `//` line comments and `/* */` block comments.
+    ///
+    /// Configuration files written by humans often carry comments.  With
+    /// this enabled comments outside of strings are replaced with spaces,
+    /// newlines inside of block comments are kept so that line numbers in
+    /// error messages stay correct.  Unterminated block comments are left
+    /// alone.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().comments(true);
+    /// assert_eq!(lenient_to_json(b"[1 /* x */, 2] // y", &options), b"[1        , 2]     ");
+    /// ```
+    pub fn comments(mut self, yes: bool) -> LenientOptions {
+        self.comments = yes;
+        self
+    }
+
+    /// Converts single quoted strings to double quoted strings.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().single_quotes(true);
+    /// assert_eq!(lenient_to_json(br#"['a"b\'c']"#, &options), br#"["a\"b'c"]"#);
+    /// ```
+    pub fn single_quotes(mut self, yes: bool) -> LenientOptions {
+        self.single_quotes = yes;
+        self
+    }
+
+    /// Removes escaped newlines from strings.
+    ///
+    /// JSON5 allows strings to continue on the next line if the line ends
+    /// with a backslash.  With this enabled the backslash and the newline are
+    /// removed.
+    pub fn line_continuations(mut self, yes: bool) -> LenientOptions {
+        self.line_continuations = yes;
+        self
+    }
+
+    /// Translates the string escapes of JSON5 that JSON does not have.
+    ///
+    /// With this enabled `\xXX`, `\v` and `\0` are written as `\u00XX`,
+    /// `\u000b` and `\u0000` and `\'` as a plain quote, also in double
+    /// quoted strings.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+    /// let options = LenientOptions::new().json5_escapes(true);
+    /// let json = lenient_to_json(br#"["it\'s", "\x41\v\0"]"#, &options);
+    /// assert_eq!(json, br#"["it's", "\u0041\u000b\u0000"]"#);
+    /// ```
+    pub fn json5_escapes(mut self, yes: bool) -> LenientOptions {
+        self.json5_escapes = yes;
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Segment {
+    out_start: usize,
+    out_len: usize,
+    start: usize,
+    len: usize,
+}
+
+/// Maps offsets in a repaired document back to the input.
+///
+/// This is returned by `lenient_to_json_with_map`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OffsetMap {
+    segments: Vec<Segment>,
+}
+
+impl OffsetMap {
+    fn push(&mut self, out_start: usize, out_end: usize, start: usize, end: usize) {
+        let (out_len, len) = (out_end - out_start, end - start);
+        if let Some(last) = self.segments.last_mut() {
+            // runs of copied bytes are merged into one segment
+            if last.out_len == last.len && out_len == len {
+                last.out_len += out_len;
+                last.len += len;
+                return;
+            }
+        }
+        self.segments.push(Segment {
+            out_start,
+            out_len,
+            start,
+            len,
+        });
+    }
+
+    /// Returns the offset in the input for an offset in the output.
+    ///
+    /// Offsets in parts that were copied map to the same byte in the input,
+    /// offsets in a repaired token map into the token they were created from.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let idx = self
+            .segments
+            .partition_point(|segment| segment.out_start <= offset);
+        match idx.checked_sub(1).map(|idx| self.segments[idx]) {
+            Some(segment) => segment.start + (offset - segment.out_start).min(segment.len),
+            None => offset,
+        }
+    }
+}
+
+struct Repairer<'a> {
+    options: &'a LenientOptions,
+    input: &'a [u8],
+    pos: usize,
+    out: Vec<u8>,
+    map: Option<OffsetMap>,
+}
+
+impl<'a> Repairer<'a> {
+    fn run(&mut self) {
+        while let Some(&b) = self.input.get(self.pos) {
+            let (start, out_start) = (self.pos, self.out.len());
+            self.step(b);
+            if let Some(ref mut map) = self.map {
+                map.push(out_start, self.out.len(), start, self.pos);
+            }
+        }
+    }
+
+    fn step(&mut self, b: u8) {
+        match b {
+            b'"' => self.string(b'"'),
+            b'\'' if self.options.single_quotes => self.string(b'\''),
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'$' => self.ident(),
+            0x80..=0xff if self.options.quote_keys => self.ident(),
+            b'0'..=b'9' => self.number(),
+            b'.' if self.options.bare_decimals
+                && matches!(self.input.get(self.pos + 1), Some(b'0'..=b'9')) =>
+            {
+                self.number()
+            }
+            b'+' if self.options.normalize_numbers
+                && matches!(self.input.get(self.pos + 1), Some(b'0'..=b'9')) =>
+            {
+                self.pos += 1;
+            }
+            b'/' if self.comment_len(self.pos).is_some() => self.comment(),
+            b',' if self.options.trailing_commas => {
+                self.pos += 1;
+                match self.next_significant() {
+                    Some(b']' | b'}') => self.out.push(b' '),
+                    _ => self.out.push(b','),
+                }
+            }
+            b => {
+                self.out.push(b);
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Returns the next byte that is not whitespace or a comment without
+    /// consuming it.
+    fn next_significant(&self) -> Option<u8> {
+        let mut pos = self.pos;
+        loop {
+            match self.input.get(pos) {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => pos += 1,
+                Some(b'/') => match self.comment_len(pos) {
+                    Some(len) => pos += len,
+                    None => return Some(b'/'),
+                },
+                rv => return rv.copied(),
+            }
+        }
+    }
+
+    /// Returns the length of the comment at `pos` if there is one.
+    fn comment_len(&self, pos: usize) -> Option<usize> {
+        if !self.options.comments {
+            return None;
+        }
+        let rest = &self.input[pos..];
+        if rest.starts_with(b"//") {
+            Some(
+                rest.iter()
+                    .position(|&b| b == b'\n' || b == b'\r')
+                    .unwrap_or(rest.len()),
+            )
+        } else if rest.starts_with(b"/*") {
+            rest[2..]
+                .windows(2)
+                .position(|window| window == b"*/")
+                .map(|end| end + 4)
+        } else {
+            None
+        }
+    }
+
+    fn comment(&mut self) {
+        let len = self.comment_len(self.pos).unwrap_or(0);
+        for &b in &self.input[self.pos..self.pos + len] {
+            self.out.push(match b {
+                b'\n' | b'\r' => b,
+                _ => b' ',
+            });
+        }
+        self.pos += len;
+    }
+
+    fn ident(&mut self) {
+        let start = self.pos;
+        // non-ASCII bytes are only taken as part of keys
+        let unicode = self.options.quote_keys;
+        while let Some(&b) = self.input.get(self.pos) {
+            match b {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'$' => {}
+                0x80..=0xff if unicode => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let ident = &self.input[start..self.pos];
+        if self.options.quote_keys && self.next_significant() == Some(b':') {
+            self.out.push(b'"');
+            self.out.extend_from_slice(ident);
+            self.out.push(b'"');
+            return;
+        }
+        match self.options.undefined {
+            Some(ref replacement) if ident == b"undefined" => {
+                self.out.extend_from_slice(replacement.as_bytes())
+            }
+            _ => self.out.extend_from_slice(ident),
+        }
+    }
+
+    fn number(&mut self) {
+        let start = self.pos;
+        while let Some(b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'.' | b'_') =
+            self.input.get(self.pos)
+        {
+            self.pos += 1;
+        }
+        // exponents can carry a sign
+        while let (Some(b'e' | b'E'), Some(b'+' | b'-')) =
+            (self.input.get(self.pos - 1), self.input.get(self.pos))
+        {
+            self.pos += 1;
+            while let Some(b'0'..=b'9') = self.input.get(self.pos) {
+                self.pos += 1;
+            }
+        }
+        let mut number = &self.input[start..self.pos];
+        if self.options.hex_numbers {
+            if let Some(value) = parse_hex(number) {
+                self.out.extend_from_slice(value.to_string().as_bytes());
+                return;
+            }
+        }
+        if self.options.normalize_numbers {
+            while let [b'0', b'0'..=b'9', ..] = number {
+                number = &number[1..];
+            }
+        }
+        if self.options.bare_decimals {
+            if number.first() == Some(&b'.') {
+                self.out.push(b'0');
+            }
+            if let Some(dot) = number.iter().position(|&b| b == b'.') {
+                if !matches!(number.get(dot + 1), Some(b'0'..=b'9')) {
+                    self.out.extend_from_slice(&number[..=dot]);
+                    self.out.push(b'0');
+                    number = &number[dot + 1..];
+                }
+            }
+        }
+        self.out.extend_from_slice(number);
+    }
+
+    fn string(&mut self, quote: u8) {
+        self.out.push(b'"');
+        self.pos += 1;
+        while let Some(&b) = self.input.get(self.pos) {
+            self.pos += 1;
+            match b {
+                b if b == quote => {
+                    self.out.push(b'"');
+                    return;
+                }
+                b'"' => self.out.extend_from_slice(b"\\\""),
+                b'\\' => match self.input.get(self.pos) {
+                    Some(b'\'') if quote == b'\'' || self.options.json5_escapes => {
+                        self.out.push(b'\'');
+                        self.pos += 1;
+                    }
+                    Some(&escaped) if self.options.json5_escapes && self.json5_escape(escaped) => {}
+                    Some(b'\r') if self.options.line_continuations => {
+                        self.pos += 1;
+                        if self.input.get(self.pos) == Some(&b'\n') {
+                            self.pos += 1;
+                        }
+                    }
+                    Some(b'\n') if self.options.line_continuations => self.pos += 1,
+                    Some(&escaped) => {
+                        self.out.push(b);
+                        self.out.push(escaped);
+                        self.pos += 1;
+                    }
+                    None => self.out.push(b),
+                },
+                b'\n' | b'\r' | b'\t'
+                    if self.options.escape_control_chars || self.options.escape_newlines =>
+                {
+                    self.out.extend_from_slice(match b {
+                        b'\n' => b"\\n",
+                        b'\r' => b"\\r",
+                        _ => b"\\t",
+                    })
+                }
+                0x00..=0x1f if self.options.escape_control_chars => self
+                    .out
+                    .extend_from_slice(format!("\\u{:04x}", b).as_bytes()),
+                b => self.out.push(b),
+            }
+        }
+    }
+}
+
+impl<'a> Repairer<'a> {
+    /// Writes a JSON5 escape as a JSON escape.
+    ///
+    /// `pos` is after the backslash.  Returns `false` if it's not an escape
+    /// that needs translating.
+    fn json5_escape(&mut self, escaped: u8) -> bool {
+        let code = match escaped {
+            b'v' => 0x0b,
+            b'0' if !matches!(self.input.get(self.pos + 1), Some(b'0'..=b'9')) => 0,
+            b'x' => match self.input.get(self.pos + 1..self.pos + 3) {
+                Some(digits) if digits.iter().all(u8::is_ascii_hexdigit) => {
+                    self.out.extend_from_slice(b"\\u00");
+                    self.out.extend_from_slice(digits);
+                    self.pos += 3;
+                    return true;
+                }
+                _ => return false,
+            },
+            _ => return false,
+        };
+        self.out
+            .extend_from_slice(format!("\\u{:04x}", code).as_bytes());
+        self.pos += 1;
+        true
+    }
+}
+
+fn parse_hex(number: &[u8]) -> Option<u128> {
+    let digits = number
+        .strip_prefix(b"0x")
+        .or_else(|| number.strip_prefix(b"0X"))?;
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    u128::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+}
+
+/// Repairs JSON-ish input that is not quite JSON into a new buffer.
+///
+/// This is the owned counterpart to the translation for problems that
+/// cannot be fixed in place because the repaired document is longer or
+/// shorter than the input.  The repairs that are applied are selected with
+/// the options.  Everything else, including `NaN` and `Infinity`, is copied
+/// unchanged, so the result is meant to be passed to `from_slice` or
+/// `from_slice_lossless`.  Like with the translation malformed input is
+/// passed through and left for the JSON parser to reject.
+///
+/// ```
+/// # use python_json_read_adapter::{lenient_to_json, LenientOptions};
+/// let options = LenientOptions::new().escape_control_chars(true);
+/// let json = lenient_to_json(b"{\"text\": \"a\tb\nc\"}", &options);
+/// assert_eq!(json, br#"{"text": "a\tb\nc"}"#);
+/// ```
+pub fn lenient_to_json(input: &[u8], options: &LenientOptions) -> Vec<u8> {
+    let mut repairer = Repairer {
+        options,
+        input,
+        pos: 0,
+        out: Vec::with_capacity(input.len()),
+        map: None,
+    };
+    repairer.run();
+    repairer.out
+}
+
+/// Like `lenient_to_json` but also returns an `OffsetMap`.
+///
+/// As the repairs change the length of the document the offsets of errors
+/// reported by the JSON parser no longer match the input.  The map
+/// translates them back.
+///
+/// ```
+/// # use python_json_read_adapter::{lenient_to_json_with_map, LenientOptions};
+/// let options = LenientOptions::new().quote_keys(true).undefined_as("null");
+/// let (json, map) = lenient_to_json_with_map(b"{a: undefined, b: ?}", &options);
+/// assert_eq!(json, br#"{"a": null, "b": ?}"#);
+/// assert_eq!(json[17], b'?');
+/// assert_eq!(map.original_offset(17), 18);
+/// ```
+pub fn lenient_to_json_with_map(input: &[u8], options: &LenientOptions) -> (Vec<u8>, OffsetMap) {
+    let mut repairer = Repairer {
+        options,
+        input,
+        pos: 0,
+        out: Vec::with_capacity(input.len()),
+        map: Some(OffsetMap::default()),
+    };
+    repairer.run();
+    (repairer.out, repairer.map.unwrap_or_default())
+}
+
+#[test]
+fn test_escape_control_chars() {
+    let options = LenientOptions::new().escape_control_chars(true);
+    let input = b"[\"a\x01\tb\r\n\", \"\\\"\x1f\", \n\t1]";
+    assert_eq!(
+        lenient_to_json(input, &options),
+        &b"[\"a\\u0001\\tb\\r\\n\", \"\\\"\\u001f\", \n\t1]"[..]
+    );
+    assert_eq!(lenient_to_json(input, &LenientOptions::new()), &input[..]);
+    assert_eq!(
+        lenient_to_json(b"\"abc\x00", &options),
+        &b"\"abc\\u0000"[..]
+    );
+}
+
+#[test]
+fn test_escape_newlines() {
+    let options = LenientOptions::new().escape_newlines(true);
+    assert_eq!(
+        lenient_to_json(b"[\"a\r\n\tb\x01\",\n1]", &options),
+        &b"[\"a\\r\\n\\tb\x01\",\n1]"[..]
+    );
+}
+
+#[test]
+fn test_undefined() {
+    let options = LenientOptions::new().undefined_as("null");
+    assert_eq!(
+        lenient_to_json(
+            br#"[undefined,undefinedx,"undefined", undefined]"#,
+            &options
+        ),
+        &br#"[null,undefinedx,"undefined", null]"#[..]
+    );
+    let options = LenientOptions::new().undefined_as("\"\"");
+    assert_eq!(
+        lenient_to_json(b"{\"a\":undefined}", &options),
+        b"{\"a\":\"\"}"
+    );
+    assert_eq!(
+        lenient_to_json(b"[undefined]", &LenientOptions::new()),
+        b"[undefined]"
+    );
+}
+
+#[test]
+fn test_quote_keys() {
+    let options = LenientOptions::new().quote_keys(true).undefined_as("null");
+    assert_eq!(
+        lenient_to_json(
+            b"{a: {b\n:[true]}, \"c\": undefined, undefined: 1}",
+            &options
+        ),
+        &b"{\"a\": {\"b\"\n:[true]}, \"c\": null, \"undefined\": 1}"[..]
+    );
+    assert_eq!(
+        lenient_to_json(b"{a: 1}", &LenientOptions::new()),
+        b"{a: 1}"
+    );
+}
+
+#[test]
+fn test_hex_numbers() {
+    let options = LenientOptions::new().hex_numbers(true);
+    assert_eq!(
+        lenient_to_json(
+            br#"[0x0, 0xdeadBEEF, -0x10, 1e+5, 0x, 0xG, 0x100000000000000000000000000000000, "0x1"]"#,
+            &options
+        ),
+        &br#"[0, 3735928559, -16, 1e+5, 0x, 0xG, 0x100000000000000000000000000000000, "0x1"]"#[..]
+    );
+    assert_eq!(
+        lenient_to_json(b"[0x1F]", &LenientOptions::new()),
+        b"[0x1F]"
+    );
+}
+
+#[test]
+fn test_normalize_numbers() {
+    let options = LenientOptions::new().normalize_numbers(true);
+    assert_eq!(
+        lenient_to_json(
+            br#"[+1, 0123, -00, 000.5, 0, 1e+05, +Infinity, "+1", 0x1]"#,
+            &options
+        ),
+        &br#"[1, 123, -0, 0.5, 0, 1e+05, +Infinity, "+1", 0x1]"#[..]
+    );
+    assert_eq!(
+        lenient_to_json(b"[+1, 01]", &LenientOptions::new()),
+        b"[+1, 01]"
+    );
+}
+
+#[test]
+fn test_bare_decimals() {
+    let options = LenientOptions::new().bare_decimals(true);
+    assert_eq!(
+        lenient_to_json(br#"[.5, -.25, 1., 1.E-2, 1.5, ".5", 1e5, [1.]]"#, &options),
+        &br#"[0.5, -0.25, 1.0, 1.0E-2, 1.5, ".5", 1e5, [1.0]]"#[..]
+    );
+    assert_eq!(
+        lenient_to_json(b"[.5, 1.]", &LenientOptions::new()),
+        b"[.5, 1.]"
+    );
+}
+
+#[test]
+fn test_trailing_commas() {
+    let options = LenientOptions::new().trailing_commas(true);
+    assert_eq!(lenient_to_json(b"[1,\n]", &options), &b"[1 \n]"[..]);
+    assert_eq!(
+        lenient_to_json(br#"{"a,}": [[],], "b": {},}"#, &options),
+        &br#"{"a,}": [[] ], "b": {} }"#[..]
+    );
+    assert_eq!(lenient_to_json(b"[1,,2,", &options), b"[1,,2,");
+    assert_eq!(lenient_to_json(b"[1,]", &LenientOptions::new()), b"[1,]");
+}
+
+#[test]
+fn test_comments() {
+    let options = LenientOptions::new().comments(true).trailing_commas(true);
+    assert_eq!(
+        lenient_to_json(
+            b"{\"a//\": 1, // one\n\"b\": /* two\r\n */ 2, /**/ /* */\n}",
+            &options
+        ),
+        &b"{\"a//\": 1,       \n\"b\":       \r\n    2            \n}"[..]
+    );
+    assert_eq!(lenient_to_json(b"[1] /* x", &options), b"[1] /* x");
+    assert_eq!(lenient_to_json(b"[1] /", &options), b"[1] /");
+    assert_eq!(
+        lenient_to_json(b"[1] // x", &LenientOptions::new()),
+        b"[1] // x"
+    );
+}
+
+#[test]
+fn test_single_quotes() {
+    let options = LenientOptions::new().single_quotes(true);
+    assert_eq!(
+        lenient_to_json(br#"{'a': 'it\'s "x"', "b": "'\"", 'c\\': ''}"#, &options),
+        &br#"{"a": "it's \"x\"", "b": "'\"", "c\\": ""}"#[..]
+    );
+    assert_eq!(lenient_to_json(b"['a']", &LenientOptions::new()), b"['a']");
+}
+
+#[test]
+fn test_line_continuations() {
+    let options = LenientOptions::new()
+        .line_continuations(true)
+        .single_quotes(true);
+    assert_eq!(
+        lenient_to_json(b"['a\\\nb\\\r\nc\\\rd\\n']", &options),
+        &b"[\"abcd\\n\"]"[..]
+    );
+    assert_eq!(
+        lenient_to_json(b"\"a\\\nb\"", &LenientOptions::new()),
+        b"\"a\\\nb\""
+    );
+}
+
+#[test]
+fn test_json5_escapes() {
+    let options = LenientOptions::new()
+        .json5_escapes(true)
+        .single_quotes(true);
+    assert_eq!(
+        lenient_to_json(
+            br#"["\x41\x4a", 'it\'s', "it\'s", "\v\0", "\01", "\xG1", "\x4", "\\x41"]"#,
+            &options
+        ),
+        &br#"["\u0041\u004a", "it's", "it's", "\u000b\u0000", "\01", "\xG1", "\x4", "\\x41"]"#[..]
+    );
+    assert_eq!(
+        lenient_to_json(br#"["\x41"]"#, &LenientOptions::new()),
+        br#"["\x41"]"#
+    );
+}
+
+#[test]
+fn test_unicode_keys() {
+    let options = LenientOptions::new().quote_keys(true);
+    assert_eq!(
+        lenient_to_json("{é: 1, ключ_2 : [x]}".as_bytes(), &options),
+        "{\"é\": 1, \"ключ_2\" : [x]}".as_bytes()
+    );
+    assert_eq!(
+        lenient_to_json("{é: 1}".as_bytes(), &LenientOptions::new()),
+        "{é: 1}".as_bytes()
+    );
+}
+
+#[test]
+fn test_offset_map() {
+    let input = b"{// x\n  a: 'it\\'s', b: 0x10, c: [1,],\n  d: \"\\\n\"}";
+    let options = LenientOptions::json5();
+    let (json, map) = lenient_to_json_with_map(input, &options);
+    assert_eq!(json, lenient_to_json(input, &options));
+    for (needle, original) in &[(&b"\"b\""[..], 20), (b"16", 23), (b"\"d\"", 40), (b"}", 47)] {
+        let offset = json
+            .windows(needle.len())
+            .position(|window| window == *needle)
+            .unwrap();
+        assert_eq!(map.original_offset(offset), *original);
+    }
+    assert_eq!(map.original_offset(0), 0);
+    assert_eq!(map.original_offset(json.len()), input.len());
+    assert_eq!(OffsetMap::default().original_offset(5), 5);
+}
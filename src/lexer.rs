@@ -0,0 +1,385 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::str;
+
+use crate::error::{Error, ErrorKind};
+
+/// A non-finite float token.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NonFinite {
+    /// `NaN` or `-NaN`
+    NaN,
+    /// `Infinity`
+    PosInf,
+    /// `-Infinity`
+    NegInf,
+}
+
+impl NonFinite {
+    /// Returns the float value of the token.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            NonFinite::NaN => f64::NAN,
+            NonFinite::PosInf => f64::INFINITY,
+            NonFinite::NegInf => f64::NEG_INFINITY,
+        }
+    }
+}
+
+/// A token of a Python JSON document.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Token<'a> {
+    /// `{`
+    BeginObject,
+    /// `}`
+    EndObject,
+    /// `[`
+    BeginArray,
+    /// `]`
+    EndArray,
+    /// `:`
+    Colon,
+    /// `,`
+    Comma,
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// A number that fits into a `i64` or `u64`, or a float.
+    Number(&'a [u8]),
+    /// An integer that does not fit into 64 bits.
+    BigInt(&'a [u8]),
+    /// `NaN`, `-NaN`, `Infinity` or `-Infinity`.
+    NonFinite(NonFinite),
+    /// The contents of a string between the quotes with escapes intact.
+    String(&'a [u8]),
+}
+
+/// A pull lexer for Python JSON documents.
+///
+/// The lexer yields the tokens of a document together with their byte
+/// ranges, so tools like redactors or metric collectors can be built on
+/// top of it without parsing the document.  It only checks that the tokens
+/// are valid, not that they form a valid document.
+///
+/// ```
+/// # use python_json_read_adapter::{NonFinite, PyJsonLexer, Token};
+/// let tokens = PyJsonLexer::new(b"[-Infinity]")
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(tokens[1], (Token::NonFinite(NonFinite::NegInf), 1..10));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PyJsonLexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> PyJsonLexer<'a> {
+    /// Creates a lexer for a document.
+    pub fn new(input: &'a [u8]) -> PyJsonLexer<'a> {
+        PyJsonLexer {
+            input,
+            pos: 0,
+            failed: false,
+        }
+    }
+
+    /// Returns the offset of the next byte the lexer looks at.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    fn error(&self, kind: ErrorKind, pos: usize) -> Error {
+        Error::new(kind, pos as u64)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.input.get(self.pos) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn ident(&mut self, ident: &[u8], token: Token<'a>) -> Result<Token<'a>, Error> {
+        let rest = &self.input[self.pos..];
+        if rest.starts_with(ident) {
+            self.pos += ident.len();
+            Ok(token)
+        } else if ident.starts_with(rest) {
+            Err(self.error(ErrorKind::UnexpectedEof, self.input.len()))
+        } else {
+            Err(self.error(ErrorKind::InvalidToken, self.pos))
+        }
+    }
+
+    fn string(&mut self) -> Result<Token<'a>, Error> {
+        let start = self.pos + 1;
+        let mut pos = start;
+        loop {
+            match self.input.get(pos) {
+                Some(b'"') => {
+                    self.pos = pos + 1;
+                    return Ok(Token::String(&self.input[start..pos]));
+                }
+                Some(b'\\') => pos += 2,
+                Some(0..=0x1f) => return Err(self.error(ErrorKind::InvalidToken, pos)),
+                Some(_) => pos += 1,
+                None => return Err(self.error(ErrorKind::UnexpectedEof, self.input.len())),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Token<'a>, Error> {
+        let start = self.pos;
+        let invalid = |lexer: &Self| {
+            if lexer.pos >= lexer.input.len() {
+                lexer.error(ErrorKind::UnexpectedEof, lexer.pos)
+            } else {
+                lexer.error(ErrorKind::InvalidToken, lexer.pos)
+            }
+        };
+        let negative = self.input.get(self.pos) == Some(&b'-');
+        if negative {
+            self.pos += 1;
+        }
+        match self.input.get(self.pos) {
+            Some(b'I') => {
+                let token = if negative {
+                    NonFinite::NegInf
+                } else {
+                    NonFinite::PosInf
+                };
+                return self.ident(b"Infinity", Token::NonFinite(token));
+            }
+            Some(b'N') => {
+                return self.ident(b"NaN", Token::NonFinite(NonFinite::NaN));
+            }
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => {
+                self.skip_digits();
+            }
+            _ => return Err(invalid(self)),
+        }
+        let mut is_float = false;
+        if self.input.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            is_float = true;
+            if self.skip_digits() == 0 {
+                return Err(invalid(self));
+            }
+        }
+        if let Some(b'e' | b'E') = self.input.get(self.pos) {
+            self.pos += 1;
+            is_float = true;
+            if let Some(b'+' | b'-') = self.input.get(self.pos) {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                return Err(invalid(self));
+            }
+        }
+        let text = &self.input[start..self.pos];
+        if !is_float && !fits_64_bits(text) {
+            Ok(Token::BigInt(text))
+        } else {
+            Ok(Token::Number(text))
+        }
+    }
+
+    fn single(&mut self, token: Token<'a>) -> Result<Token<'a>, Error> {
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn token(&mut self) -> Option<Result<(Token<'a>, Range<usize>), Error>> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let token = match *self.input.get(self.pos)? {
+            b'{' => self.single(Token::BeginObject),
+            b'}' => self.single(Token::EndObject),
+            b'[' => self.single(Token::BeginArray),
+            b']' => self.single(Token::EndArray),
+            b':' => self.single(Token::Colon),
+            b',' => self.single(Token::Comma),
+            b'n' => self.ident(b"null", Token::Null),
+            b't' => self.ident(b"true", Token::Bool(true)),
+            b'f' => self.ident(b"false", Token::Bool(false)),
+            b'"' => self.string(),
+            b'-' | b'0'..=b'9' | b'I' | b'N' => self.number(),
+            _ => Err(self.error(ErrorKind::InvalidToken, start)),
+        };
+        Some(token.map(|token| (token, start..self.pos)))
+    }
+}
+
+fn hex4(raw: &[u8], pos: usize) -> Option<u32> {
+    let digits = str::from_utf8(raw.get(pos..pos + 4)?).ok()?;
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Decodes the contents of a `Token::String`.
+///
+/// `offset` is the offset of the contents in the document and is used for
+/// errors.
+pub(crate) fn unescape(raw: &[u8], offset: usize) -> Result<Cow<'_, str>, Error> {
+    let invalid = |pos: usize| Error::new(ErrorKind::InvalidToken, (offset + pos) as u64);
+    if !raw.contains(&b'\\') {
+        return str::from_utf8(raw)
+            .map(Cow::Borrowed)
+            .map_err(|err| invalid(err.valid_up_to()));
+    }
+    let mut rv = Vec::with_capacity(raw.len());
+    let mut pos = 0;
+    while let Some(&byte) = raw.get(pos) {
+        if byte != b'\\' {
+            rv.push(byte);
+            pos += 1;
+            continue;
+        }
+        let decoded = match raw.get(pos + 1) {
+            Some(b'"') => b'"',
+            Some(b'\\') => b'\\',
+            Some(b'/') => b'/',
+            Some(b'b') => b'\x08',
+            Some(b'f') => b'\x0c',
+            Some(b'n') => b'\n',
+            Some(b'r') => b'\r',
+            Some(b't') => b'\t',
+            Some(b'u') => {
+                let mut len = 6;
+                let c = match hex4(raw, pos + 2).ok_or_else(|| invalid(pos))? {
+                    high @ 0xd800..=0xdbff => {
+                        len = 12;
+                        let low = match raw.get(pos + 6..pos + 8) {
+                            Some(b"\\u") => hex4(raw, pos + 8),
+                            _ => None,
+                        };
+                        match low {
+                            Some(low @ 0xdc00..=0xdfff) => {
+                                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                            }
+                            _ => None,
+                        }
+                    }
+                    code => char::from_u32(code),
+                };
+                let c = c.ok_or_else(|| invalid(pos))?;
+                rv.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                pos += len;
+                continue;
+            }
+            _ => return Err(invalid(pos)),
+        };
+        rv.push(decoded);
+        pos += 2;
+    }
+    String::from_utf8(rv)
+        .map(Cow::Owned)
+        .map_err(|_| invalid(0))
+}
+
+fn fits_64_bits(text: &[u8]) -> bool {
+    // the text is an integer and thus ASCII
+    let text = std::str::from_utf8(text).unwrap_or_default();
+    text.parse::<i64>().is_ok() || text.parse::<u64>().is_ok()
+}
+
+impl<'a> Iterator for PyJsonLexer<'a> {
+    type Item = Result<(Token<'a>, Range<usize>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let rv = self.token();
+        if let Some(Err(_)) = rv {
+            self.failed = true;
+        }
+        rv
+    }
+}
+
+#[test]
+fn test_lexer() {
+    let json = br#"{"a\"b": [NaN, Infinity, -1.5e3, 18446744073709551616, -9, true, null]}"#;
+    let tokens = PyJsonLexer::new(json)
+        .map(|rv| rv.map(|(token, _)| token))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::BeginObject,
+            Token::String(br#"a\"b"#),
+            Token::Colon,
+            Token::BeginArray,
+            Token::NonFinite(NonFinite::NaN),
+            Token::Comma,
+            Token::NonFinite(NonFinite::PosInf),
+            Token::Comma,
+            Token::Number(b"-1.5e3"),
+            Token::Comma,
+            Token::BigInt(b"18446744073709551616"),
+            Token::Comma,
+            Token::Number(b"-9"),
+            Token::Comma,
+            Token::Bool(true),
+            Token::Comma,
+            Token::Null,
+            Token::EndArray,
+            Token::EndObject,
+        ]
+    );
+    let spans = PyJsonLexer::new(br#" "x" , -Infinity"#)
+        .map(|rv| rv.unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(spans, vec![1..4, 5..6, 7..16]);
+    let mut lexer = PyJsonLexer::new(b"-NaN");
+    assert_eq!(
+        lexer.next().unwrap().unwrap(),
+        (Token::NonFinite(NonFinite::NaN), 0..4)
+    );
+}
+
+#[test]
+fn test_lexer_errors() {
+    let err = |json: &[u8]| {
+        PyJsonLexer::new(json)
+            .find_map(Result::err)
+            .map(|err| (err.kind(), err.offset().unwrap()))
+    };
+    assert_eq!(err(b"[Infin"), Some((ErrorKind::UnexpectedEof, 6)));
+    assert_eq!(err(b"[Nax]"), Some((ErrorKind::InvalidToken, 1)));
+    assert_eq!(err(b"[1.]"), Some((ErrorKind::InvalidToken, 3)));
+    assert_eq!(err(b"\"abc"), Some((ErrorKind::UnexpectedEof, 4)));
+    assert_eq!(err(b"[@]"), Some((ErrorKind::InvalidToken, 1)));
+    assert_eq!(err(b"-"), Some((ErrorKind::UnexpectedEof, 1)));
+    assert_eq!(err(b"[1, 2]"), None);
+    let mut lexer = PyJsonLexer::new(b"@ 1");
+    assert!(lexer.next().unwrap().is_err());
+    assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_unescape() {
+    assert!(matches!(unescape(b"abc", 0), Ok(Cow::Borrowed("abc"))));
+    assert_eq!(
+        unescape(br#"a\"\n\u00e9\ud83d\ude00"#, 0).unwrap(),
+        "a\"\n\u{e9}\u{1f600}"
+    );
+    assert_eq!(unescape(br#"x\ud83d"#, 10).unwrap_err().offset(), Some(11));
+    assert_eq!(
+        unescape(br#"\q"#, 0).unwrap_err().kind(),
+        ErrorKind::InvalidToken
+    );
+    assert!(unescape(b"\xff", 0).is_err());
+}
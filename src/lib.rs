@@ -25,150 +25,247 @@
 //! {"nan":0.0,"inf":0.0     ,"-inf":-0.0     }
 //! ```
 //!
+//! # Panics
+//!
+//! The translation never panics, no matter what the input is or how it is
+//! split into chunks.  Malformed input is passed through and left for the
+//! JSON parser to reject.  When compiled with `--cfg fuzzing` the translator
+//! additionally checks its own output and panics if it ever rewrites a byte
+//! that is not part of a non-finite token.
+//!
+//! # Python repr
+//!
+//! `repr_to_json` converts the output of `str()` and `repr()` on Python
+//! values, which is often found in logs, into Python JSON.  This produces a
+//! new buffer as the conversion cannot be done in place.
+//!
+//! # Lenient JSON
+//!
+//! `lenient_to_json` repairs other deviations from JSON that cannot be
+//! fixed in place, such as raw control characters in strings.
+//! `LenientOptions::json5` enables all of them to accept JSON5 and the
+//! `json5` feature adds `from_slice_json5` and `from_reader_json5` on top.
+//! `lenient_to_json_with_map` also returns an `OffsetMap` to translate the
+//! offsets of parser errors back to the input.
+//!
 //! # serde support
 //!
 //! If the `serde` feature is enabled then the crate provides some basic
 //! wrappers around `serde_json` to deserialize quickly and also by running
-//! the conversions.
-use std::fmt;
-use std::io::{self, Read};
+//! the conversions.  `to_string_python` and friends go the other way and
+//! write non-finite floats as `NaN` and `Infinity` for Python consumers.
+//! `NdjsonReader` and `NdjsonSlice` deserialize newline-delimited Python
+//! JSON record by record.
+//! With the `serde_path_to_error` feature `from_slice_with_path` and
+//! `from_reader_with_path` report the path of the value that failed.
+//! The `arbitrary_precision` feature enables the feature of the same name in
+//! `serde_json` so that big integers and long decimals emitted by Python's
+//! `decimal` support survive a round trip through `serde_json::Value`.
+//! With the `raw_value` feature `TranslationRecord::original_raw` hands back
+//! the untranslated text of `RawValue` fields.
+//!
+//! The `simd_json` feature adds `from_slice_simd` which parses the
+//! translated document with `simd-json` instead of `serde_json`.  The
+//! `sonic_rs` feature does the same for `sonic-rs` with `from_slice_sonic`
+//! and `from_str_sonic`.
+//!
+//! The `serde_transcode` feature adds `transcode_to_strict_json` which
+//! streams a document from a reader to a writer as strict JSON.  The
+//! `cbor` feature adds `transcode_to_cbor` which keeps non-finite floats,
+//! the `msgpack` feature does the same for MessagePack with
+//! `transcode_to_msgpack`.
+//! `explain_serde_error` tells if a parse error is at or near a token that
+//! was rewritten.
+//! `from_slice_lossless` uses a separate parser that deserializes these
+//! tokens into the actual non-finite float values instead of `0.0`.
+//!
+//! # logging and metrics
+//!
+//! With the `log` feature every rewritten token is reported as a warning
+//! through the `log` crate together with its offset, which helps to find
+//! misbehaving upstream services.
+//!
+//! With the `tracing` feature the slice translations run in a `translate`
+//! span and every translated document, including the ones read through
+//! `JsonCompatRead`, is reported as a debug event with its size, the counts
+//! of replacements and the duration.
+//!
+//! With the `metrics` feature every rewritten token increments the
+//! `python_json_replacements_total` counter of the `metrics` crate, labeled
+//! with the `kind` of token (`nan`, `pos_inf` or `neg_inf`).
+//!
+//! # parallel translation
+//!
+//! With the `rayon` feature `translate_slice_parallel` splits large
+//! documents into chunks that are translated on the `rayon` thread pool.
+//!
+//! # memory maps
+//!
+//! With the `mmap` feature `translate_file_mmap` translates a private
+//! memory map of a file and `from_file_mmap` deserializes from it, which
+//! avoids copying huge files through buffers.
+//!
+//! # tokio support
+//!
+//! If the `codec` feature is enabled then a `PythonJsonLinesDecoder` is
+//! provided which can be used with `tokio_util::codec::FramedRead` to decode
+//! newline-delimited Python JSON.
+//!
+//! # C interface
+//!
+//! The `ffi` feature enables the `ffi` module which exposes the translator
+//! through a C ABI that operates on caller owned memory.
+//!
+//! # testing
+//!
+//! The `test-support` feature enables the `test_support` module with helpers
+//! to test readers that wrap this adapter against chunk boundary issues.
+mod backend;
+mod duplicates;
+mod error;
+mod events;
+mod explain;
+mod lenient;
+mod lexer;
+mod literals;
+mod locale;
+mod ndjson;
+mod options;
+mod original;
+mod pointer;
+mod pretty;
+mod quarantine;
+mod read;
+mod repr;
+mod scan;
+mod selftest;
+mod tables;
+mod translator;
+#[cfg(feature = "simd")]
+mod vectorized;
+
+pub use self::backend::Backend;
+pub use self::duplicates::{resolve_duplicate_keys, DuplicateKeyPolicy};
+pub use self::error::*;
+pub use self::events::{parse_events, Handler};
+#[cfg(feature = "serde")]
+pub use self::explain::explain_serde_error;
+pub use self::explain::{explain, explain_line_column, Explanation};
+pub use self::lenient::{lenient_to_json, lenient_to_json_with_map, LenientOptions, OffsetMap};
+pub use self::lexer::{NonFinite, PyJsonLexer, Token};
+pub use self::literals::*;
+pub use self::locale::*;
+pub use self::ndjson::{split_lines, LineEnding, LineEndingStats, LineSeparators, SplitLines};
+pub use self::options::*;
+pub use self::original::*;
+pub use self::pointer::{get_pointer, RawSpan};
+pub use self::pretty::{pretty_print, pretty_print_reader};
+pub use self::quarantine::*;
+pub use self::read::*;
+pub use self::repr::{repr_to_json, repr_to_json_with_options, BytesEncoding, ReprOptions};
+pub use self::scan::{contains_python_token, contains_python_tokens};
+pub use self::selftest::{SelfTestCase, SelfTestReport};
+pub use self::translator::*;
+
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 #[cfg(feature = "serde")]
 mod serde_impl;
 #[cfg(feature = "serde")]
 pub use self::serde_impl::*;
 
-#[derive(Copy, Clone)]
-enum State {
-    Initial,
-    Quoted,
-    QuotedEscape,
-    NaN0,
-    NaN1,
-    Infinity0,
-    Infinity1,
-    Infinity2,
-    Infinity3,
-    Infinity4,
-    Infinity5,
-    Infinity6,
-}
-
-/// A reader that transparently translates python JSON compat tokens.
-pub struct JsonCompatRead<R> {
-    reader: R,
-    state: State,
-}
-
-impl<R: Read> fmt::Debug for JsonCompatRead<R> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("JsonCompatRead").finish()
-    }
-}
-
-impl<R: Read> JsonCompatRead<R> {
-    /// Wraps another reader.
-    pub fn wrap(reader: R) -> JsonCompatRead<R> {
-        JsonCompatRead {
-            reader,
-            state: State::Initial,
-        }
-    }
-}
-
-impl<R: Read> Read for JsonCompatRead<R> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let read = io::Read::read(&mut self.reader, buf)?;
-        self.state = translate_slice_impl(&mut buf[..read], self.state);
-        Ok(read)
-    }
-}
-
-fn translate_slice_impl(bytes: &mut [u8], mut state: State) -> State {
-    for c in bytes {
-        let rv = match (state, *c) {
-            (State::Initial, b'N') => (State::NaN0, b'0'),
-            (State::NaN0, b'a') => (State::NaN1, b'.'),
-            (State::NaN1, b'N') => (State::Initial, b'0'),
-            (State::Initial, b'I') => (State::Infinity0, b'0'),
-            (State::Infinity0, b'n') => (State::Infinity1, b'.'),
-            (State::Infinity1, b'f') => (State::Infinity2, b'0'),
-            (State::Infinity2, b'i') => (State::Infinity3, b' '),
-            (State::Infinity3, b'n') => (State::Infinity4, b' '),
-            (State::Infinity4, b'i') => (State::Infinity5, b' '),
-            (State::Infinity5, b't') => (State::Infinity6, b' '),
-            (State::Infinity6, b'y') => (State::Initial, b' '),
-            (State::Initial, b'"') => (State::Quoted, b'"'),
-            (State::Quoted, b'\\') => (State::QuotedEscape, b'\\'),
-            (State::QuotedEscape, c) => (State::Quoted, c),
-            (State::Quoted, b'"') => (State::Initial, b'"'),
-            (State::Quoted, c) | (State::Initial, c) => (state, c),
-            (_, c) => (State::Initial, c),
-        };
-        state = rv.0;
-        *c = rv.1;
-    }
-    state
-}
-
-/// Translates a slice in place.
-///
-/// This works the same as the `JsonCompatRead` struct but instead converts a
-/// slice in place.  This is useful when working with JSON in slices.
-pub fn translate_slice(bytes: &mut [u8]) {
-    translate_slice_impl(bytes, State::Initial);
-}
-
-#[test]
-fn test_reader_simple() {
-    let json = r#"{"nan":0.0,"inf":Infinity,"-inf":-Infinity}"#;
-    assert_eq!(json.len(), 43);
-    let mut rdr = JsonCompatRead::wrap(json.as_bytes());
-    let mut rv = String::new();
-    let read = rdr.read_to_string(&mut rv).unwrap();
-    assert_eq!(read, 43);
-    assert_eq!(rv, "{\"nan\":0.0,\"inf\":0.0     ,\"-inf\":-0.0     }");
-}
-
-#[test]
-fn test_reader_string() {
-    let json = r#"{"nan":"nan","Infinity":"-Infinity","other":NaN}"#;
-    assert_eq!(json.len(), 48);
-    let mut rdr = JsonCompatRead::wrap(json.as_bytes());
-    let mut rv = String::new();
-    let read = rdr.read_to_string(&mut rv).unwrap();
-    assert_eq!(read, 48);
-    assert_eq!(
-        rv,
-        "{\"nan\":\"nan\",\"Infinity\":\"-Infinity\",\"other\":0.0}"
-    );
-}
-
-#[test]
-fn test_reader_string_escaping() {
-    let json = r#""NaN\"NaN\"NaN""#;
-    assert_eq!(json.len(), 15);
-    let mut rdr = JsonCompatRead::wrap(json.as_bytes());
-    let mut rv = String::new();
-    let read = rdr.read_to_string(&mut rv).unwrap();
-    assert_eq!(read, 15);
-    assert_eq!(rv, r#""NaN\"NaN\"NaN""#);
-}
-
-#[test]
-fn test_translate_slice() {
-    let mut json = br#"{"nan":"nan","Infinity":"-Infinity","other":NaN}"#.to_vec();
-    translate_slice(&mut json[..]);
-    assert_eq!(
-        &json[..],
-        &b"{\"nan\":\"nan\",\"Infinity\":\"-Infinity\",\"other\":0.0}"[..]
-    );
-}
-
-#[test]
-fn test_translate_slice_invalid() {
-    let mut json = br#"Inferior,Nax,null,"Infinity",Nen"#.to_vec();
-    translate_slice(&mut json[..]);
-    println!("{}", String::from_utf8_lossy(&json));
-    assert_eq!(&json[..], &br#"0.0erior,0.x,null,"Infinity",0en"#[..]);
-}
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "serde")]
+pub use self::de::{from_reader_lossless, from_slice_lossless, from_str_lossless, Deserializer};
+
+#[cfg(feature = "serde")]
+mod ndjson_read;
+#[cfg(feature = "serde")]
+pub use self::ndjson_read::{NdjsonReader, NdjsonSlice};
+
+#[cfg(feature = "serde")]
+mod float;
+#[cfg(feature = "serde")]
+pub use self::float::PyFloat;
+
+#[cfg(feature = "serde")]
+mod value;
+#[cfg(feature = "serde")]
+pub use self::value::{from_reader_value, from_slice_value, Dialect, NonFinitePolicy, PyValue};
+
+#[cfg(feature = "serde")]
+pub mod nan_as_none;
+#[cfg(feature = "serde")]
+pub mod nested_python_json;
+#[cfg(feature = "serde")]
+pub mod non_finite_as_string;
+
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "serde")]
+pub use self::ser::{
+    to_string_python, to_vec_python, to_writer_python, NullFormatter, PythonFormatter,
+    PythonNdjsonWriter, PythonSerializer,
+};
+
+#[cfg(feature = "simd_json")]
+mod simd;
+#[cfg(feature = "simd_json")]
+pub use self::simd::from_slice_simd;
+
+#[cfg(feature = "sonic_rs")]
+mod sonic;
+#[cfg(feature = "sonic_rs")]
+pub use self::sonic::{from_slice_sonic, from_str_sonic};
+
+#[cfg(feature = "json5")]
+mod json5;
+#[cfg(feature = "json5")]
+pub use self::json5::{from_reader_json5, from_slice_json5};
+
+#[cfg(feature = "serde_transcode")]
+mod transcode;
+#[cfg(feature = "serde_transcode")]
+pub use self::transcode::*;
+
+#[cfg(feature = "serde")]
+mod canonical;
+#[cfg(feature = "serde")]
+pub use self::canonical::*;
+
+#[cfg(feature = "serde")]
+mod diff;
+#[cfg(feature = "serde")]
+pub use self::diff::*;
+
+#[cfg(feature = "serde")]
+mod shadow;
+#[cfg(feature = "serde")]
+pub use self::shadow::*;
+
+#[cfg(feature = "serde")]
+mod audit;
+#[cfg(feature = "serde")]
+pub use self::audit::*;
+
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "rayon")]
+pub use self::parallel::translate_slice_parallel;
+
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(all(feature = "mmap", feature = "serde"))]
+pub use self::mmap::from_file_mmap;
+#[cfg(feature = "mmap")]
+pub use self::mmap::translate_file_mmap;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "codec")]
+mod codec;
+#[cfg(feature = "codec")]
+pub use self::codec::*;
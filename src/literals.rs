@@ -0,0 +1,245 @@
+use crate::error::{Error, ErrorKind};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Initial,
+    Quoted,
+    QuotedEscape,
+    Token { pair: usize, len: usize },
+}
+
+/// A string-aware streaming replacer for arbitrary literals.
+///
+/// This generalizes the machinery that is used to translate `NaN` and
+/// `Infinity` to other literals.  Every literal is replaced by another literal
+/// of the same length so that the replacement can happen in place and
+/// offsets in the document do not change.  Literals inside of JSON strings
+/// are left alone.
+///
+/// Like the built-in translation, the replacement is performed eagerly: a
+/// prefix of a literal is already rewritten before the rest of it was seen.
+/// This is what makes it possible to translate chunk by chunk without
+/// buffering.
+///
+/// ```
+/// # use python_json_read_adapter::replace_literals;
+/// let replacer = replace_literals(&[
+///     (b"True", b"true"),
+///     (b"False", b"false"),
+///     (b"None", b"null"),
+/// ]).unwrap();
+/// let mut json = br#"{"x": True, "y": None, "z": "None"}"#.to_vec();
+/// replacer.translate_slice(&mut json);
+/// assert_eq!(&json[..], br#"{"x": true, "y": null, "z": "None"}"#);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LiteralReplacer {
+    pairs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Creates a `LiteralReplacer` for the given `(from, to)` pairs.
+///
+/// This is a shortcut for `LiteralReplacer::new`.
+pub fn replace_literals(pairs: &[(&[u8], &[u8])]) -> Result<LiteralReplacer, Error> {
+    LiteralReplacer::new(pairs)
+}
+
+impl LiteralReplacer {
+    /// Creates a replacer for the given `(from, to)` pairs.
+    ///
+    /// This fails if a literal is empty, if a literal and its replacement
+    /// differ in length, if a literal contains a quote or backslash, or if
+    /// two literals share a prefix but their replacements do not.  The last
+    /// restriction exists because the shared prefix is rewritten before it's
+    /// known which of the literals is being replaced.
+    pub fn new(pairs: &[(&[u8], &[u8])]) -> Result<LiteralReplacer, Error> {
+        for (idx, &(from, to)) in pairs.iter().enumerate() {
+            if from.is_empty() {
+                return Err(Error::with_detail(
+                    ErrorKind::InvalidReplacement,
+                    "literal is empty",
+                ));
+            }
+            if from.len() != to.len() {
+                return Err(Error::with_detail(
+                    ErrorKind::InvalidReplacement,
+                    "literal and replacement differ in length",
+                ));
+            }
+            if from.iter().any(|&b| b == b'"' || b == b'\\') {
+                return Err(Error::with_detail(
+                    ErrorKind::InvalidReplacement,
+                    "literal contains a quote or backslash",
+                ));
+            }
+            for &(other_from, other_to) in &pairs[..idx] {
+                let common = from
+                    .iter()
+                    .zip(other_from)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if to[..common] != other_to[..common] {
+                    return Err(Error::with_detail(
+                        ErrorKind::InvalidReplacement,
+                        "literals share a prefix but their replacements do not",
+                    ));
+                }
+            }
+        }
+        Ok(LiteralReplacer {
+            pairs: pairs
+                .iter()
+                .map(|&(from, to)| (from.to_vec(), to.to_vec()))
+                .collect(),
+        })
+    }
+
+    /// Replaces the literals in a slice in place.
+    pub fn translate_slice(&self, bytes: &mut [u8]) {
+        self.translator().feed(bytes);
+    }
+
+    /// Returns a resumable translator for a new document.
+    pub fn translator(&self) -> LiteralTranslator<'_> {
+        LiteralTranslator {
+            replacer: self,
+            state: State::Initial,
+            offset: 0,
+        }
+    }
+
+    /// Finds the first literal with the given prefix followed by `c`.
+    fn find(&self, prefix: &[u8], c: u8) -> Option<usize> {
+        self.pairs
+            .iter()
+            .position(|(from, _)| from.starts_with(prefix) && from.get(prefix.len()) == Some(&c))
+    }
+
+    fn is_extended(&self, prefix: &[u8]) -> bool {
+        self.pairs
+            .iter()
+            .any(|(from, _)| from.len() > prefix.len() && from.starts_with(prefix))
+    }
+
+    fn start_token(&self, prefix: &[u8], c: &mut u8) -> Option<State> {
+        let pair = self.find(prefix, *c)?;
+        let (from, to) = &self.pairs[pair];
+        let len = prefix.len() + 1;
+        *c = to[len - 1];
+        if len == from.len() && !self.is_extended(from) {
+            Some(State::Initial)
+        } else {
+            Some(State::Token { pair, len })
+        }
+    }
+
+    fn step(&self, state: State, c: &mut u8) -> State {
+        match state {
+            State::Quoted => match *c {
+                b'"' => State::Initial,
+                b'\\' => State::QuotedEscape,
+                _ => State::Quoted,
+            },
+            State::QuotedEscape => State::Quoted,
+            State::Token { pair, len } => {
+                let prefix = &self.pairs[pair].0[..len];
+                match self.start_token(prefix, c) {
+                    Some(state) => state,
+                    // the token ended (or was malformed), so this byte has to
+                    // be looked at in the initial state.
+                    None => self.step(State::Initial, c),
+                }
+            }
+            State::Initial => {
+                if *c == b'"' {
+                    State::Quoted
+                } else {
+                    self.start_token(&[], c).unwrap_or(State::Initial)
+                }
+            }
+        }
+    }
+}
+
+/// A resumable translator created by `LiteralReplacer::translator`.
+///
+/// This works like `Translator` but replaces the literals of the replacer.
+#[derive(Clone, Debug)]
+pub struct LiteralTranslator<'a> {
+    replacer: &'a LiteralReplacer,
+    state: State,
+    offset: u64,
+}
+
+impl<'a> LiteralTranslator<'a> {
+    /// Translates the next chunk of the document in place.
+    pub fn feed(&mut self, bytes: &mut [u8]) {
+        for c in bytes.iter_mut() {
+            self.state = self.replacer.step(self.state, c);
+        }
+        self.offset += bytes.len() as u64;
+    }
+
+    /// Returns the number of bytes fed so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Finishes the document.
+    ///
+    /// This fails if the document ended in the middle of a literal.
+    pub fn finish(self) -> Result<(), Error> {
+        match self.state {
+            State::Token { pair, len } if len < self.replacer.pairs[pair].0.len() => {
+                Err(Error::new(ErrorKind::UnexpectedEof, self.offset))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_replace_literals() {
+    let replacer = replace_literals(&[
+        (b"inf", b"0.0"),
+        (b"infinity", b"0.0     "),
+        (b"nan", b"0.0"),
+        (b"None", b"null"),
+    ])
+    .unwrap();
+    let mut json = br#"[inf, infinity, "nan\"inf", nan,None, iNone, infNone]"#.to_vec();
+    replacer.translate_slice(&mut json);
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        r#"[0.0, 0.0     , "nan\"inf", 0.0,null, 0null, 0.0null]"#
+    );
+}
+
+#[test]
+fn test_replace_literals_chunked() {
+    let replacer = replace_literals(&[(b"True", b"true"), (b"Truthy", b"truthy")]).unwrap();
+    let input = br#"[True,Truthy,"True",Tru"#;
+    for chunk_size in 1..input.len() {
+        let mut json = input.to_vec();
+        let mut translator = replacer.translator();
+        for chunk in json.chunks_mut(chunk_size) {
+            translator.feed(chunk);
+        }
+        assert_eq!(&json[..], br#"[true,truthy,"True",tru"#);
+        assert_eq!(
+            translator.finish().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+}
+
+#[test]
+fn test_replace_literals_invalid() {
+    let err = replace_literals(&[(b"None", b"null ")]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidReplacement);
+    assert_eq!(err.offset(), None);
+    assert!(replace_literals(&[(b"", b"")]).is_err());
+    assert!(replace_literals(&[(b"a\"", b"bb")]).is_err());
+    assert!(replace_literals(&[(b"Nab", b"xyz"), (b"Nac", b"xzz")]).is_err());
+    assert!(replace_literals(&[(b"Nab", b"xyz"), (b"Nac", b"xyy")]).is_ok());
+}
@@ -0,0 +1,122 @@
+use crate::tables;
+
+/// The decimal commas found by `repair_decimal_commas`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DecimalCommaReport {
+    /// Offsets of the commas that were rewritten to a dot.
+    pub repaired: Vec<usize>,
+    /// Offsets of the commas that look like decimal commas but were left
+    /// alone because they could also separate two array items.
+    pub ambiguous: Vec<usize>,
+}
+
+impl DecimalCommaReport {
+    /// Returns `true` if no decimal commas were found.
+    pub fn is_empty(&self) -> bool {
+        self.repaired.is_empty() && self.ambiguous.is_empty()
+    }
+}
+
+/// Rewrites decimal commas in numbers to dots in place.
+///
+/// Python code that formats floats with a locale aware function can end up
+/// emitting numbers like `1,5`.  A comma directly between two runs of digits
+/// is treated as a decimal comma if it cannot be a separator, which is the
+/// case in object values and at the top level.  Inside of arrays `[1,5]` is
+/// valid JSON, so such commas are only flagged as ambiguous in the returned
+/// report.  Numbers that already contain a dot or an exponent are left
+/// alone.
+///
+/// ```
+/// # use python_json_read_adapter::repair_decimal_commas;
+/// let mut json = br#"{"x": 1,5, "y": [2,5]}"#.to_vec();
+/// let report = repair_decimal_commas(&mut json);
+/// assert_eq!(&json[..], br#"{"x": 1.5, "y": [2,5]}"#);
+/// assert_eq!(report.repaired, vec![7]);
+/// assert_eq!(report.ambiguous, vec![18]);
+/// ```
+pub fn repair_decimal_commas(bytes: &mut [u8]) -> DecimalCommaReport {
+    let mut report = DecimalCommaReport::default();
+    let mut containers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut in_number = false;
+    // whether the current number only consists of an optional sign and digits
+    let mut plain_number = false;
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let byte = bytes[idx];
+        idx += 1;
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        if tables::is(byte, tables::DIGIT) || byte == b'-' {
+            if !in_number {
+                in_number = true;
+                plain_number = true;
+            }
+            continue;
+        } else if in_number && matches!(byte, b'.' | b'e' | b'E' | b'+') {
+            plain_number = false;
+            continue;
+        }
+
+        if byte == b',' && in_number && plain_number {
+            let digits = bytes[idx..]
+                .iter()
+                .take_while(|&&b| tables::is(b, tables::DIGIT))
+                .count();
+            let terminated = match bytes.get(idx + digits) {
+                Some(&b) => tables::is(b, tables::STRUCTURAL | tables::WHITESPACE),
+                None => true,
+            };
+            if digits > 0 && terminated {
+                if containers.last() == Some(&b'[') {
+                    report.ambiguous.push(idx - 1);
+                } else {
+                    bytes[idx - 1] = b'.';
+                    report.repaired.push(idx - 1);
+                    idx += digits;
+                }
+            }
+        }
+        in_number = false;
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => containers.push(byte),
+            b'}' | b']' => {
+                containers.pop();
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+#[test]
+fn test_repair_decimal_commas() {
+    let mut json = br#"1,25"#.to_vec();
+    let report = repair_decimal_commas(&mut json);
+    assert_eq!(&json[..], b"1.25");
+    assert_eq!(report.repaired, vec![1]);
+
+    let mut json = br#"{"a":-3,5,"b":"1,5","c":1.5,5,"d":1,"e":2}"#.to_vec();
+    let report = repair_decimal_commas(&mut json);
+    assert_eq!(&json[..], br#"{"a":-3.5,"b":"1,5","c":1.5,5,"d":1,"e":2}"#);
+    assert_eq!(report.repaired, vec![7]);
+    assert!(report.ambiguous.is_empty());
+
+    let mut json = br#"[1,5, 1, 5, {"x": [0,75]}]"#.to_vec();
+    let report = repair_decimal_commas(&mut json);
+    assert_eq!(&json[..], br#"[1,5, 1, 5, {"x": [0,75]}]"#);
+    assert_eq!(report.ambiguous, vec![2, 20]);
+    assert!(report.repaired.is_empty());
+}
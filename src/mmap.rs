@@ -0,0 +1,76 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::{MmapMut, MmapOptions};
+
+use crate::translator::Translator;
+
+/// Maps a file into memory and translates it in place.
+///
+/// The map is private, so the translation never reaches the file.  Pages
+/// are only copied once they are written to, which for most documents is
+/// only a small part of the file.
+///
+/// Like with every memory map the behavior is undefined if the file is
+/// modified by another process while it is mapped.
+pub fn translate_file_mmap<P: AsRef<Path>>(path: P) -> io::Result<MmapMut> {
+    let mut map = map_file(path.as_ref())?;
+    // without options the translation cannot fail
+    let _ = Translator::new().feed(&mut map);
+    Ok(map)
+}
+
+fn map_file(path: &Path) -> io::Result<MmapMut> {
+    let file = File::open(path)?;
+    // SAFETY: the map is private and the caveat about concurrent
+    // modifications is documented.
+    unsafe { MmapOptions::new().map_copy(&file) }
+}
+
+/// Deserialize an instance of type `T` from a file of JSON.
+///
+/// This works like `from_reader` on the file but translates and parses a
+/// memory map of it.  See `translate_file_mmap`.
+#[cfg(feature = "serde")]
+pub fn from_file_mmap<T, P>(path: P) -> serde_json::Result<T>
+where
+    T: serde_self::de::DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let mut map = map_file(path.as_ref()).map_err(serde_json::Error::io)?;
+    // like `from_reader` this fails on truncated tokens
+    let mut translator = Translator::new();
+    translator
+        .feed(&mut map)
+        .and_then(|_| translator.finish())
+        .map_err(|err| serde_json::Error::io(err.into()))?;
+    serde_json::from_slice(&map)
+}
+
+#[test]
+fn test_translate_file_mmap() {
+    let path = std::env::temp_dir().join(format!("pjra-mmap-{}.json", std::process::id()));
+    std::fs::write(&path, br#"{"x": [NaN, -Infinity], "y": "NaN"}"#).unwrap();
+    let map = translate_file_mmap(&path).unwrap();
+    assert_eq!(&map[..], br#"{"x": [0.0, -0.0     ], "y": "NaN"}"#);
+    drop(map);
+    // the file itself is left alone
+    assert_eq!(
+        std::fs::read(&path).unwrap(),
+        br#"{"x": [NaN, -Infinity], "y": "NaN"}"#
+    );
+
+    #[cfg(feature = "serde")]
+    {
+        let rv: serde_json::Value = from_file_mmap(&path).unwrap();
+        assert_eq!(rv, serde_json::json!({"x": [0.0, -0.0], "y": "NaN"}));
+        std::fs::write(&path, b"").unwrap();
+        assert!(translate_file_mmap(&path).unwrap().is_empty());
+        assert!(from_file_mmap::<f64, _>(&path).is_err());
+        std::fs::write(&path, b"Infin").unwrap();
+        assert!(from_file_mmap::<f64, _>(&path).is_err());
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert!(translate_file_mmap(&path).is_err());
+}
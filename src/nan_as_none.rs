@@ -0,0 +1,57 @@
+//! Serde helpers that map NaN to `None`.
+//!
+//! This is meant for `Option<f64>` fields of data produced by pandas and
+//! similar tools which use NaN for missing values:
+//!
+//! ```ignore
+//! #[derive(Deserialize, Serialize)]
+//! struct Row {
+//!     #[serde(with = "python_json_read_adapter::nan_as_none")]
+//!     value: Option<f64>,
+//! }
+//! ```
+//!
+//! On deserialization both `null` and NaN become `None`.  Note that the
+//! translating functions like `from_slice` turn `NaN` into `0.0` before
+//! deserialization, so for the NaN mapping to take effect the document has
+//! to be parsed with `from_slice_lossless` or its siblings.  `None` is
+//! serialized as `null`.
+use serde_self::de::{Deserialize, Deserializer};
+use serde_self::ser::Serializer;
+
+/// Serializes `None` as `null` and other values as numbers.
+pub fn serialize<S>(value: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match *value {
+        Some(value) if !value.is_nan() => serializer.serialize_some(&value),
+        _ => serializer.serialize_none(),
+    }
+}
+
+/// Deserializes `null` and NaN as `None`.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<f64>::deserialize(deserializer)?.filter(|value| !value.is_nan()))
+}
+
+#[test]
+fn test_nan_as_none() {
+    let parse = |json: &str| deserialize(&mut crate::Deserializer::from_str(json)).unwrap();
+    assert_eq!(parse("NaN"), None);
+    assert_eq!(parse("null"), None);
+    assert_eq!(parse("1.5"), Some(1.5));
+    assert_eq!(parse("-Infinity"), Some(f64::NEG_INFINITY));
+
+    let write = |value| {
+        let mut rv = Vec::new();
+        serialize(&value, &mut serde_json::Serializer::new(&mut rv)).unwrap();
+        String::from_utf8(rv).unwrap()
+    };
+    assert_eq!(write(None), "null");
+    assert_eq!(write(Some(f64::NAN)), "null");
+    assert_eq!(write(Some(2.0)), "2.0");
+}
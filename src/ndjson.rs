@@ -0,0 +1,276 @@
+/// The kind of line ending that terminated a record.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LineEnding {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// A lone `\r`.
+    Cr,
+    /// U+2028 LINE SEPARATOR
+    LineSeparator,
+    /// U+2029 PARAGRAPH SEPARATOR
+    ParagraphSeparator,
+}
+
+/// Configures which line endings separate records in newline-delimited JSON.
+///
+/// `\n` and `\r\n` always separate records.  Lone carriage returns and the
+/// Unicode separators U+2028 and U+2029 can be enabled in addition.  As the
+/// Unicode separators are valid inside of JSON strings they only separate
+/// records outside of strings.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LineSeparators {
+    cr: bool,
+    unicode: bool,
+}
+
+impl LineSeparators {
+    /// Only `\n` and `\r\n` separate records.
+    pub fn new() -> LineSeparators {
+        LineSeparators::default()
+    }
+
+    /// All supported line endings separate records.
+    pub fn all() -> LineSeparators {
+        LineSeparators {
+            cr: true,
+            unicode: true,
+        }
+    }
+
+    /// Enables or disables lone `\r` as separator.
+    pub fn cr(mut self, yes: bool) -> LineSeparators {
+        self.cr = yes;
+        self
+    }
+
+    /// Enables or disables U+2028 and U+2029 as separators.
+    pub fn unicode(mut self, yes: bool) -> LineSeparators {
+        self.unicode = yes;
+        self
+    }
+}
+
+/// Counts of the line endings seen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LineEndingStats {
+    /// The number of `\n` line endings.
+    pub lf: usize,
+    /// The number of `\r\n` line endings.
+    pub crlf: usize,
+    /// The number of lone `\r` line endings.
+    pub cr: usize,
+    /// The number of U+2028 line endings.
+    pub line_separator: usize,
+    /// The number of U+2029 line endings.
+    pub paragraph_separator: usize,
+}
+
+impl LineEndingStats {
+    /// Checks if more than one kind of line ending was seen.
+    pub fn is_mixed(&self) -> bool {
+        [
+            self.lf,
+            self.crlf,
+            self.cr,
+            self.line_separator,
+            self.paragraph_separator,
+        ]
+        .iter()
+        .filter(|&&x| x > 0)
+        .count()
+            > 1
+    }
+
+    pub(crate) fn record(&mut self, ending: LineEnding) {
+        let count = match ending {
+            LineEnding::Lf => &mut self.lf,
+            LineEnding::CrLf => &mut self.crlf,
+            LineEnding::Cr => &mut self.cr,
+            LineEnding::LineSeparator => &mut self.line_separator,
+            LineEnding::ParagraphSeparator => &mut self.paragraph_separator,
+        };
+        *count += 1;
+    }
+}
+
+/// A line found by the `LineScanner`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Line {
+    /// The length of the line without the line ending.
+    pub len: usize,
+    /// The length of the line ending.
+    pub ending_len: usize,
+    /// The kind of line ending.
+    pub ending: LineEnding,
+}
+
+/// Finds line endings in a buffer that grows at the end.
+///
+/// The scanner remembers how far it got so that bytes are only looked at
+/// once even if the line ending arrives much later.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LineScanner {
+    pos: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl LineScanner {
+    /// Finds the end of the first line in `buf`.
+    ///
+    /// Returns `None` if more data is needed.  Once a line is returned the
+    /// caller must remove it (and its ending) from the front of the buffer.
+    pub fn next_line(&mut self, buf: &[u8], seps: LineSeparators, eof: bool) -> Option<Line> {
+        let mut idx = self.pos;
+        while let Some(&b) = buf.get(idx) {
+            let found = match b {
+                b'\n' => Some((1, LineEnding::Lf)),
+                b'\r' => match buf.get(idx + 1) {
+                    Some(b'\n') => Some((2, LineEnding::CrLf)),
+                    Some(_) if seps.cr => Some((1, LineEnding::Cr)),
+                    None if !eof => break,
+                    None if seps.cr => Some((1, LineEnding::Cr)),
+                    _ => None,
+                },
+                0xe2 if seps.unicode && !self.in_string => match buf.get(idx + 1..idx + 3) {
+                    Some([0x80, 0xa8]) => Some((3, LineEnding::LineSeparator)),
+                    Some([0x80, 0xa9]) => Some((3, LineEnding::ParagraphSeparator)),
+                    None if !eof => break,
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some((ending_len, ending)) = found {
+                *self = LineScanner::default();
+                return Some(Line {
+                    len: idx,
+                    ending_len,
+                    ending,
+                });
+            }
+            if self.escaped {
+                self.escaped = false;
+            } else if b == b'\\' && self.in_string {
+                self.escaped = true;
+            } else if b == b'"' {
+                self.in_string = !self.in_string;
+            }
+            idx += 1;
+        }
+        self.pos = idx;
+        None
+    }
+}
+
+/// An iterator over the lines of a slice.
+///
+/// Created by `split_lines`.
+#[derive(Clone, Debug)]
+pub struct SplitLines<'a> {
+    bytes: &'a [u8],
+    separators: LineSeparators,
+    scanner: LineScanner,
+    line_endings: LineEndingStats,
+}
+
+/// Splits a slice of newline-delimited JSON into lines.
+///
+/// The iterator yields every line together with the line ending that
+/// terminated it.  The last line has no line ending if the slice does not end
+/// with one.
+pub fn split_lines(bytes: &[u8], separators: LineSeparators) -> SplitLines<'_> {
+    SplitLines {
+        bytes,
+        separators,
+        scanner: LineScanner::default(),
+        line_endings: LineEndingStats::default(),
+    }
+}
+
+impl<'a> SplitLines<'a> {
+    /// Returns the counts of the line endings seen so far.
+    pub fn line_endings(&self) -> LineEndingStats {
+        self.line_endings
+    }
+}
+
+impl<'a> Iterator for SplitLines<'a> {
+    type Item = (&'a [u8], Option<LineEnding>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        match self.scanner.next_line(self.bytes, self.separators, true) {
+            Some(line) => {
+                self.line_endings.record(line.ending);
+                let (rv, rest) = self.bytes.split_at(line.len);
+                self.bytes = &rest[line.ending_len..];
+                Some((rv, Some(line.ending)))
+            }
+            None => Some((std::mem::take(&mut self.bytes), None)),
+        }
+    }
+}
+
+#[test]
+fn test_split_lines() {
+    let mut iter = split_lines(b"[1]\r\n[2]\r[3]\n\n[4]", LineSeparators::new().cr(true));
+    assert_eq!(iter.next(), Some((&b"[1]"[..], Some(LineEnding::CrLf))));
+    assert_eq!(iter.next(), Some((&b"[2]"[..], Some(LineEnding::Cr))));
+    assert_eq!(iter.next(), Some((&b"[3]"[..], Some(LineEnding::Lf))));
+    assert_eq!(iter.next(), Some((&b""[..], Some(LineEnding::Lf))));
+    assert_eq!(iter.next(), Some((&b"[4]"[..], None)));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.line_endings().lf, 2);
+}
+
+#[test]
+fn test_line_scanner() {
+    let seps = LineSeparators::all();
+    let mut scanner = LineScanner::default();
+    assert_eq!(scanner.next_line(b"[1]\r", seps, false), None);
+    assert_eq!(
+        scanner.next_line(b"[1]\r\n", seps, false),
+        Some(Line {
+            len: 3,
+            ending_len: 2,
+            ending: LineEnding::CrLf
+        })
+    );
+    assert_eq!(
+        scanner.next_line(b"[1]\r[2]", seps, false).unwrap().ending,
+        LineEnding::Cr
+    );
+    assert_eq!(
+        scanner.next_line(b"[1]\r", seps, true).unwrap().ending,
+        LineEnding::Cr
+    );
+    assert_eq!(
+        scanner.next_line(b"[1]\r[2]", LineSeparators::new(), true),
+        None
+    );
+
+    let mut scanner = LineScanner::default();
+    let doc = "[\"a\u{2028}\\\"b\u{2028}\"]\u{2029}[2]";
+    let line = scanner.next_line(doc.as_bytes(), seps, false).unwrap();
+    assert_eq!(line.ending, LineEnding::ParagraphSeparator);
+    assert_eq!(&doc[..line.len], "[\"a\u{2028}\\\"b\u{2028}\"]");
+    assert_eq!(
+        scanner.next_line(doc.as_bytes(), LineSeparators::new(), true),
+        None
+    );
+}
+
+#[test]
+fn test_line_ending_stats() {
+    let mut stats = LineEndingStats::default();
+    stats.record(LineEnding::Lf);
+    stats.record(LineEnding::Lf);
+    assert!(!stats.is_mixed());
+    stats.record(LineEnding::CrLf);
+    assert!(stats.is_mixed());
+}
@@ -0,0 +1,247 @@
+use std::io;
+use std::marker::PhantomData;
+
+use serde_self::de;
+
+use crate::ndjson::{LineEndingStats, LineScanner, LineSeparators};
+use crate::translate_slice;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// An iterator over the records of newline-delimited Python JSON in a reader.
+///
+/// Every line is treated as an independent document: the translation state
+/// is reset at the start of each line so that a malformed record only fails
+/// that record and iteration continues with the next line.  Blank lines are
+/// skipped.  IO errors end the iteration.
+///
+/// ```
+/// # use python_json_read_adapter::NdjsonReader;
+/// let stream = &b"[NaN]\n[1, 2\n\n[Infinity]\n"[..];
+/// let records: Vec<_> = NdjsonReader::<_, Vec<f64>>::new(stream).collect();
+/// assert_eq!(records.len(), 3);
+/// assert_eq!(records[0].as_ref().unwrap(), &vec![0.0]);
+/// assert!(records[1].is_err());
+/// assert_eq!(records[2].as_ref().unwrap(), &vec![0.0]);
+/// ```
+pub struct NdjsonReader<R, T> {
+    rdr: R,
+    buf: Vec<u8>,
+    scanner: LineScanner,
+    separators: LineSeparators,
+    line_endings: LineEndingStats,
+    eof: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: io::Read, T> NdjsonReader<R, T> {
+    /// Creates a new reader.
+    pub fn new(rdr: R) -> NdjsonReader<R, T> {
+        NdjsonReader {
+            rdr,
+            buf: Vec::new(),
+            scanner: LineScanner::default(),
+            separators: LineSeparators::default(),
+            line_endings: LineEndingStats::default(),
+            eof: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Configures which line endings separate records.
+    pub fn line_separators(mut self, separators: LineSeparators) -> NdjsonReader<R, T> {
+        self.separators = separators;
+        self
+    }
+
+    /// Returns the counts of the line endings seen so far.
+    pub fn line_endings(&self) -> LineEndingStats {
+        self.line_endings
+    }
+
+    /// Returns the next line or `None` once the reader is exhausted.
+    fn next_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(line) = self.scanner.next_line(&self.buf, self.separators, self.eof) {
+                self.line_endings.record(line.ending);
+                let mut rv: Vec<u8> = self.buf.drain(..line.len + line.ending_len).collect();
+                rv.truncate(line.len);
+                return Ok(Some(rv));
+            }
+            if self.eof {
+                self.scanner = LineScanner::default();
+                return Ok(if self.buf.is_empty() {
+                    None
+                } else {
+                    Some(std::mem::take(&mut self.buf))
+                });
+            }
+            let len = self.buf.len();
+            self.buf.resize(len + CHUNK_SIZE, 0);
+            let rv = self.rdr.read(&mut self.buf[len..]);
+            match rv {
+                Ok(read) => {
+                    self.buf.truncate(len + read);
+                    self.eof = read == 0;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => self.buf.truncate(len),
+                Err(err) => {
+                    self.buf.clear();
+                    self.eof = true;
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+impl<R: io::Read, T: de::DeserializeOwned> Iterator for NdjsonReader<R, T> {
+    type Item = serde_json::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut line = match self.next_line() {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(serde_json::Error::io(err))),
+            };
+            if is_blank(&line) {
+                continue;
+            }
+            translate_slice(&mut line);
+            return Some(serde_json::from_slice(&line));
+        }
+    }
+}
+
+impl<R, T> std::fmt::Debug for NdjsonReader<R, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NdjsonReader")
+            .field("separators", &self.separators)
+            .field("line_endings", &self.line_endings)
+            .field("eof", &self.eof)
+            .finish()
+    }
+}
+
+/// An iterator over the records of newline-delimited Python JSON in a slice.
+///
+/// This works like `NdjsonReader` but translates every line in place, so the
+/// records can borrow from the slice.
+///
+/// ```
+/// # use python_json_read_adapter::NdjsonSlice;
+/// let mut json = b"[\"a\", NaN]\r\n[\"b\", -Infinity]".to_vec();
+/// let records = NdjsonSlice::<(&str, f64)>::new(&mut json)
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(records, vec![("a", 0.0), ("b", -0.0)]);
+/// ```
+pub struct NdjsonSlice<'a, T> {
+    bytes: &'a mut [u8],
+    separators: LineSeparators,
+    line_endings: LineEndingStats,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> NdjsonSlice<'a, T> {
+    /// Creates a new iterator over the lines in `bytes`.
+    pub fn new(bytes: &'a mut [u8]) -> NdjsonSlice<'a, T> {
+        NdjsonSlice {
+            bytes,
+            separators: LineSeparators::default(),
+            line_endings: LineEndingStats::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Configures which line endings separate records.
+    pub fn line_separators(mut self, separators: LineSeparators) -> NdjsonSlice<'a, T> {
+        self.separators = separators;
+        self
+    }
+
+    /// Returns the counts of the line endings seen so far.
+    pub fn line_endings(&self) -> LineEndingStats {
+        self.line_endings
+    }
+}
+
+impl<'a, T: de::Deserialize<'a>> Iterator for NdjsonSlice<'a, T> {
+    type Item = serde_json::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.bytes.is_empty() {
+            let bytes = std::mem::take(&mut self.bytes);
+            let line = match LineScanner::default().next_line(bytes, self.separators, true) {
+                Some(line) => {
+                    self.line_endings.record(line.ending);
+                    let (rv, rest) = bytes.split_at_mut(line.len);
+                    self.bytes = &mut rest[line.ending_len..];
+                    rv
+                }
+                None => bytes,
+            };
+            if is_blank(line) {
+                continue;
+            }
+            translate_slice(line);
+            return Some(serde_json::from_slice(line));
+        }
+        None
+    }
+}
+
+impl<'a, T> std::fmt::Debug for NdjsonSlice<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NdjsonSlice")
+            .field("separators", &self.separators)
+            .field("line_endings", &self.line_endings)
+            .finish()
+    }
+}
+
+#[test]
+fn test_ndjson_reader() {
+    let mut json = Vec::new();
+    for idx in 0..2000 {
+        json.extend_from_slice(format!("{{\"idx\": {}, \"v\": NaN}}\n", idx).as_bytes());
+    }
+    json.extend_from_slice(b"\"Infinity\r\n[\"unterminated\n  \n[-Infinity]");
+    let records: Vec<serde_json::Result<serde_json::Value>> =
+        NdjsonReader::new(crate::test_support::FragmentingReader::new(&json[..], 7)).collect();
+    assert_eq!(records.len(), 2003);
+    assert_eq!(
+        records[1999].as_ref().unwrap(),
+        &serde_json::json!({"idx": 1999, "v": 0.0})
+    );
+    assert!(records[2000].is_err());
+    assert!(records[2001].is_err());
+    assert_eq!(records[2002].as_ref().unwrap(), &serde_json::json!([-0.0]));
+
+    let mut rdr = NdjsonReader::<_, Vec<f64>>::new(&b"[1]\r[2]\r\n"[..])
+        .line_separators(LineSeparators::new().cr(true));
+    assert_eq!(rdr.next().unwrap().unwrap(), vec![1.0]);
+    assert_eq!(rdr.next().unwrap().unwrap(), vec![2.0]);
+    assert!(rdr.next().is_none());
+    assert_eq!(rdr.line_endings().cr, 1);
+    assert_eq!(rdr.line_endings().crlf, 1);
+}
+
+#[test]
+fn test_ndjson_slice() {
+    let mut json = b"\n[NaN]\n[Nax]\n\"a\\u2028\"\n".to_vec();
+    let mut iter = NdjsonSlice::<serde_json::Value>::new(&mut json);
+    assert_eq!(iter.next().unwrap().unwrap(), serde_json::json!([0.0]));
+    assert!(iter.next().unwrap().is_err());
+    assert_eq!(
+        iter.next().unwrap().unwrap(),
+        serde_json::json!("a\u{2028}")
+    );
+    assert!(iter.next().is_none());
+    assert_eq!(iter.line_endings().lf, 4);
+}
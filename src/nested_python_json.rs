@@ -0,0 +1,61 @@
+//! Serde helpers for JSON documents embedded in strings.
+//!
+//! Python services like to store JSON documents as strings inside of other
+//! JSON documents, and the inner document has the same problem with `NaN`
+//! and `Infinity` as the outer one.  This module parses such a string field
+//! into the inner type in one step:
+//!
+//! ```ignore
+//! #[derive(Deserialize, Serialize)]
+//! struct Event {
+//!     #[serde(with = "python_json_read_adapter::nested_python_json")]
+//!     payload: Payload,
+//! }
+//! ```
+//!
+//! On deserialization the string is translated like with `from_slice` and
+//! then deserialized.  On serialization the value is written with
+//! `to_string_python` and embedded as a string.
+use serde_self::de::{self, DeserializeOwned, Deserializer};
+use serde_self::ser::{self, Serialize, Serializer};
+
+/// Serializes the value as a string of Python flavoured JSON.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let json = crate::to_string_python(value).map_err(ser::Error::custom)?;
+    serializer.serialize_str(&json)
+}
+
+/// Deserializes the value from a string of Python flavoured JSON.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DeserializeOwned,
+    D: Deserializer<'de>,
+{
+    let json: String = de::Deserialize::deserialize(deserializer)?;
+    crate::from_slice(&mut json.into_bytes()).map_err(de::Error::custom)
+}
+
+#[test]
+fn test_nested_python_json() {
+    let parse = |json: &str| -> serde_json::Result<Vec<f64>> {
+        deserialize(&mut serde_json::Deserializer::from_str(json))
+    };
+    assert_eq!(
+        parse(r#""[1.5, NaN, -Infinity]""#).unwrap(),
+        vec![1.5, 0.0, -0.0]
+    );
+    assert!(parse(r#""[1.5""#).is_err());
+    assert!(parse("[1.5]").is_err());
+
+    let mut rv = Vec::new();
+    serialize(
+        &vec![f64::NAN, 2.0],
+        &mut serde_json::Serializer::new(&mut rv),
+    )
+    .unwrap();
+    assert_eq!(rv, br#""[NaN,2.0]""#);
+}
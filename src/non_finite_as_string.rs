@@ -0,0 +1,106 @@
+//! Serde helpers that encode non-finite floats as strings.
+//!
+//! Many JSON producers that need to be portable write NaN and the
+//! infinities as the strings `"NaN"`, `"Infinity"` and `"-Infinity"`.  This
+//! module implements that encoding for `f64` fields:
+//!
+//! ```ignore
+//! #[derive(Deserialize, Serialize)]
+//! struct Sample {
+//!     #[serde(with = "python_json_read_adapter::non_finite_as_string")]
+//!     value: f64,
+//! }
+//! ```
+//!
+//! Finite values are serialized as numbers.  On deserialization numbers and
+//! the three strings are accepted.
+use std::fmt;
+
+use serde_self::de::value::MapAccessDeserializer;
+use serde_self::de::{self, Deserialize, Deserializer, Visitor};
+use serde_self::ser::Serializer;
+
+/// Serializes non-finite values as strings and other values as numbers.
+pub fn serialize<S>(value: &f64, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if value.is_nan() {
+        serializer.serialize_str("NaN")
+    } else if value.is_infinite() && *value > 0.0 {
+        serializer.serialize_str("Infinity")
+    } else if value.is_infinite() {
+        serializer.serialize_str("-Infinity")
+    } else {
+        serializer.serialize_f64(*value)
+    }
+}
+
+/// Deserializes a number or one of the non-finite strings.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct NonFiniteVisitor;
+
+    impl<'de> Visitor<'de> for NonFiniteVisitor {
+        type Value = f64;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a number or \"NaN\", \"Infinity\" or \"-Infinity\"")
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<f64, E> {
+            Ok(value as f64)
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<f64, E> {
+            Ok(value as f64)
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<f64, E> {
+            Ok(value)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<f64, E> {
+            match value {
+                "NaN" => Ok(f64::NAN),
+                "Infinity" => Ok(f64::INFINITY),
+                "-Infinity" => Ok(f64::NEG_INFINITY),
+                _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+            }
+        }
+
+        // with arbitrary_precision serde_json hands out numbers as maps
+        fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<f64, A::Error> {
+            let number = serde_json::Number::deserialize(MapAccessDeserializer::new(map))?;
+            number
+                .as_f64()
+                .ok_or_else(|| de::Error::custom("number out of range"))
+        }
+    }
+
+    deserializer.deserialize_any(NonFiniteVisitor)
+}
+
+#[test]
+fn test_non_finite_as_string() {
+    let parse = |json: &str| deserialize(&mut serde_json::Deserializer::from_str(json));
+    assert!(parse("\"NaN\"").unwrap().is_nan());
+    assert_eq!(parse("\"Infinity\"").unwrap(), f64::INFINITY);
+    assert_eq!(parse("\"-Infinity\"").unwrap(), f64::NEG_INFINITY);
+    assert_eq!(parse("42").unwrap(), 42.0);
+    assert_eq!(parse("-1.5").unwrap(), -1.5);
+    assert!(parse("\"nan\"").is_err());
+    assert!(parse("null").is_err());
+
+    let write = |value| {
+        let mut rv = Vec::new();
+        serialize(&value, &mut serde_json::Serializer::new(&mut rv)).unwrap();
+        String::from_utf8(rv).unwrap()
+    };
+    assert_eq!(write(f64::NAN), "\"NaN\"");
+    assert_eq!(write(f64::INFINITY), "\"Infinity\"");
+    assert_eq!(write(f64::NEG_INFINITY), "\"-Infinity\"");
+    assert_eq!(write(0.5), "0.5");
+}
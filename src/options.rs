@@ -0,0 +1,225 @@
+use crate::backend::Backend;
+use crate::selftest::{self, SelfTestReport};
+
+/// Options that control the translation.
+///
+/// The default options translate `NaN` and `Infinity` without any limits,
+/// which is the behavior of `translate_slice` and `JsonCompatRead::wrap`.
+///
+/// ```
+/// # use python_json_read_adapter::{translate_slice_with_options, Options};
+/// let options = Options::new().max_replacements(1);
+/// let mut json = b"[NaN, NaN]".to_vec();
+/// assert!(translate_slice_with_options(&mut json, &options).is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub(crate) max_replacements: Option<usize>,
+    pub(crate) backend: Backend,
+    pub(crate) skip_prefix: Option<usize>,
+    pub(crate) repair_surrogates: bool,
+    pub(crate) case_insensitive: bool,
+    pub(crate) plus_infinity: bool,
+    pub(crate) reject_binary: bool,
+    pub(crate) count_big_ints: bool,
+    pub(crate) reject: bool,
+}
+
+impl Options {
+    /// Creates the default options.
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    /// Aborts the translation once more than `max` tokens were rewritten.
+    ///
+    /// This makes it possible to cheaply reject payloads that are grossly
+    /// malformed instead of sanitizing all of it.  Exceeding the budget fails
+    /// with `ErrorKind::BudgetExceeded`.
+    pub fn max_replacements(mut self, max: usize) -> Options {
+        self.max_replacements = Some(max);
+        self
+    }
+
+    /// Overrides the backend used to scan for tokens.
+    ///
+    /// By default the backend is picked for every chunk based on its size,
+    /// which should rarely need tuning.  With the `simd` or `memchr` feature
+    /// large chunks are scanned with `Backend::Simd` on x86_64 or with
+    /// `Backend::Memchr`.
+    pub fn backend(mut self, backend: Backend) -> Options {
+        self.backend = backend;
+        self
+    }
+
+    /// Skips up to `max` bytes of junk in front of the document.
+    ///
+    /// Python web frameworks and log wrappers like to put things in front of
+    /// JSON: byte order marks, shebang lines, log prefixes or the `)]}'`
+    /// prefix that protects against XSSI.  With this option everything up to
+    /// the first `{` or `[` that opens a document is overwritten with spaces,
+    /// which means the document itself has to be an object or an array.  The
+    /// bracket has to be followed by something that can start a value, so a
+    /// bracketed log prefix like `[2024-01-01 INFO]` is skipped as well.  If
+    /// there is no document start within `max` bytes the translation fails
+    /// with `ErrorKind::PrefixTooLong`.  The skipped bytes are available from
+    /// `Translator::skipped_prefix`.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{translate_slice_with_options, Options};
+    /// let options = Options::new().skip_prefix(16);
+    /// let mut json = b")]}'\n[NaN]".to_vec();
+    /// translate_slice_with_options(&mut json, &options).unwrap();
+    /// assert_eq!(json, b"     [0.0]");
+    /// ```
+    pub fn skip_prefix(mut self, max: usize) -> Options {
+        self.skip_prefix = Some(max);
+        self
+    }
+
+    /// Enables or disables the repair of lone surrogate escapes.
+    ///
+    /// Python's `json.dumps` happily writes strings with unpaired UTF-16
+    /// surrogates such as `"\ud800"` which `serde_json` rejects.  With this
+    /// option enabled escapes of surrogates that are not part of a pair are
+    /// rewritten to `\uFFFD`, the replacement character, which is the same
+    /// length.  This is disabled by default.
+    ///
+    /// The readers and the slice functions handle escapes that span chunks.
+    /// When driving a `Translator` by hand, chunks have to be passed to
+    /// `Translator::feed_partial` for this as `Translator::feed` cannot look
+    /// at the next chunk.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{translate_slice_with_options, Options};
+    /// let options = Options::new().repair_surrogates(true);
+    /// let mut json = br#"["\ud800", "\ud83d\ude00"]"#.to_vec();
+    /// translate_slice_with_options(&mut json, &options).unwrap();
+    /// assert_eq!(json, br#"["\uFFFD", "\ud83d\ude00"]"#);
+    /// ```
+    pub fn repair_surrogates(mut self, yes: bool) -> Options {
+        self.repair_surrogates = yes;
+        self
+    }
+
+    /// Enables or disables case insensitive matching of the tokens.
+    ///
+    /// Hand rolled serializers that interpolate `str(float)` or some C
+    /// extensions write `nan`, `inf` and `-inf` instead of the spellings of
+    /// the `json` module.  With this option enabled the tokens are matched
+    /// regardless of case and `inf` is accepted as a complete token.  This is
+    /// disabled by default.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{translate_slice_with_options, Options};
+    /// let options = Options::new().case_insensitive(true);
+    /// let mut json = b"[nan, -inf, Inf, null]".to_vec();
+    /// translate_slice_with_options(&mut json, &options).unwrap();
+    /// assert_eq!(json, b"[0.0, -0.0, 0.0, null]");
+    /// ```
+    pub fn case_insensitive(mut self, yes: bool) -> Options {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Enables or disables the translation of `+Infinity`.
+    ///
+    /// Some emitters write a leading plus sign which is not valid in JSON.
+    /// With this option enabled the sign is overwritten with a space as part
+    /// of the token.  Signs in exponents are left alone.  This is disabled by
+    /// default.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{translate_slice_with_options, Options};
+    /// let options = Options::new().plus_infinity(true);
+    /// let mut json = b"[+Infinity, 1e+5]".to_vec();
+    /// translate_slice_with_options(&mut json, &options).unwrap();
+    /// assert_eq!(json, b"[ 0.0     , 1e+5]");
+    /// ```
+    pub fn plus_infinity(mut self, yes: bool) -> Options {
+        self.plus_infinity = yes;
+        self
+    }
+
+    /// Enables or disables the rejection of binary data.
+    ///
+    /// By default bytes that cannot appear outside of strings are passed
+    /// through and left for the JSON parser, which then fails with a
+    /// confusing error if it was handed a binary file.  With this option
+    /// enabled the translation fails with `ErrorKind::BinaryData` at the
+    /// first NUL byte or non-ASCII byte outside of a string.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{translate_slice_with_options, ErrorKind, Options};
+    /// let options = Options::new().reject_binary(true);
+    /// let mut json = b"[\"\xc3\xa9\", \x00\x01]".to_vec();
+    /// let err = translate_slice_with_options(&mut json, &options).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::BinaryData);
+    /// assert_eq!(err.offset(), Some(7));
+    /// ```
+    pub fn reject_binary(mut self, yes: bool) -> Options {
+        self.reject_binary = yes;
+        self
+    }
+
+    /// Enables or disables counting integers that do not fit into 64 bits.
+    ///
+    /// Python has arbitrary precision integers which most JSON parsers turn
+    /// into floats, losing precision on the way.  With this enabled they are
+    /// counted in `TranslateStats::big_int`.  This is disabled by default as
+    /// it makes the translation slower.
+    pub fn count_big_ints(mut self, yes: bool) -> Options {
+        self.count_big_ints = yes;
+        self
+    }
+
+    /// Enables or disables rejecting documents instead of rewriting them.
+    ///
+    /// This is for deployments that must refuse Python JSON rather than fix
+    /// it.  With this enabled the translation fails with
+    /// `ErrorKind::RejectedToken` at the first `NaN`, `Infinity`, `-Infinity`
+    /// or integer that does not fit into 64 bits.  The token is not rewritten
+    /// and `Error::token_kind` tells which kind of token it was.  For
+    /// negative tokens the offset points at the minus sign.  Readers fail
+    /// with an `io::Error` that wraps the error.
+    ///
+    /// ```
+    /// # use std::io::Read;
+    /// # use python_json_read_adapter::{Error, JsonCompatRead, Options, TokenKind};
+    /// let options = Options::new().reject(true);
+    /// let mut rdr = JsonCompatRead::with_options(&b"[1, -Infinity]"[..], options);
+    /// let err = rdr.read_to_end(&mut Vec::new()).unwrap_err();
+    /// let err = err.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+    /// assert_eq!(err.offset(), Some(4));
+    /// assert_eq!(err.token_kind(), Some(TokenKind::NegInfinity));
+    /// ```
+    pub fn reject(mut self, yes: bool) -> Options {
+        self.reject = yes;
+        self
+    }
+
+    /// Checks if the translation needs to look past the end of a chunk.
+    pub(crate) fn needs_lookahead(&self) -> bool {
+        self.repair_surrogates
+            || self.case_insensitive
+            || self.plus_infinity
+            || self.skip_prefix.is_some()
+    }
+
+    /// Runs the translation against a set of canonical documents.
+    ///
+    /// The returned report contains input and output pairs for these options,
+    /// including the handling of the replacement budget and of truncated
+    /// documents.  Services can use it at startup to assert that the
+    /// translation still behaves as their contract expects, for instance
+    /// after upgrading this crate.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::Options;
+    /// let report = Options::new().max_replacements(2).self_test();
+    /// assert!(report.passed());
+    /// ```
+    pub fn self_test(&self) -> SelfTestReport {
+        selftest::run(self)
+    }
+}
@@ -0,0 +1,232 @@
+use std::borrow::Cow;
+
+use crate::error::Error;
+use crate::options::Options;
+use crate::translator::{TokenKind, Translator};
+
+fn token_text(kind: TokenKind) -> &'static [u8] {
+    match kind {
+        TokenKind::NaN => b"NaN",
+        TokenKind::PosInfinity | TokenKind::NegInfinity => b"Infinity",
+        // big integers are never rewritten
+        TokenKind::BigInt => b"",
+    }
+}
+
+/// Checks if the options only ever rewrite the default token spellings.
+///
+/// Otherwise the original text of a rewrite cannot be told from its kind.
+fn rewrites_only_tokens(options: &Options) -> bool {
+    !options.case_insensitive
+        && !options.plus_infinity
+        && !options.repair_surrogates
+        && options.skip_prefix.is_none()
+}
+
+/// Returns the runs of bytes that differ between two documents.
+pub(crate) fn changed_runs(original: &[u8], translated: &[u8]) -> Vec<(u64, Cow<'static, [u8]>)> {
+    let mut rv: Vec<(u64, Cow<'static, [u8]>)> = Vec::new();
+    let mut run: Option<usize> = None;
+    for (idx, (a, b)) in original.iter().zip(translated).enumerate() {
+        match (run, a == b) {
+            (None, false) => run = Some(idx),
+            (Some(start), true) => {
+                rv.push((start as u64, Cow::Owned(original[start..idx].to_vec())));
+                run = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run {
+        rv.push((start as u64, Cow::Owned(original[start..].to_vec())));
+    }
+    rv
+}
+
+/// Remembers the tokens rewritten by a translation.
+///
+/// The translation is done in place, so the original text of a document is
+/// gone afterwards.  A record keeps the offset and the original bytes of
+/// every rewrite which is enough to restore the original text of any part of
+/// the translated document.  This is useful to pass values through untouched, for instance
+/// fields that are deserialized as `RawValue`.
+///
+/// ```
+/// # use python_json_read_adapter::TranslationRecord;
+/// let mut json = br#"{"a": [NaN, -Infinity]}"#.to_vec();
+/// let record = TranslationRecord::translate(&mut json);
+/// let translated = std::str::from_utf8(&json[6..22]).unwrap();
+/// assert_eq!(translated, "[0.0, -0.0     ]");
+/// assert_eq!(record.original_text(translated, 6), "[NaN, -Infinity]");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TranslationRecord {
+    tokens: Vec<(u64, TokenKind)>,
+    /// The offsets and original bytes of the rewritten regions.
+    edits: Vec<(u64, Cow<'static, [u8]>)>,
+}
+
+impl TranslationRecord {
+    /// Translates a slice in place and records the rewritten tokens.
+    pub fn translate(bytes: &mut [u8]) -> TranslationRecord {
+        // without options the translation cannot fail
+        TranslationRecord::translate_with_options(bytes, &Options::default()).unwrap_or_default()
+    }
+
+    /// Translates a slice in place with the given options.
+    ///
+    /// Options that rewrite more than the default token spellings (case
+    /// insensitive tokens, plus signs, surrogates or a skipped prefix) make
+    /// the record keep a copy of the document during the translation to
+    /// remember the original bytes.
+    pub fn translate_with_options(
+        bytes: &mut [u8],
+        options: &Options,
+    ) -> Result<TranslationRecord, Error> {
+        let mut rv = TranslationRecord::default();
+        let mut translator = Translator::with_options(options.clone());
+        if rewrites_only_tokens(options) {
+            translator.feed_with(bytes, |kind, offset| rv.tokens.push((offset, kind)))?;
+            rv.edits = rv
+                .tokens
+                .iter()
+                .map(|&(offset, kind)| (offset, Cow::Borrowed(token_text(kind))))
+                .collect();
+        } else {
+            let original = bytes.to_vec();
+            translator.feed_with(bytes, |kind, offset| rv.tokens.push((offset, kind)))?;
+            rv.edits = changed_runs(&original, bytes);
+        }
+        Ok(rv)
+    }
+
+    /// Returns the offsets and kinds of the rewritten tokens.
+    ///
+    /// For `-Infinity` the offset points to the `I` as the sign is not
+    /// rewritten.
+    pub fn tokens(&self) -> &[(u64, TokenKind)] {
+        &self.tokens
+    }
+
+    /// Restores the original text of a part of the translated document.
+    ///
+    /// `offset` is the position of `translated` in the document.  If the
+    /// part contains no rewrites it's returned unchanged.  Rewrites that
+    /// start in the part are restored completely, even if the part ends
+    /// before the padding of the token does.  That way a value like `0.0`
+    /// cut out of `0.0     ` is restored to `Infinity`.
+    pub fn original_text<'a>(&self, translated: &'a str, offset: u64) -> Cow<'a, str> {
+        let end = offset.saturating_add(translated.len() as u64);
+        let start_idx = self.edits.partition_point(|&(pos, _)| pos < offset);
+        let end_idx = self.edits.partition_point(|&(pos, _)| pos < end);
+        let mut rv: Option<Vec<u8>> = None;
+        // splice from the back so that the indexes of earlier edits stay
+        // valid when a restored edit sticks out of the part
+        for (pos, text) in self.edits[start_idx..end_idx].iter().rev() {
+            let buf = rv.get_or_insert_with(|| translated.as_bytes().to_vec());
+            let idx = (pos - offset) as usize;
+            let edit_end = (idx + text.len()).min(buf.len());
+            buf.splice(idx..edit_end, text.iter().copied());
+        }
+        match rv {
+            // only ASCII was replaced by ASCII so this stays valid UTF-8
+            Some(buf) => Cow::Owned(String::from_utf8_lossy(&buf).into_owned()),
+            None => Cow::Borrowed(translated),
+        }
+    }
+
+    /// Restores the original text of a raw value.
+    ///
+    /// `document` is the translated document the value was borrowed from.
+    /// Only values that borrow from the document (`&RawValue`) can be
+    /// located, for others (like `Box<RawValue>`) `None` is returned.
+    #[cfg(feature = "raw_value")]
+    pub fn original_raw<'a>(
+        &self,
+        document: &[u8],
+        raw: &'a serde_json::value::RawValue,
+    ) -> Option<Cow<'a, str>> {
+        let text = raw.get();
+        let offset = (text.as_ptr() as usize).checked_sub(document.as_ptr() as usize)?;
+        if offset.checked_add(text.len())? > document.len() {
+            return None;
+        }
+        Some(self.original_text(text, offset as u64))
+    }
+}
+
+#[test]
+fn test_original_text() {
+    let mut json = br#"[NaN, "NaN", Infinity, -Infinity, 1]"#.to_vec();
+    let record = TranslationRecord::translate(&mut json);
+    assert_eq!(
+        record.tokens(),
+        &[
+            (1, TokenKind::NaN),
+            (13, TokenKind::PosInfinity),
+            (24, TokenKind::NegInfinity)
+        ]
+    );
+    let translated = std::str::from_utf8(&json).unwrap();
+    assert_eq!(
+        record.original_text(translated, 0),
+        r#"[NaN, "NaN", Infinity, -Infinity, 1]"#
+    );
+    assert!(matches!(
+        record.original_text(&translated[6..11], 6),
+        Cow::Borrowed("\"NaN\"")
+    ));
+    assert_eq!(record.original_text(&translated[13..21], 13), "Infinity");
+    // the padding does not need to be part of the text
+    assert_eq!(record.original_text(&translated[13..16], 13), "Infinity");
+    assert_eq!(record.original_text(&translated[23..27], 23), "-Infinity");
+}
+
+#[test]
+fn test_original_text_options() {
+    let check = |json: &[u8], options: Options| {
+        let mut translated = json.to_vec();
+        let record = TranslationRecord::translate_with_options(&mut translated, &options).unwrap();
+        assert_ne!(translated, json);
+        let translated = std::str::from_utf8(&translated).unwrap();
+        assert_eq!(
+            record.original_text(translated, 0),
+            std::str::from_utf8(json).unwrap()
+        );
+    };
+    check(
+        b"[inf, 1, 2, -INF, nan]",
+        Options::new().case_insensitive(true),
+    );
+    check(b"[+Infinity, 1]", Options::new().plus_infinity(true));
+    check(
+        br#"["\ud800", NaN]"#,
+        Options::new().repair_surrogates(true),
+    );
+    check(b")]}'\n[NaN]", Options::new().skip_prefix(8));
+
+    let mut json = b"[+inf, 1]".to_vec();
+    let options = Options::new().case_insensitive(true).plus_infinity(true);
+    let record = TranslationRecord::translate_with_options(&mut json, &options).unwrap();
+    let translated = std::str::from_utf8(&json).unwrap();
+    assert_eq!(record.original_text(&translated[1..5], 1), "+inf");
+    assert_eq!(record.original_text(&translated[7..8], 7), "1");
+}
+
+#[cfg(feature = "raw_value")]
+#[test]
+fn test_original_raw() {
+    use serde_json::value::RawValue;
+
+    let mut json = br#"{"a": {"b": [NaN, -Infinity]}, "c": Infinity}"#.to_vec();
+    let record = TranslationRecord::translate(&mut json);
+    let value: std::collections::BTreeMap<&str, &RawValue> = serde_json::from_slice(&json).unwrap();
+    assert_eq!(value["a"].get(), r#"{"b": [0.0, -0.0     ]}"#);
+    assert_eq!(
+        record.original_raw(&json, value["a"]).unwrap(),
+        r#"{"b": [NaN, -Infinity]}"#
+    );
+    assert_eq!(record.original_raw(&json, value["c"]).unwrap(), "Infinity");
+    let boxed = RawValue::from_string("0.0".into()).unwrap();
+    assert_eq!(record.original_raw(&json, &boxed), None);
+}
@@ -0,0 +1,173 @@
+use rayon::prelude::*;
+
+use crate::backend::Backend;
+use crate::translator::{TranslateStats, Translator};
+
+/// The size of the chunks that are translated in parallel.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Translates a slice in place on the `rayon` thread pool.
+///
+/// The document is split into chunks of about a megabyte right after
+/// whitespace or structural characters so that no token crosses a chunk.
+/// A quick pass over every chunk determines if it starts inside a string,
+/// then all chunks are translated in parallel.  The result is the same as
+/// with `translate_slice`, smaller documents are translated on the calling
+/// thread.  The returned counts do not include big integers.
+///
+/// ```
+/// # use python_json_read_adapter::translate_slice_parallel;
+/// let mut json = br#"{"x": NaN, "y": -Infinity}"#.to_vec();
+/// let stats = translate_slice_parallel(&mut json);
+/// assert_eq!(&json[..], br#"{"x": 0.0, "y": -0.0     }"#);
+/// assert_eq!(stats.total(), 2);
+/// ```
+pub fn translate_slice_parallel(bytes: &mut [u8]) -> TranslateStats {
+    translate_chunks(bytes, CHUNK_SIZE)
+}
+
+fn is_separator(b: u8) -> bool {
+    matches!(
+        b,
+        b' ' | b'\t' | b'\n' | b'\r' | b',' | b':' | b'[' | b']' | b'{' | b'}'
+    )
+}
+
+/// Returns the offsets at which the document is split.
+///
+/// A translator is either between tokens or inside a string after one of
+/// the separators, no matter which of the two it is.
+fn split_points(bytes: &[u8], chunk_size: usize) -> Vec<usize> {
+    let mut rv = Vec::new();
+    let mut pos = chunk_size.max(1);
+    while pos < bytes.len() {
+        match bytes[pos - 1..].iter().position(|&b| is_separator(b)) {
+            Some(idx) if pos + idx < bytes.len() => {
+                rv.push(pos + idx);
+                pos += idx + chunk_size.max(1);
+            }
+            _ => break,
+        }
+    }
+    rv
+}
+
+/// Returns whether a chunk ends inside of a string.
+///
+/// This follows the translator: a byte that breaks off a partial token is
+/// consumed without being looked at, even if it is a quote.
+fn ends_quoted(bytes: &[u8], mut quoted: bool) -> bool {
+    let mut pos = 0;
+    while let Some(rest) = bytes.get(pos..) {
+        pos += Backend::Auto.skip(rest, quoted);
+        let token: &[u8] = match bytes.get(pos) {
+            None => break,
+            Some(b'"') => {
+                quoted = !quoted;
+                pos += 1;
+                continue;
+            }
+            Some(b'\\') => {
+                pos += 2;
+                continue;
+            }
+            Some(b'N') => b"NaN",
+            Some(_) => b"Infinity",
+        };
+        let matched = bytes[pos..]
+            .iter()
+            .zip(token)
+            .take_while(|(a, b)| a == b)
+            .count();
+        pos += if matched == token.len() {
+            matched
+        } else {
+            matched + 1
+        };
+    }
+    quoted
+}
+
+fn translate_chunks(bytes: &mut [u8], chunk_size: usize) -> TranslateStats {
+    let splits = split_points(bytes, chunk_size);
+    if splits.is_empty() {
+        let mut translator = Translator::new();
+        // without options the translation cannot fail
+        let _ = translator.feed(bytes);
+        return translator.stats();
+    }
+
+    let mut bounds = vec![0];
+    bounds.extend_from_slice(&splits);
+    bounds.push(bytes.len());
+    let transitions: Vec<(bool, bool)> = bounds
+        .par_windows(2)
+        .map(|window| {
+            let chunk = &bytes[window[0]..window[1]];
+            (ends_quoted(chunk, false), ends_quoted(chunk, true))
+        })
+        .collect();
+    let mut starts = Vec::with_capacity(transitions.len());
+    let mut quoted = false;
+    for (idx, &(from_initial, from_quoted)) in transitions.iter().enumerate() {
+        let last_byte = match idx {
+            0 => 0,
+            _ => bytes[bounds[idx] - 1],
+        };
+        starts.push((quoted, last_byte));
+        quoted = if quoted { from_quoted } else { from_initial };
+    }
+
+    let mut chunks = Vec::with_capacity(starts.len());
+    let mut rest = bytes;
+    let mut offset = 0;
+    for &split in &splits {
+        let (chunk, tail) = rest.split_at_mut(split - offset);
+        chunks.push((offset, chunk));
+        rest = tail;
+        offset = split;
+    }
+    chunks.push((offset, rest));
+
+    chunks
+        .into_par_iter()
+        .zip(starts)
+        .map(|((offset, chunk), (quoted, last_byte))| {
+            let mut translator = Translator::resume(offset as u64, quoted, last_byte);
+            let _ = translator.feed(chunk);
+            translator.stats()
+        })
+        .reduce(TranslateStats::default, |mut a, b| {
+            a.merge(&b);
+            a
+        })
+}
+
+#[test]
+fn test_translate_parallel() {
+    use crate::translator::translate_slice;
+
+    let docs: &[&[u8]] = &[
+        br#"{"a": [NaN, -Infinity, Infinity], "b": "NaN, \"Infinity\" \\", "c": [1, 2]}"#,
+        br#"[N"x, y", In"NaN", Infinit, "a\"b, c", Na "Infinity"]"#,
+        b"[\n  NaN,\n  \"\\\\\",\n  -Infinity\n]",
+        br#"["unterminated, NaN, -Infinity"#,
+    ];
+    for doc in docs {
+        let mut expected = doc.to_vec();
+        translate_slice(&mut expected);
+        for chunk_size in 1..=doc.len() + 1 {
+            let mut json = doc.to_vec();
+            let stats = translate_chunks(&mut json, chunk_size);
+            assert_eq!(
+                String::from_utf8_lossy(&json),
+                String::from_utf8_lossy(&expected),
+                "{}",
+                chunk_size
+            );
+            let mut translator = Translator::new();
+            translator.feed(&mut doc.to_vec()).unwrap();
+            assert_eq!(stats, translator.stats());
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::ops::Range;
+
+use crate::lexer::{unescape, PyJsonLexer, Token};
+
+/// The raw text of a value inside of a document.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RawSpan<'a> {
+    /// The offset of the first byte of the value.
+    pub start: usize,
+    /// The offset after the last byte of the value.
+    pub end: usize,
+    /// The untranslated text of the value.
+    pub text: &'a [u8],
+}
+
+impl<'a> RawSpan<'a> {
+    /// Returns the byte range of the value.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Parses the value.
+    #[cfg(feature = "serde")]
+    pub fn to_value(&self) -> serde_json::Result<crate::PyValue> {
+        crate::from_slice_value(self.text)
+    }
+}
+
+fn next_token<'a>(lexer: &mut PyJsonLexer<'a>) -> Option<(Token<'a>, Range<usize>)> {
+    lexer.next()?.ok()
+}
+
+/// Skips a value that starts with `token` and returns the end of it.
+fn skip_value(lexer: &mut PyJsonLexer<'_>, token: Token<'_>, span: Range<usize>) -> Option<usize> {
+    let mut depth = 0usize;
+    let (mut token, mut span) = (token, span);
+    loop {
+        match token {
+            Token::BeginObject | Token::BeginArray => depth += 1,
+            Token::EndObject | Token::EndArray => depth = depth.checked_sub(1)?,
+            Token::Colon | Token::Comma if depth == 0 => return None,
+            _ => {}
+        }
+        if depth == 0 {
+            return Some(span.end);
+        }
+        let next = next_token(lexer)?;
+        token = next.0;
+        span = next.1;
+    }
+}
+
+fn find(lexer: &mut PyJsonLexer<'_>, segments: &[String]) -> Option<Range<usize>> {
+    let (token, span) = next_token(lexer)?;
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            let start = span.start;
+            return skip_value(lexer, token, span).map(|end| start..end);
+        }
+    };
+    match token {
+        Token::BeginObject => loop {
+            let key = match next_token(lexer)? {
+                (Token::String(raw), span) => unescape(raw, span.start + 1).ok()?,
+                _ => return None,
+            };
+            if next_token(lexer)?.0 != Token::Colon {
+                return None;
+            }
+            if *key == **segment {
+                return find(lexer, rest);
+            }
+            let (token, span) = next_token(lexer)?;
+            skip_value(lexer, token, span)?;
+            if next_token(lexer)?.0 != Token::Comma {
+                return None;
+            }
+        },
+        Token::BeginArray => {
+            let index = parse_index(segment)?;
+            for _ in 0..index {
+                let (token, span) = next_token(lexer)?;
+                skip_value(lexer, token, span)?;
+                if next_token(lexer)?.0 != Token::Comma {
+                    return None;
+                }
+            }
+            find(lexer, rest)
+        }
+        _ => None,
+    }
+}
+
+fn parse_index(segment: &str) -> Option<usize> {
+    if segment.is_empty()
+        || (segment.len() > 1 && segment.starts_with('0'))
+        || !segment.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+/// Looks up a value in a Python JSON document by JSON pointer.
+///
+/// The document is scanned without parsing the values that are not on the
+/// way to the target, so this is cheap for picking a single field out of a
+/// large document.  The returned span points to the untranslated text of
+/// the value.  `None` is returned if the value does not exist, the pointer
+/// is invalid or the document is malformed before the target.
+///
+/// ```
+/// # use python_json_read_adapter::get_pointer;
+/// let json = br#"{"results": [{"score": NaN}, {"score": -Infinity}]}"#;
+/// let span = get_pointer(json, "/results/1/score").unwrap();
+/// assert_eq!(span.text, b"-Infinity");
+/// assert!(get_pointer(json, "/results/2").is_none());
+/// ```
+pub fn get_pointer<'a>(bytes: &'a [u8], pointer: &str) -> Option<RawSpan<'a>> {
+    let segments = match pointer.strip_prefix('/') {
+        Some(pointer) => pointer
+            .split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+        None if pointer.is_empty() => Vec::new(),
+        None => return None,
+    };
+    let range = find(&mut PyJsonLexer::new(bytes), &segments)?;
+    Some(RawSpan {
+        start: range.start,
+        end: range.end,
+        text: bytes.get(range)?,
+    })
+}
+
+#[test]
+fn test_get_pointer() {
+    let json =
+        br#" {"a": [1, {"b/c": NaN, "x": [[]]}, "s"], "~": {"big": 123456789012345678901234}} "#;
+    let get = |pointer| get_pointer(json, pointer).map(|span| span.text);
+    assert_eq!(get("/a/1/b~1c"), Some(&b"NaN"[..]));
+    assert_eq!(get("/a/1/x"), Some(&b"[[]]"[..]));
+    assert_eq!(get("/a/2"), Some(&b"\"s\""[..]));
+    assert_eq!(get("/~0/big"), Some(&b"123456789012345678901234"[..]));
+    assert_eq!(get("").map(|text| text.len()), Some(json.len() - 2));
+    assert_eq!(get("/a/3"), None);
+    assert_eq!(get("/a/01"), None);
+    assert_eq!(get("/a/-"), None);
+    assert_eq!(get("/b"), None);
+    assert_eq!(get("/a/0/x"), None);
+    assert_eq!(get("a"), None);
+    let span = get_pointer(json, "/a/1/b~1c").unwrap();
+    assert_eq!(&json[span.range()], b"NaN");
+    assert_eq!(get_pointer(b"[1, Nax]", "/1"), None);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_get_pointer_value() {
+    let json = br#"{"a": {"b": [Infinity, 2]}}"#;
+    let value = get_pointer(json, "/a/b").unwrap().to_value().unwrap();
+    assert_eq!(value.to_string(), "[Infinity,2]");
+}
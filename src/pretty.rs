@@ -0,0 +1,213 @@
+use std::io;
+
+use crate::events::{parse_events, Handler};
+use crate::lexer::NonFinite;
+
+const INDENT: &[u8] = b"  ";
+
+struct PrettyPrinter<W> {
+    writer: W,
+    depth: usize,
+    // true right after a container was opened
+    first: bool,
+    // true right after a key was written
+    after_key: bool,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> PrettyPrinter<W> {
+    fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_none() {
+            if let Err(err) = self.writer.write_all(bytes) {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    fn newline(&mut self) {
+        self.write(b"\n");
+        for _ in 0..self.depth {
+            self.write(INDENT);
+        }
+    }
+
+    /// Writes the separator in front of a value or key.
+    fn begin_item(&mut self) {
+        if self.after_key {
+            self.after_key = false;
+            return;
+        }
+        if self.depth > 0 {
+            if !self.first {
+                self.write(b",");
+            }
+            self.newline();
+        }
+        self.first = false;
+    }
+
+    fn open(&mut self, bracket: &[u8]) {
+        self.begin_item();
+        self.write(bracket);
+        self.depth += 1;
+        self.first = true;
+    }
+
+    fn close(&mut self, bracket: &[u8]) {
+        self.depth -= 1;
+        if !self.first {
+            self.newline();
+        }
+        self.first = false;
+        self.write(bracket);
+    }
+
+    fn write_string(&mut self, value: &str) {
+        self.write(b"\"");
+        let mut start = 0;
+        for (idx, byte) in value.bytes().enumerate() {
+            let escape: &[u8] = match byte {
+                b'"' => b"\\\"",
+                b'\\' => b"\\\\",
+                b'\n' => b"\\n",
+                b'\r' => b"\\r",
+                b'\t' => b"\\t",
+                0x08 => b"\\b",
+                0x0c => b"\\f",
+                0x00..=0x1f => b"",
+                _ => continue,
+            };
+            self.write(&value.as_bytes()[start..idx]);
+            if escape.is_empty() {
+                self.write(format!("\\u{:04x}", byte).as_bytes());
+            } else {
+                self.write(escape);
+            }
+            start = idx + 1;
+        }
+        self.write(&value.as_bytes()[start..]);
+        self.write(b"\"");
+    }
+
+    fn scalar(&mut self, bytes: &[u8]) {
+        self.begin_item();
+        self.write(bytes);
+    }
+}
+
+impl<W: io::Write> Handler for PrettyPrinter<W> {
+    fn begin_object(&mut self) {
+        self.open(b"{");
+    }
+
+    fn end_object(&mut self) {
+        self.close(b"}");
+    }
+
+    fn begin_array(&mut self) {
+        self.open(b"[");
+    }
+
+    fn end_array(&mut self) {
+        self.close(b"]");
+    }
+
+    fn key(&mut self, key: &str) {
+        self.begin_item();
+        self.write_string(key);
+        self.write(b": ");
+        self.after_key = true;
+    }
+
+    fn null(&mut self) {
+        self.scalar(b"null");
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.scalar(if value { b"true" } else { b"false" });
+    }
+
+    fn number(&mut self, text: &str) {
+        self.scalar(text.as_bytes());
+    }
+
+    fn non_finite(&mut self, value: NonFinite) {
+        // the same values the translation produces
+        self.scalar(match value {
+            NonFinite::NegInf => b"-0.0",
+            NonFinite::NaN | NonFinite::PosInf => b"0.0",
+        });
+    }
+
+    fn string(&mut self, value: &str) {
+        self.begin_item();
+        self.write_string(value);
+    }
+}
+
+/// Reformats a Python JSON document into indented strict JSON.
+///
+/// The document is written to the writer while it's parsed, with two
+/// spaces of indentation and `NaN` and `Infinity` translated to `0.0` like
+/// `translate_slice` does.  Unlike the translated document the output
+/// carries no padding.  The whole document is validated, if it is
+/// malformed an error is returned and the output is incomplete.
+///
+/// ```
+/// # use python_json_read_adapter::pretty_print;
+/// let mut rv = Vec::new();
+/// pretty_print(br#"{"a": [NaN, -Infinity], "b": {}}"#, &mut rv).unwrap();
+/// assert_eq!(
+///     std::str::from_utf8(&rv).unwrap(),
+///     "{\n  \"a\": [\n    0.0,\n    -0.0\n  ],\n  \"b\": {}\n}"
+/// );
+/// ```
+pub fn pretty_print<W: io::Write>(bytes: &[u8], writer: W) -> io::Result<()> {
+    let mut printer = PrettyPrinter {
+        writer,
+        depth: 0,
+        first: true,
+        after_key: false,
+        error: None,
+    };
+    let rv = parse_events(bytes, &mut printer);
+    match printer.error {
+        Some(err) => Err(err),
+        None => Ok(rv?),
+    }
+}
+
+/// Reformats a Python JSON document from a reader into indented strict JSON.
+///
+/// This works like `pretty_print` but reads the whole document into memory
+/// first.
+pub fn pretty_print_reader<R, W>(mut rdr: R, writer: W) -> io::Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let mut buf = Vec::new();
+    rdr.read_to_end(&mut buf)?;
+    pretty_print(&buf, writer)
+}
+
+#[test]
+fn test_pretty_print() {
+    let mut rv = Vec::new();
+    pretty_print(
+        br#"[1, "a\"\u0001\u00e9", [[]], {"k\n": [true, null]}, Infinity]"#,
+        &mut rv,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&rv).unwrap(),
+        "[\n  1,\n  \"a\\\"\\u0001\u{e9}\",\n  [\n    []\n  ],\n  {\n    \"k\\n\": [\n      true,\n      null\n    ]\n  },\n  0.0\n]"
+    );
+
+    let mut rv = Vec::new();
+    pretty_print_reader(&b" NaN "[..], &mut rv).unwrap();
+    assert_eq!(rv, b"0.0");
+
+    let err = pretty_print(b"[1, ", &mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
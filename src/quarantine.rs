@@ -0,0 +1,253 @@
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use crate::error::Error;
+use crate::options::Options;
+use crate::original::changed_runs;
+use crate::read::JsonCompatRead;
+use crate::translator::{feed_document, TranslateStats, Translator};
+
+/// The default limit for the bytes written to a quarantine sink.
+const DEFAULT_QUARANTINE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Checks if an IO error was caused by the translation rejecting a document.
+fn is_translation_error(err: &io::Error) -> bool {
+    err.get_ref()
+        .is_some_and(|inner| inner.downcast_ref::<Error>().is_some())
+}
+
+/// Keeps a copy of the first `limit` bytes read from a reader.
+struct Recording<R> {
+    reader: R,
+    recorded: Vec<u8>,
+    limit: u64,
+    truncated: bool,
+}
+
+impl<R: Read> Read for Recording<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        let chunk = buf.get(..read).unwrap_or(buf);
+        let room = usize::try_from(self.limit)
+            .unwrap_or(usize::MAX)
+            .saturating_sub(self.recorded.len());
+        if chunk.len() > room {
+            self.truncated = true;
+        }
+        self.recorded
+            .extend_from_slice(chunk.get(..room).unwrap_or(chunk));
+        Ok(read)
+    }
+}
+
+/// A translating reader that quarantines rejected documents.
+///
+/// This works like `JsonCompatRead::with_options` but if the options reject
+/// the document (for instance because the replacement budget is exceeded),
+/// the original untranslated bytes of the whole document are streamed to a
+/// quarantine sink before the error is returned.  This way bad producer data
+/// can be kept for later inspection.
+///
+/// To be able to do this the reader keeps a copy of the document read so far
+/// in memory, up to the size of the quarantine.  IO errors of the wrapped reader do not cause the document to
+/// be quarantined.  At most 64 MiB are written to the sink unless configured
+/// otherwise with `max_quarantine_bytes`, longer documents are truncated.
+pub struct QuarantineRead<R, W> {
+    inner: JsonCompatRead<Recording<R>>,
+    sink: Option<W>,
+    truncated: bool,
+}
+
+impl<R: Read, W: Write> QuarantineRead<R, W> {
+    /// Wraps a reader with the given options and quarantine sink.
+    pub fn new(reader: R, options: Options, sink: W) -> QuarantineRead<R, W> {
+        QuarantineRead {
+            inner: JsonCompatRead::with_options(
+                Recording {
+                    reader,
+                    recorded: Vec::new(),
+                    limit: DEFAULT_QUARANTINE_BYTES,
+                    truncated: false,
+                },
+                options,
+            ),
+            sink: Some(sink),
+            truncated: false,
+        }
+    }
+
+    /// Limits the size of the document.
+    ///
+    /// This works like `JsonCompatRead::wrap_limited`.  Documents exceeding
+    /// the limit are quarantined as well.
+    pub fn max_bytes(mut self, max_bytes: u64) -> QuarantineRead<R, W> {
+        self.inner.set_limit(Some(max_bytes));
+        self
+    }
+
+    /// Limits the number of bytes written to the quarantine sink.
+    pub fn max_quarantine_bytes(mut self, max_bytes: u64) -> QuarantineRead<R, W> {
+        self.inner.get_mut().limit = max_bytes;
+        self
+    }
+
+    /// Checks if the quarantined copy of the document was truncated.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Checks if the document was quarantined.
+    pub fn is_quarantined(&self) -> bool {
+        self.sink.is_none()
+    }
+
+    /// Returns the quarantine sink unless the document was quarantined.
+    pub fn into_sink(self) -> Option<W> {
+        self.sink
+    }
+
+    fn quarantine(&mut self) -> io::Result<()> {
+        if let Some(mut sink) = self.sink.take() {
+            let recording = self.inner.get_mut();
+            let recorded = std::mem::take(&mut recording.recorded);
+            sink.write_all(&recorded)?;
+            self.truncated = recording.truncated || {
+                let remaining = recording.limit.saturating_sub(recorded.len() as u64);
+                let copied = io::copy(&mut (&mut recording.reader).take(remaining), &mut sink)?;
+                copied == remaining && recording.reader.read(&mut [0])? > 0
+            };
+            sink.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read, W: Write> Read for QuarantineRead<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Err(err) if is_translation_error(&err) => {
+                self.quarantine()?;
+                Err(err)
+            }
+            rv => rv,
+        }
+    }
+}
+
+/// Translates a slice in place and quarantines it if it is rejected.
+///
+/// If the options reject the document or it ends in the middle of a token,
+/// the original bytes are written to the sink and the error is returned.  On
+/// failure the slice is left in its original state.
+///
+/// Instead of a copy of the whole slice only the original bytes of the
+/// rewritten regions are kept to restore it.
+pub fn translate_slice_quarantined<W: Write>(
+    bytes: &mut [u8],
+    options: &Options,
+    mut sink: W,
+) -> io::Result<TranslateStats> {
+    let mut translator = Translator::with_options(options.clone());
+    let mut edits = Vec::new();
+    let rv = feed_document(
+        &mut translator,
+        bytes,
+        &mut |_, _| {},
+        |pos: usize, original: &[u8], chunk: &[u8]| {
+            edits.extend(
+                changed_runs(original, chunk)
+                    .into_iter()
+                    .map(|(offset, text)| (pos + offset as usize, text)),
+            );
+        },
+    )
+    .map(|_| translator.stats());
+    rv.or_else(|err| {
+        for (offset, text) in edits {
+            bytes[offset..offset + text.len()].copy_from_slice(&text);
+        }
+        sink.write_all(bytes)?;
+        sink.flush()?;
+        Err(err.into())
+    })
+}
+
+#[test]
+fn test_quarantine_read() {
+    let json = b"[NaN, NaN, NaN, 1, 2, 3, 4, 5, 6, 7, 8, 9]";
+    let mut sink = Vec::new();
+    let rdr = QuarantineRead::new(&json[..], Options::new().max_replacements(2), &mut sink);
+    let err = crate::test_support::read_fragmented(rdr, 4).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(&sink[..], &json[..]);
+
+    let mut sink = Vec::new();
+    let mut rdr = QuarantineRead::new(&json[..], Options::new(), &mut sink).max_bytes(8);
+    assert!(rdr.read_to_end(&mut Vec::new()).is_err());
+    assert!(rdr.is_quarantined());
+    assert_eq!(&sink[..], &json[..]);
+
+    let mut rdr = QuarantineRead::new(&json[..], Options::new(), Vec::new());
+    let mut rv = Vec::new();
+    rdr.read_to_end(&mut rv).unwrap();
+    assert_eq!(&rv[..], b"[0.0, 0.0, 0.0, 1, 2, 3, 4, 5, 6, 7, 8, 9]");
+    assert_eq!(rdr.into_sink(), Some(Vec::new()));
+}
+
+#[test]
+fn test_quarantine_truncated() {
+    let json = b"[NaN, NaN, NaN, 1, 2, 3, 4, 5, 6, 7, 8, 9]";
+    for &(limit, truncated) in &[(2, true), (20, true), (42, false), (100, false)] {
+        let mut sink = Vec::new();
+        let mut rdr = QuarantineRead::new(&json[..], Options::new().max_replacements(2), &mut sink)
+            .max_quarantine_bytes(limit);
+        assert!(rdr.read_to_end(&mut Vec::new()).is_err());
+        assert!(rdr.is_quarantined());
+        assert_eq!(rdr.is_truncated(), truncated, "{}", limit);
+        drop(rdr);
+        assert_eq!(&sink[..], &json[..json.len().min(limit as usize)]);
+    }
+}
+
+#[test]
+fn test_translate_slice_quarantined() {
+    let mut sink = Vec::new();
+    let mut json = b"[NaN, Infinity]".to_vec();
+    let options = Options::new().max_replacements(1);
+    assert!(translate_slice_quarantined(&mut json, &options, &mut sink).is_err());
+    assert_eq!(&json[..], b"[NaN, Infinity]");
+    assert_eq!(&sink[..], b"[NaN, Infinity]");
+
+    let mut sink = Vec::new();
+    let mut json = b"[NaN]".to_vec();
+    let stats = translate_slice_quarantined(&mut json, &options, &mut sink).unwrap();
+    assert_eq!(stats.nan, 1);
+    assert!(sink.is_empty());
+
+    let mut sink = Vec::new();
+    let mut json = b"[NaN, Na, Infin".to_vec();
+    assert!(translate_slice_quarantined(&mut json, &Options::new(), &mut sink).is_err());
+    assert_eq!(&json[..], b"[NaN, Na, Infin");
+    assert_eq!(&sink[..], b"[NaN, Na, Infin");
+
+    // the chunks are translated like a single slice
+    let mut original = b"[".to_vec();
+    while original.len() < 3 * 64 * 1024 {
+        original.extend_from_slice(b"NaN, -Infinity, \"\\ud800 NaN\", ");
+    }
+    original.extend_from_slice(b"1]");
+    let options = Options::new().repair_surrogates(true);
+    let mut expected = original.clone();
+    crate::translate_slice_with_options(&mut expected, &options).unwrap();
+    let mut json = original.clone();
+    let mut sink = Vec::new();
+    translate_slice_quarantined(&mut json, &options, &mut sink).unwrap();
+    assert_eq!(json, expected);
+    assert!(sink.is_empty());
+
+    let mut json = original.clone();
+    let options = Options::new().max_replacements(10_000);
+    assert!(translate_slice_quarantined(&mut json, &options, &mut sink).is_err());
+    assert_eq!(json, original);
+    assert_eq!(sink, original);
+}
@@ -0,0 +1,796 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::error::{Error, ErrorKind};
+use crate::options::Options;
+use crate::translator::{TokenKind, TranslateStats, Translator};
+
+/// The smallest internal buffer that fits any undecided bytes.
+const MIN_REPAIR_CAPACITY: usize = 64;
+
+/// A position in the stream handed out by `JsonCompatRead`.
+///
+/// As the translation does not move any bytes this is also the position in
+/// the original document.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// The number of bytes handed out.
+    pub offset: u64,
+    /// The current line, starting at one.
+    pub line: u64,
+    /// The number of bytes handed out since the start of the line.
+    ///
+    /// This matches the column `serde_json` reports for an error in the
+    /// last byte it read.
+    pub column: u64,
+}
+
+impl Position {
+    fn advance(&mut self, bytes: &[u8]) {
+        self.offset = self.offset.saturating_add(bytes.len() as u64);
+        match bytes.iter().rposition(|&b| b == b'\n') {
+            Some(idx) => {
+                let lines = bytes.iter().filter(|&&b| b == b'\n').count();
+                self.line = self.line.saturating_add(lines as u64);
+                self.column = (bytes.len() - idx - 1) as u64;
+            }
+            None => self.column = self.column.saturating_add(bytes.len() as u64),
+        }
+    }
+}
+
+/// A reader that transparently translates python JSON compat tokens.
+pub struct JsonCompatRead<R> {
+    reader: R,
+    translator: Translator,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+    limit: Option<u64>,
+    check_eof: bool,
+    carry: Vec<u8>,
+    on_replacement: Option<Box<dyn FnMut(TokenKind, u64) + Send>>,
+    position: Position,
+    #[cfg(feature = "tracing")]
+    trace_start: Option<std::time::Instant>,
+    #[cfg(feature = "tracing")]
+    traced: bool,
+}
+
+impl<R: Read> fmt::Debug for JsonCompatRead<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JsonCompatRead").finish()
+    }
+}
+
+impl<R: Read> JsonCompatRead<R> {
+    /// Wraps another reader.
+    pub fn wrap(reader: R) -> JsonCompatRead<R> {
+        JsonCompatRead::with_capacity(reader, 0)
+    }
+
+    /// Wraps another reader with an internal buffer of the given capacity.
+    ///
+    /// Reads smaller than the capacity are served from the buffer which is
+    /// refilled with a single large read from the wrapped reader.  This is
+    /// useful when wrapping unbuffered readers like sockets or files as
+    /// `serde_json` tends to request data in small chunks.  A capacity of
+    /// zero disables buffering.
+    pub fn with_capacity(reader: R, capacity: usize) -> JsonCompatRead<R> {
+        JsonCompatRead {
+            reader,
+            translator: Translator::new(),
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            limit: None,
+            check_eof: false,
+            carry: Vec::new(),
+            on_replacement: None,
+            position: Position {
+                line: 1,
+                ..Position::default()
+            },
+            #[cfg(feature = "tracing")]
+            trace_start: None,
+            #[cfg(feature = "tracing")]
+            traced: false,
+        }
+    }
+
+    /// Wraps another reader and translates with the given options.
+    ///
+    /// If the options reject the document the read fails with an
+    /// `io::Error` of kind `InvalidData` that wraps an `Error`.
+    ///
+    /// With `Options::repair_surrogates`, `Options::case_insensitive`,
+    /// `Options::plus_infinity` or `Options::skip_prefix` the reader buffers
+    /// at least a few bytes internally as some tokens can only be translated
+    /// once the bytes after them are known.
+    pub fn with_options(reader: R, options: Options) -> JsonCompatRead<R> {
+        let capacity = if options.needs_lookahead() {
+            MIN_REPAIR_CAPACITY
+        } else {
+            0
+        };
+        let mut rv = JsonCompatRead::with_capacity(reader, capacity);
+        rv.translator = Translator::with_options(options);
+        rv
+    }
+
+    /// Wraps another reader and limits the size of the document.
+    ///
+    /// Unlike `Read::take` which silently truncates, this fails with an
+    /// `io::Error` of kind `InvalidData` once the wrapped reader produces
+    /// more than `max_bytes` bytes.  The inner error is an `Error` of kind
+    /// `ErrorKind::LimitExceeded`.
+    pub fn wrap_limited(reader: R, max_bytes: u64) -> JsonCompatRead<R> {
+        let mut rv = JsonCompatRead::wrap(reader);
+        rv.set_limit(Some(max_bytes));
+        rv
+    }
+
+    pub(crate) fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = limit;
+    }
+
+    /// Enables or disables the check for truncated documents.
+    ///
+    /// When enabled, reaching the end of the wrapped reader in the middle of
+    /// a token that is being rewritten (for instance `Infin`) fails with an
+    /// `io::Error` of kind `UnexpectedEof` instead of passing the partial
+    /// token through.  This is disabled by default.
+    pub fn check_eof(mut self, yes: bool) -> JsonCompatRead<R> {
+        self.check_eof = yes;
+        self
+    }
+
+    /// Registers a callback that is invoked for every rewritten token.
+    ///
+    /// The callback receives the kind and the offset of the first byte of
+    /// every token once it was completely read from the wrapped reader,
+    /// which can be before the translated bytes were handed out.  Unlike
+    /// collecting a report after the fact this works for unbounded streams.
+    ///
+    /// ```
+    /// # use std::io::Read;
+    /// # use std::sync::{Arc, Mutex};
+    /// # use python_json_read_adapter::{JsonCompatRead, TokenKind};
+    /// let seen = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = seen.clone();
+    /// let mut rdr = JsonCompatRead::wrap(&b"[1, NaN]"[..])
+    ///     .on_replacement(move |kind, offset| sink.lock().unwrap().push((kind, offset)));
+    /// rdr.read_to_end(&mut Vec::new()).unwrap();
+    /// assert_eq!(*seen.lock().unwrap(), vec![(TokenKind::NaN, 4)]);
+    /// ```
+    pub fn on_replacement<F>(mut self, f: F) -> JsonCompatRead<R>
+    where
+        F: FnMut(TokenKind, u64) + Send + 'static,
+    {
+        self.on_replacement = Some(Box::new(f));
+        self
+    }
+
+    /// Checks if the document read so far could end at the current position.
+    ///
+    /// Call this after the reader returned EOF to tell a truncated document
+    /// apart from a clean end.
+    pub fn verify(&self) -> Result<(), Error> {
+        self.translator.verify()
+    }
+
+    /// Returns a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the wrapped reader.
+    ///
+    /// Reading from the wrapped reader directly will confuse the translation
+    /// state and should be avoided.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwraps the adapter and returns the wrapped reader.
+    ///
+    /// If the adapter was created with an internal buffer, data that was
+    /// buffered but not yet read is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the number of bytes read from the wrapped reader so far.
+    ///
+    /// This includes bytes that are buffered but were not read yet.
+    pub fn bytes_processed(&self) -> u64 {
+        self.translator
+            .offset()
+            .saturating_add(self.carry.len() as u64)
+    }
+
+    /// Returns the position of the reader.
+    ///
+    /// Unlike `bytes_processed` this only covers the bytes that were handed
+    /// out, which makes it possible to correlate errors of the JSON parser
+    /// with the original document.
+    ///
+    /// ```
+    /// # use std::io::Read;
+    /// # use python_json_read_adapter::JsonCompatRead;
+    /// let mut rdr = JsonCompatRead::wrap(&b"{\n  \"a\": NaN\n}"[..]);
+    /// rdr.read_exact(&mut [0; 12]).unwrap();
+    /// let pos = rdr.position();
+    /// assert_eq!((pos.offset, pos.line, pos.column), (12, 2, 10));
+    /// ```
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the counts of the tokens that were rewritten so far.
+    pub fn replacements(&self) -> TranslateStats {
+        self.translator.stats()
+    }
+
+    /// Returns the bytes that were skipped in front of the document.
+    ///
+    /// See `Options::skip_prefix`.
+    pub fn skipped_prefix(&self) -> &[u8] {
+        self.translator.skipped_prefix()
+    }
+
+    /// Starts timing the document on the first read.
+    #[cfg(feature = "tracing")]
+    fn trace_begin(&mut self) {
+        if self.trace_start.is_none() && !self.traced {
+            self.trace_start = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Reports the document to `tracing` the first time EOF is reached.
+    #[cfg(feature = "tracing")]
+    fn trace_eof(&mut self) {
+        if let Some(start) = self.trace_start.take() {
+            self.traced = true;
+            crate::translator::trace_document(&self.translator, start.elapsed(), None);
+        }
+    }
+
+    /// Feeds bytes to the translator and invokes the callback.
+    fn feed(&mut self, bytes: &mut [u8], partial: bool) -> Result<usize, Error> {
+        match self.on_replacement {
+            Some(ref mut f) => {
+                self.translator
+                    .feed_impl(bytes, &mut |kind, range| f(kind, range.start), partial)
+            }
+            None => self.translator.feed_impl(bytes, &mut |_, _| {}, partial),
+        }
+    }
+
+    /// Called once the limit is reached to check if the reader is at EOF.
+    fn check_limit_eof(&mut self, limit: u64) -> io::Result<usize> {
+        let mut probe = [0; 1];
+        loop {
+            match self.reader.read(&mut probe) {
+                Ok(0) => return Ok(0),
+                Ok(_) => return Err(Error::new(ErrorKind::LimitExceeded, limit).into()),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Reads from the wrapped reader, stopping at the limit.
+    fn read_raw(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let buf = match self.limit {
+            Some(limit) => {
+                let remaining = limit.saturating_sub(self.bytes_processed());
+                if remaining == 0 {
+                    self.check_limit_eof(limit)?;
+                    return Ok(0);
+                }
+                let len = usize::try_from(remaining).map_or(buf.len(), |x| x.min(buf.len()));
+                &mut buf[..len]
+            }
+            None => buf,
+        };
+        let read = io::Read::read(&mut self.reader, buf)?;
+        // a misbehaving reader could claim to have read more than fits into
+        // the buffer.  Report that instead of panicking.
+        if read > buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "wrapped reader returned more bytes than requested",
+            ));
+        }
+        Ok(read)
+    }
+
+    fn read_translated(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(feature = "tracing")]
+        self.trace_begin();
+        loop {
+            // bytes the translator could not decide on yet go first.  The
+            // buffer is always large enough for them.
+            let carried = self.carry.len().min(buf.len());
+            let (head, tail) = buf.split_at_mut(carried);
+            head.copy_from_slice(&self.carry[..carried]);
+            let requested = tail.len();
+            let read = self.read_raw(tail);
+            self.carry.drain(..carried);
+            let read = read?;
+            let bytes = &mut buf[..carried + read];
+            let translated = self.feed(bytes, read > 0)?;
+            self.carry.extend_from_slice(&bytes[translated..]);
+            if read == 0 && requested > 0 && self.check_eof {
+                self.translator.verify()?;
+            }
+            #[cfg(feature = "tracing")]
+            if read == 0 && requested > 0 {
+                self.trace_eof();
+            }
+            if translated > 0 || read == 0 {
+                return Ok(translated);
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for JsonCompatRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // bypass the internal buffer if it is empty and the read is at
+        // least as large as the buffer itself.
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            let n = self.read_translated(buf)?;
+            self.position.advance(&buf[..n]);
+            return Ok(n);
+        }
+
+        if self.pos == self.filled {
+            let mut internal = std::mem::take(&mut self.buf);
+            let rv = self.read_translated(&mut internal);
+            self.buf = internal;
+            self.filled = rv?;
+            self.pos = 0;
+        }
+
+        let available = &self.buf[self.pos..self.filled];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.position.advance(&available[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        // the buffered bytes were already translated.  Everything else is
+        // slurped and translated in one go which is a lot faster than going
+        // through many small reads.
+        #[cfg(feature = "tracing")]
+        self.trace_begin();
+        let buffered = &self.buf[self.pos..self.filled];
+        self.position.advance(buffered);
+        buf.extend_from_slice(buffered);
+        let buffered = buffered.len();
+        self.pos = self.filled;
+        let start = buf.len();
+        let carried = self.carry.len();
+        let rv = match self.limit {
+            Some(limit) => {
+                let remaining = limit.saturating_sub(self.bytes_processed());
+                buf.append(&mut self.carry);
+                (&mut self.reader).take(remaining).read_to_end(buf)
+            }
+            None => {
+                buf.append(&mut self.carry);
+                self.reader.read_to_end(buf)
+            }
+        };
+        let before = self.translator.offset();
+        let fed = self.feed(&mut buf[start..], false);
+        if fed.is_err() {
+            // only hand out the bytes that were translated
+            let translated = self.translator.offset() - before;
+            buf.truncate(start + usize::try_from(translated).unwrap_or(usize::MAX));
+        }
+        self.position.advance(&buf[start..]);
+        let read = carried + rv?;
+        fed?;
+        if let Some(limit) = self.limit {
+            self.check_limit_eof(limit)?;
+        }
+        if self.check_eof {
+            self.translator.verify()?;
+        }
+        #[cfg(feature = "tracing")]
+        self.trace_eof();
+        Ok(buffered + read)
+    }
+
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        let mut bytes = std::mem::take(buf).into_bytes();
+        let start = bytes.len();
+        let rv = self.read_to_end(&mut bytes);
+        if std::str::from_utf8(&bytes[start..]).is_err() {
+            bytes.truncate(start);
+            *buf = String::from_utf8(bytes).expect("prefix was valid UTF-8");
+            return Err(rv.err().unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )
+            }));
+        }
+        *buf = String::from_utf8(bytes).expect("validated as UTF-8");
+        rv
+    }
+}
+
+#[test]
+fn test_reader_simple() {
+    let json = r#"{"nan":0.0,"inf":Infinity,"-inf":-Infinity}"#;
+    assert_eq!(json.len(), 43);
+    let mut rdr = JsonCompatRead::wrap(json.as_bytes());
+    let mut rv = String::new();
+    let read = rdr.read_to_string(&mut rv).unwrap();
+    assert_eq!(read, 43);
+    assert_eq!(rv, "{\"nan\":0.0,\"inf\":0.0     ,\"-inf\":-0.0     }");
+}
+
+#[test]
+fn test_reader_string() {
+    let json = r#"{"nan":"nan","Infinity":"-Infinity","other":NaN}"#;
+    assert_eq!(json.len(), 48);
+    let mut rdr = JsonCompatRead::wrap(json.as_bytes());
+    let mut rv = String::new();
+    let read = rdr.read_to_string(&mut rv).unwrap();
+    assert_eq!(read, 48);
+    assert_eq!(
+        rv,
+        "{\"nan\":\"nan\",\"Infinity\":\"-Infinity\",\"other\":0.0}"
+    );
+}
+
+#[test]
+fn test_reader_string_escaping() {
+    let json = r#""NaN\"NaN\"NaN""#;
+    assert_eq!(json.len(), 15);
+    let mut rdr = JsonCompatRead::wrap(json.as_bytes());
+    let mut rv = String::new();
+    let read = rdr.read_to_string(&mut rv).unwrap();
+    assert_eq!(read, 15);
+    assert_eq!(rv, r#""NaN\"NaN\"NaN""#);
+}
+
+#[test]
+fn test_reader_with_capacity() {
+    let json = r#"{"nan":NaN,"inf":Infinity,"-inf":-Infinity}"#;
+    let mut rdr = JsonCompatRead::with_capacity(json.as_bytes(), 4);
+    let mut rv = Vec::new();
+    let mut chunk = [0; 3];
+    loop {
+        let read = rdr.read(&mut chunk).unwrap();
+        if read == 0 {
+            break;
+        }
+        rv.extend_from_slice(&chunk[..read]);
+    }
+    assert_eq!(
+        String::from_utf8(rv).unwrap(),
+        "{\"nan\":0.0,\"inf\":0.0     ,\"-inf\":-0.0     }"
+    );
+
+    let mut rdr = JsonCompatRead::with_capacity(json.as_bytes(), 1024);
+    let mut rv = String::new();
+    rdr.read_to_string(&mut rv).unwrap();
+    assert_eq!(rv, "{\"nan\":0.0,\"inf\":0.0     ,\"-inf\":-0.0     }");
+}
+
+#[test]
+fn test_reader_read_to_end() {
+    let json = r#"{"nan":NaN,"inf":Infinity,"-inf":-Infinity}"#;
+    let mut rdr = JsonCompatRead::with_capacity(json.as_bytes(), 8);
+    let mut chunk = [0; 4];
+    rdr.read_exact(&mut chunk).unwrap();
+    let mut rv = chunk.to_vec();
+    let read = rdr.read_to_end(&mut rv).unwrap();
+    assert_eq!(read, 39);
+    assert_eq!(
+        String::from_utf8(rv).unwrap(),
+        "{\"nan\":0.0,\"inf\":0.0     ,\"-inf\":-0.0     }"
+    );
+
+    let mut rdr = JsonCompatRead::wrap(&b"[\"\xff\", NaN]"[..]);
+    let mut rv = "prefix".to_string();
+    assert!(rdr.read_to_string(&mut rv).is_err());
+    assert_eq!(rv, "prefix");
+}
+
+#[test]
+fn test_reader_accessors() {
+    let json = b"[NaN]";
+    let mut rdr = JsonCompatRead::wrap(&json[..]);
+    let mut buf = [0; 2];
+    rdr.read_exact(&mut buf).unwrap();
+    assert_eq!(rdr.get_ref().len(), 3);
+    assert_eq!(rdr.get_mut().len(), 3);
+    assert_eq!(rdr.into_inner(), b"aN]");
+}
+
+#[test]
+fn test_reader_counters() {
+    let json = r#"{"nan":NaN,"inf":Infinity,"-inf":-Infinity}"#;
+    let mut rdr = JsonCompatRead::wrap(json.as_bytes());
+    let mut rv = String::new();
+    rdr.read_to_string(&mut rv).unwrap();
+    assert_eq!(rdr.bytes_processed(), 43);
+    assert_eq!(
+        rdr.replacements(),
+        TranslateStats {
+            nan: 1,
+            pos_inf: 1,
+            neg_inf: 1,
+            big_int: 0,
+        }
+    );
+}
+
+#[test]
+fn test_reader_limited() {
+    let json = b"[NaN, Infinity]";
+    let mut rdr = JsonCompatRead::wrap_limited(&json[..], 15);
+    let mut rv = String::new();
+    rdr.read_to_string(&mut rv).unwrap();
+    assert_eq!(rv, "[0.0, 0.0     ]");
+
+    let mut rdr = JsonCompatRead::wrap_limited(&json[..], 14);
+    let err = rdr.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner = err.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+    assert_eq!(inner.kind(), ErrorKind::LimitExceeded);
+    assert_eq!(inner.offset(), Some(14));
+
+    let mut rdr = JsonCompatRead::wrap_limited(&json[..], 4);
+    let mut buf = [0; 16];
+    assert_eq!(rdr.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf[..4], b"[0.0");
+    assert!(rdr.read(&mut buf).is_err());
+}
+
+#[test]
+fn test_reader_check_eof() {
+    let mut rdr = JsonCompatRead::wrap(&b"-Infin"[..]);
+    let mut rv = Vec::new();
+    rdr.read_to_end(&mut rv).unwrap();
+    assert_eq!(&rv[..], b"-0.0  ");
+    assert_eq!(rdr.verify().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+    let mut rdr = JsonCompatRead::wrap(&b"-Infin"[..]).check_eof(true);
+    let err = rdr.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let mut rdr = JsonCompatRead::wrap(&b"[NaN"[..]).check_eof(true);
+    let mut buf = [0; 2];
+    assert_eq!(rdr.read(&mut buf).unwrap(), 2);
+    assert_eq!(rdr.read(&mut buf).unwrap(), 2);
+    assert_eq!(rdr.read(&mut buf).unwrap(), 0);
+    assert!(rdr.verify().is_ok());
+
+    let mut rdr = JsonCompatRead::wrap(&b"[Na"[..]).check_eof(true);
+    assert_eq!(rdr.read(&mut buf).unwrap(), 2);
+    assert_eq!(rdr.read(&mut buf).unwrap(), 1);
+    let err = rdr.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_reader_options() {
+    let json = b"[NaN, NaN, NaN]";
+    let rdr = JsonCompatRead::with_options(&json[..], Options::new().max_replacements(2));
+    let err = crate::test_support::read_fragmented(rdr, 4).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner = err.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+    assert_eq!(inner.kind(), ErrorKind::BudgetExceeded);
+    assert_eq!(inner.offset(), Some(11));
+
+    let mut rdr = JsonCompatRead::with_options(&json[..], Options::new().max_replacements(2));
+    assert!(rdr.read_to_end(&mut Vec::new()).is_err());
+
+    let json = b"#!/usr/bin/python\n{\"x\": Infinity}";
+    let mut rdr = JsonCompatRead::with_options(&json[..], Options::new().skip_prefix(64));
+    let mut rv = Vec::new();
+    rdr.read_to_end(&mut rv).unwrap();
+    assert_eq!(&rv[..], &b"                  {\"x\": 0.0     }"[..]);
+    assert_eq!(rdr.skipped_prefix(), b"#!/usr/bin/python\n");
+    let json = b"[2024-01-01 INFO] {\"x\": NaN}";
+    let rdr = JsonCompatRead::with_options(&json[..], Options::new().skip_prefix(64));
+    let rv = crate::test_support::read_fragmented(rdr, 1).unwrap();
+    assert_eq!(&rv[..], &b"                  {\"x\": 0.0}"[..]);
+}
+
+#[test]
+fn test_reader_misbehaving() {
+    struct LyingRead;
+
+    impl Read for LyingRead {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            Ok(buf.len() + 1)
+        }
+    }
+
+    let mut rdr = JsonCompatRead::wrap(LyingRead);
+    let err = rdr.read(&mut [0; 4]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_reader_split_tokens() {
+    // tokens that straddle read boundaries are handled as the state of the
+    // translator is carried over from one read to the next.
+    struct OneByteRead<'a>(&'a [u8]);
+
+    impl<'a> Read for OneByteRead<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&b, rest)), Some(out)) => {
+                    *out = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    let json = br#"[NaN,-Infinity,"NaN\"",12345678901234567890123]"#;
+    let mut rdr = JsonCompatRead::wrap(OneByteRead(json));
+    let mut rv = Vec::new();
+    let mut chunk = [0; 2];
+    loop {
+        let read = rdr.read(&mut chunk).unwrap();
+        if read == 0 {
+            break;
+        }
+        rv.extend_from_slice(&chunk[..read]);
+    }
+    assert_eq!(
+        &rv[..],
+        &br#"[0.0,-0.0     ,"NaN\"",12345678901234567890123]"#[..]
+    );
+}
+
+#[test]
+fn test_reader_repair_surrogates() {
+    let json = br#"{"a": "\ud800", "b": "\ud83d\ude00", "c": NaN}"#;
+    let expected = br#"{"a": "\uFFFD", "b": "\ud83d\ude00", "c": 0.0}"#;
+    let options = Options::new().repair_surrogates(true);
+    for chunk_size in 1..=json.len() {
+        let inner = crate::test_support::FragmentingReader::new(&json[..], chunk_size);
+        let rdr = JsonCompatRead::with_options(inner, options.clone());
+        let rv = crate::test_support::read_fragmented(rdr, chunk_size).unwrap();
+        assert_eq!(&rv[..], &expected[..]);
+
+        let inner = crate::test_support::FragmentingReader::new(&json[..], chunk_size);
+        let mut rdr = JsonCompatRead::with_options(inner, options.clone());
+        let mut rv = vec![0; 4];
+        let read = rdr.read(&mut rv).unwrap();
+        rv.truncate(read);
+        rdr.read_to_end(&mut rv).unwrap();
+        assert_eq!(&rv[..], &expected[..]);
+        assert_eq!(rdr.bytes_processed(), json.len() as u64);
+    }
+}
+
+#[test]
+fn test_reader_reject() {
+    let options = Options::new().reject(true);
+    let json = b"[1, 2, 3, 123456789012345678901234567890, 4]";
+    let mut rdr = JsonCompatRead::with_options(
+        crate::test_support::FragmentingReader::new(&json[..], 3),
+        options,
+    );
+    let mut rv = Vec::new();
+    let mut buf = [0; 4];
+    let err = loop {
+        match rdr.read(&mut buf) {
+            Ok(n) => rv.extend_from_slice(&buf[..n]),
+            Err(err) => break err,
+        }
+    };
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner = err.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+    assert_eq!(inner.offset(), Some(10));
+    assert_eq!(inner.token_kind(), Some(TokenKind::BigInt));
+    assert!(rv.len() < json.len());
+    assert!(rdr.read(&mut buf).is_err());
+}
+
+#[test]
+fn test_reader_read_to_end_error() {
+    let options = Options::new().max_replacements(1);
+    let mut rdr = JsonCompatRead::with_options(&b"[NaN, NaN, 1, 2, 3]"[..], options);
+    let mut rv = b"prefix".to_vec();
+    assert!(rdr.read_to_end(&mut rv).is_err());
+    assert_eq!(&rv[..], b"prefix[0.0, 0.0");
+
+    let options = Options::new().reject(true);
+    let mut rdr = JsonCompatRead::with_options(&b"[1, NaN, 2]"[..], options);
+    let mut rv = Vec::new();
+    assert!(rdr.read_to_end(&mut rv).is_err());
+    assert_eq!(&rv[..], b"[1, ");
+}
+
+#[test]
+fn test_reader_on_replacement() {
+    use std::sync::{Arc, Mutex};
+
+    let json = b"[NaN, \"NaN\", -Infinity, Infinity]";
+    for &chunk in &[1, 3, 100] {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut rdr = JsonCompatRead::wrap(crate::test_support::FragmentingReader::new(
+            &json[..],
+            chunk,
+        ))
+        .on_replacement(move |kind, offset| sink.lock().unwrap().push((kind, offset)));
+        let mut rv = Vec::new();
+        let mut buf = [0; 2];
+        loop {
+            match rdr.read(&mut buf).unwrap() {
+                0 => break,
+                n => rv.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(rv, b"[0.0, \"NaN\", -0.0     , 0.0     ]");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (TokenKind::NaN, 1),
+                (TokenKind::NegInfinity, 14),
+                (TokenKind::PosInfinity, 24),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_reader_position() {
+    use crate::test_support::FragmentingReader;
+
+    let json = b"[\n  NaN,\r\n  \"a\\nb\"\n]";
+    let tuple = |pos: Position| (pos.offset, pos.line, pos.column);
+    for &capacity in &[0, 4, 64] {
+        let mut rdr = JsonCompatRead::with_capacity(FragmentingReader::new(&json[..], 3), capacity);
+        assert_eq!(tuple(rdr.position()), (0, 1, 0));
+        let mut byte = [0; 1];
+        let mut positions = Vec::new();
+        while rdr.read(&mut byte).unwrap() == 1 {
+            positions.push(tuple(rdr.position()));
+        }
+        assert_eq!(positions.len(), json.len());
+        assert_eq!(positions[6], (7, 2, 5));
+        assert_eq!(positions[8], (9, 2, 7));
+        assert_eq!(positions[9], (10, 3, 0));
+        assert_eq!(positions[19], (20, 4, 1));
+    }
+
+    let mut rdr = JsonCompatRead::with_capacity(&json[..], 8);
+    rdr.read_exact(&mut [0; 2]).unwrap();
+    rdr.read_to_end(&mut Vec::new()).unwrap();
+    assert_eq!(tuple(rdr.position()), (20, 4, 1));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_reader_trace_start() {
+    let mut rdr = JsonCompatRead::wrap(&b"[NaN, 1]"[..]);
+    assert!(rdr.trace_start.is_none());
+    rdr.read_exact(&mut [0; 2]).unwrap();
+    assert!(rdr.trace_start.is_some());
+    rdr.read_to_end(&mut Vec::new()).unwrap();
+    assert!(rdr.trace_start.is_none());
+    assert!(rdr.traced);
+    rdr.read_to_end(&mut Vec::new()).unwrap();
+    assert!(rdr.trace_start.is_none());
+}
@@ -0,0 +1,550 @@
+use std::char;
+use std::convert::TryFrom;
+
+use crate::error::{Error, ErrorKind};
+
+const MAX_DEPTH: usize = 128;
+
+/// How `repr_to_json` writes bytes literals.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BytesEncoding {
+    /// Standard base64 with padding.
+    #[default]
+    Base64,
+    /// Lowercase hex digits.
+    Hex,
+}
+
+/// Options that control `repr_to_json_with_options`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReprOptions {
+    bytes_encoding: BytesEncoding,
+}
+
+impl ReprOptions {
+    /// Creates the default options.
+    pub fn new() -> ReprOptions {
+        ReprOptions::default()
+    }
+
+    /// Sets how bytes literals like `b'\\x00'` are written.
+    ///
+    /// JSON has no way to represent bytes, so they are decoded and written as
+    /// a string in the given encoding.  The default is base64.
+    pub fn bytes_encoding(mut self, encoding: BytesEncoding) -> ReprOptions {
+        self.bytes_encoding = encoding;
+        self
+    }
+}
+
+struct Converter<'a> {
+    options: &'a ReprOptions,
+    input: &'a [u8],
+    pos: usize,
+    depth: usize,
+    out: Vec<u8>,
+}
+
+impl<'a> Converter<'a> {
+    fn error(&self, kind: ErrorKind, pos: usize) -> Error {
+        Error::new(kind, pos as u64)
+    }
+
+    /// Fails at the current position, with `UnexpectedEof` at the end.
+    fn invalid(&self) -> Error {
+        if self.pos >= self.input.len() {
+            self.error(ErrorKind::UnexpectedEof, self.pos)
+        } else {
+            self.error(ErrorKind::InvalidToken, self.pos)
+        }
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(&b) = self.input.get(self.pos) {
+            if !matches!(b, b' ' | b'\t' | b'\n' | b'\r') {
+                return Some(b);
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn value(&mut self) -> Result<(), Error> {
+        match self.peek() {
+            Some(b'{') => self.dict(),
+            Some(b'[') => self.sequence(b']'),
+            Some(b'(') => self.sequence(b')'),
+            // truncated or recursive structures are written with an ellipsis
+            Some(b'.') if self.input[self.pos..].starts_with(b"...") => {
+                self.pos += 3;
+                self.out.extend_from_slice(b"null");
+                Ok(())
+            }
+            Some(b'\'') | Some(b'"') => self.string(),
+            Some(b'-') | Some(b'+') | Some(b'.') | Some(b'0'..=b'9') => self.number(),
+            Some(b'a'..=b'z') | Some(b'A'..=b'Z') | Some(b'_') => self.keyword(),
+            _ => Err(self.invalid()),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), Error> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(ErrorKind::LimitExceeded, self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Converts the items of a list or tuple up to `close` into an array.
+    fn sequence(&mut self, close: u8) -> Result<(), Error> {
+        self.enter()?;
+        self.out.push(b'[');
+        self.items(close, true)
+    }
+
+    /// Converts the remaining items of an array up to `close`.
+    fn items(&mut self, close: u8, mut first: bool) -> Result<(), Error> {
+        loop {
+            match self.peek() {
+                Some(b) if b == close => break,
+                _ if !first => self.out.push(b','),
+                _ => {}
+            }
+            self.value()?;
+            first = false;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b) if b == close => break,
+                _ => return Err(self.invalid()),
+            }
+        }
+        self.pos += 1;
+        self.depth -= 1;
+        self.out.push(b']');
+        Ok(())
+    }
+
+    /// Converts a dict, or a set which is only known after the first item.
+    fn dict(&mut self) -> Result<(), Error> {
+        self.enter()?;
+        let start = self.out.len();
+        self.out.push(b'{');
+        let mut first = true;
+        loop {
+            match self.peek() {
+                Some(b'}') => break,
+                _ if !first => self.out.push(b','),
+                _ => {}
+            }
+            let key = self.out.len();
+            self.value()?;
+            if first && matches!(self.peek(), Some(b',') | Some(b'}')) {
+                if self.peek() == Some(b',') {
+                    self.pos += 1;
+                }
+                self.out[start] = b'[';
+                return self.items(b'}', false);
+            }
+            self.stringify_key(key);
+            if self.peek() != Some(b':') {
+                return Err(self.invalid());
+            }
+            self.pos += 1;
+            self.out.push(b':');
+            self.value()?;
+            first = false;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => break,
+                _ => return Err(self.invalid()),
+            }
+        }
+        self.pos += 1;
+        self.depth -= 1;
+        self.out.push(b'}');
+        Ok(())
+    }
+
+    /// Turns the key written at `start` into a string if it is not one.
+    fn stringify_key(&mut self, start: usize) {
+        if self.out.get(start) != Some(&b'"') {
+            let text = self.out.split_off(start);
+            self.out.push(b'"');
+            for c in String::from_utf8_lossy(&text).chars() {
+                push_char(&mut self.out, c);
+            }
+            self.out.push(b'"');
+        }
+    }
+
+    fn keyword(&mut self) -> Result<(), Error> {
+        let start = self.pos;
+        while let Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') = self.input.get(self.pos) {
+            self.pos += 1;
+        }
+        let replacement: &[u8] = match &self.input[start..self.pos] {
+            b"True" => b"true",
+            b"False" => b"false",
+            b"None" | b"Ellipsis" => b"null",
+            b"b" if matches!(self.input.get(self.pos), Some(b'\'') | Some(b'"')) => {
+                return self.bytes();
+            }
+            // the unicode prefix of Python 2
+            b"u" | b"U" if matches!(self.input.get(self.pos), Some(b'\'') | Some(b'"')) => {
+                return self.string();
+            }
+            // an empty set has no literal
+            b"set" if self.input[self.pos..].starts_with(b"()") => {
+                self.pos += 2;
+                b"[]"
+            }
+            b"nan" => b"NaN",
+            b"inf" => b"Infinity",
+            _ => return Err(self.error(ErrorKind::InvalidToken, start)),
+        };
+        self.out.extend_from_slice(replacement);
+        Ok(())
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.input.get(self.pos) {
+            self.out.push(self.input[self.pos]);
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<(), Error> {
+        match self.input.get(self.pos) {
+            Some(b'-') => {
+                self.out.push(b'-');
+                self.pos += 1;
+            }
+            Some(b'+') => self.pos += 1,
+            _ => {}
+        }
+        if let Some(b'a'..=b'z') = self.input.get(self.pos) {
+            // only `-inf` makes sense here
+            let start = self.pos;
+            self.keyword()?;
+            if &self.input[start..self.pos] != b"inf" {
+                return Err(self.error(ErrorKind::InvalidToken, start));
+            }
+            return Ok(());
+        }
+        let start = self.pos;
+        if self.input.get(self.pos) == Some(&b'.') {
+            self.out.push(b'0');
+        } else if self.digits() == 0 {
+            return Err(self.invalid());
+        }
+        if self.input.get(self.pos) == Some(&b'.') {
+            self.out.push(b'.');
+            self.pos += 1;
+            if self.digits() == 0 {
+                if self.pos == start + 1 {
+                    return Err(self.error(ErrorKind::InvalidToken, start));
+                }
+                self.out.push(b'0');
+            }
+        }
+        if let Some(&(b'e' | b'E')) = self.input.get(self.pos) {
+            self.out.push(b'e');
+            self.pos += 1;
+            if let Some(&sign @ (b'+' | b'-')) = self.input.get(self.pos) {
+                self.out.push(sign);
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.invalid());
+            }
+        }
+        Ok(())
+    }
+
+    fn hex_escape(&mut self, len: usize) -> Result<u32, Error> {
+        let start = self.pos;
+        let digits = self
+            .input
+            .get(start..start + len)
+            .ok_or_else(|| self.error(ErrorKind::UnexpectedEof, self.input.len()))?;
+        let value = std::str::from_utf8(digits)
+            .ok()
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error(ErrorKind::InvalidToken, start))?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<(), Error> {
+        let quote = self.input[self.pos];
+        self.pos += 1;
+        self.out.push(b'"');
+        loop {
+            let b = match self.input.get(self.pos) {
+                Some(&b) => b,
+                None => return Err(self.invalid()),
+            };
+            self.pos += 1;
+            match b {
+                b if b == quote => break,
+                b'\\' => self.escape()?,
+                b'"' => self.out.extend_from_slice(b"\\\""),
+                0x00..=0x1f => push_char(&mut self.out, char::from(b)),
+                b => self.out.push(b),
+            }
+        }
+        self.out.push(b'"');
+        Ok(())
+    }
+
+    /// Converts the escape after a backslash in a string.
+    fn escape(&mut self) -> Result<(), Error> {
+        let start = self.pos - 1;
+        let b = match self.input.get(self.pos) {
+            Some(&b) => b,
+            None => return Err(self.invalid()),
+        };
+        self.pos += 1;
+        let c = match self.simple_escape(b)? {
+            Some(value) => char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER),
+            None if b == b'u' => {
+                let value = self.hex_escape(4)?;
+                match char::from_u32(value) {
+                    Some(c) => c,
+                    None => {
+                        // Python strings can hold lone surrogates, keep the
+                        // escape and leave it to the JSON parser.
+                        self.out
+                            .extend_from_slice(format!("\\u{:04x}", value).as_bytes());
+                        return Ok(());
+                    }
+                }
+            }
+            None if b == b'U' => char::from_u32(self.hex_escape(8)?)
+                .ok_or_else(|| self.error(ErrorKind::InvalidToken, start))?,
+            None => return Err(self.error(ErrorKind::InvalidToken, start)),
+        };
+        push_char(&mut self.out, c);
+        Ok(())
+    }
+
+    /// Decodes the escapes that strings and bytes have in common.
+    fn simple_escape(&mut self, b: u8) -> Result<Option<u32>, Error> {
+        Ok(Some(match b {
+            b'\\' | b'\'' | b'"' => u32::from(b),
+            b'n' => 0x0a,
+            b'r' => 0x0d,
+            b't' => 0x09,
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'v' => 0x0b,
+            b'0'..=b'7' => {
+                let mut value = u32::from(b - b'0');
+                for _ in 0..2 {
+                    match self.input.get(self.pos) {
+                        Some(&d @ b'0'..=b'7') => {
+                            value = value * 8 + u32::from(d - b'0');
+                            self.pos += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                value
+            }
+            b'x' => self.hex_escape(2)?,
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Converts a bytes literal into an encoded string.
+    fn bytes(&mut self) -> Result<(), Error> {
+        let quote = self.input[self.pos];
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let b = match self.input.get(self.pos) {
+                Some(&b) => b,
+                None => return Err(self.invalid()),
+            };
+            self.pos += 1;
+            match b {
+                b if b == quote => break,
+                b'\\' => {
+                    let start = self.pos - 1;
+                    let value = match self.input.get(self.pos) {
+                        Some(&b) => {
+                            self.pos += 1;
+                            self.simple_escape(b)?
+                        }
+                        None => return Err(self.invalid()),
+                    };
+                    match value.and_then(|value| u8::try_from(value).ok()) {
+                        Some(value) => bytes.push(value),
+                        None => return Err(self.error(ErrorKind::InvalidToken, start)),
+                    }
+                }
+                b => bytes.push(b),
+            }
+        }
+        self.out.push(b'"');
+        match self.options.bytes_encoding {
+            BytesEncoding::Base64 => encode_base64(&bytes, &mut self.out),
+            BytesEncoding::Hex => {
+                for b in bytes {
+                    self.out.extend_from_slice(format!("{:02x}", b).as_bytes());
+                }
+            }
+        }
+        self.out.push(b'"');
+        Ok(())
+    }
+}
+
+/// Appends the padded standard base64 encoding of `bytes`.
+fn encode_base64(bytes: &[u8], out: &mut Vec<u8>) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (idx, &b)| n | u32::from(b) << (16 - 8 * idx));
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * idx) & 63) as usize]);
+            } else {
+                out.push(b'=');
+            }
+        }
+    }
+}
+
+/// Appends a character to a JSON string.
+fn push_char(out: &mut Vec<u8>, c: char) {
+    match c {
+        '"' => out.extend_from_slice(b"\\\""),
+        '\\' => out.extend_from_slice(b"\\\\"),
+        '\n' => out.extend_from_slice(b"\\n"),
+        '\r' => out.extend_from_slice(b"\\r"),
+        '\t' => out.extend_from_slice(b"\\t"),
+        '\0'..='\x1f' | '\x7f' => out.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes()),
+        c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+    }
+}
+
+/// Converts the `repr` of a Python value into Python JSON.
+///
+/// Logged Python structures are often written with `str()` or `repr()`
+/// instead of `json.dumps`, which uses single quoted strings and the
+/// `True`, `False` and `None` keywords.  This converts such output into a
+/// new buffer: strings are requoted, the keywords become `true`, `false`
+/// and `null`, tuples and sets become arrays and keys that are not
+/// strings are turned into strings.  `{}` is always an empty dict like in
+/// Python, the empty set is written as `set()` and becomes `[]`.  The
+/// floats `nan` and `inf` become `NaN` and `Infinity`, so the result is
+/// meant to be passed to `from_slice` or `from_slice_lossless`.  Bytes
+/// literals become base64 encoded strings and the `u` prefix of Python 2
+/// strings is dropped.  The ellipsis that marks truncated or recursive
+/// structures becomes `null`.
+///
+/// Unlike the translation this is not streaming and fails with
+/// `ErrorKind::InvalidToken` on input it does not understand.
+///
+/// ```
+/// # use python_json_read_adapter::repr_to_json;
+/// let json = repr_to_json(b"{'a': True, 'b': [None, nan], 1: 'it\\'s'}").unwrap();
+/// assert_eq!(json, br#"{"a":true,"b":[null,NaN],"1":"it's"}"#);
+/// ```
+pub fn repr_to_json(input: &[u8]) -> Result<Vec<u8>, Error> {
+    repr_to_json_with_options(input, &ReprOptions::default())
+}
+
+/// Converts the `repr` of a Python value into Python JSON with options.
+///
+/// ```
+/// # use python_json_read_adapter::{repr_to_json_with_options, BytesEncoding, ReprOptions};
+/// let options = ReprOptions::new().bytes_encoding(BytesEncoding::Hex);
+/// let json = repr_to_json_with_options(br"{'blob': b'\x00\xffA'}", &options).unwrap();
+/// assert_eq!(json, br#"{"blob":"00ff41"}"#);
+/// ```
+pub fn repr_to_json_with_options(input: &[u8], options: &ReprOptions) -> Result<Vec<u8>, Error> {
+    convert(input, options).map_err(|err| err.with_source(input))
+}
+
+fn convert(input: &[u8], options: &ReprOptions) -> Result<Vec<u8>, Error> {
+    let mut converter = Converter {
+        options,
+        input,
+        pos: 0,
+        depth: 0,
+        out: Vec::with_capacity(input.len()),
+    };
+    converter.value()?;
+    if converter.peek().is_some() {
+        return Err(converter.invalid());
+    }
+    Ok(converter.out)
+}
+
+#[test]
+fn test_repr_to_json() {
+    let convert =
+        |repr: &str| repr_to_json(repr.as_bytes()).map(|json| String::from_utf8(json).unwrap());
+    assert_eq!(
+        convert(r#"{'name': "it's", 'ok': False, 'n': None, 'x': [1, -2.5, 1e-07, -inf, .5, 3.]}"#)
+            .unwrap(),
+        r#"{"name":"it's","ok":false,"n":null,"x":[1,-2.5,1e-07,-Infinity,0.5,3.0]}"#
+    );
+    assert_eq!(
+        convert(r#"'a\"b\n\x00é\U0001f600\ud800\101'"#).unwrap(),
+        "\"a\\\"b\\n\\u0000\u{e9}\u{1f600}\\ud800A\""
+    );
+    assert_eq!(
+        convert("{1: 'a', None: 'b', 2.5: []}").unwrap(),
+        r#"{"1":"a","null":"b","2.5":[]}"#
+    );
+    assert_eq!(convert("  [ ]  ").unwrap(), "[]");
+    assert_eq!(
+        convert("{'a': [1, [...]], 'b': ..., 'c': Ellipsis, 'd': .5}").unwrap(),
+        r#"{"a":[1,[null]],"b":null,"c":null,"d":0.5}"#
+    );
+    assert_eq!(
+        convert(r#"{u'name': u'caf\xe9', U"x": [u'', u"it's"]}"#).unwrap(),
+        "{\"name\":\"caf\u{e9}\",\"x\":[\"\",\"it's\"]}"
+    );
+    assert_eq!(
+        convert(r#"[b'', b'f', b'fo', b"foo", b'\x00\xff\n\'\101', b'foob']"#).unwrap(),
+        r#"["","Zg==","Zm8=","Zm9v","AP8KJ0E=","Zm9vYg=="]"#
+    );
+    assert_eq!(
+        convert("[(1, 'a'), (2,), (), ([],)]").unwrap(),
+        r#"[[1,"a"],[2],[],[[]]]"#
+    );
+    assert_eq!(convert("{(1, 2): 'x'}").unwrap(), r#"{"[1,2]":"x"}"#);
+
+    let err = |repr: &str| {
+        let err = repr_to_json(repr.as_bytes()).unwrap_err();
+        (err.kind(), err.offset().unwrap())
+    };
+    assert_eq!(err("[1, nope]"), (ErrorKind::InvalidToken, 4));
+    assert_eq!(err("{'a' 1}"), (ErrorKind::InvalidToken, 5));
+    assert_eq!(err("['abc"), (ErrorKind::UnexpectedEof, 5));
+    assert_eq!(err("[1] 2"), (ErrorKind::InvalidToken, 4));
+    assert_eq!(err("-nan"), (ErrorKind::InvalidToken, 1));
+    assert_eq!(err("(1, 2]"), (ErrorKind::InvalidToken, 5));
+    assert_eq!(err("(,)"), (ErrorKind::InvalidToken, 1));
+    assert_eq!(err("{1, 2: 3}"), (ErrorKind::InvalidToken, 5));
+    assert_eq!(err("set(1)"), (ErrorKind::InvalidToken, 0));
+    assert_eq!(err(r"b'\u0041'"), (ErrorKind::InvalidToken, 2));
+    assert_eq!(err(r"b'\777'"), (ErrorKind::InvalidToken, 2));
+    assert_eq!(err("b 'x'"), (ErrorKind::InvalidToken, 0));
+    assert_eq!(err("ur'x'"), (ErrorKind::InvalidToken, 0));
+    assert_eq!(err("[..]"), (ErrorKind::InvalidToken, 1));
+    assert_eq!(err(&"[".repeat(200)), (ErrorKind::LimitExceeded, 128));
+}
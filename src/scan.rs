@@ -0,0 +1,134 @@
+use crate::backend::Backend;
+use crate::translator::TokenKind;
+
+/// Iterates over the tokens that the translation would rewrite.
+///
+/// Like in the translator a byte that breaks off a partial token is consumed
+/// without being looked at, even if it is a quote.
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = TokenKind;
+
+    fn next(&mut self) -> Option<TokenKind> {
+        loop {
+            let rest = self.bytes.get(self.pos..)?;
+            self.pos += Backend::Auto.skip(rest, false);
+            let start = self.pos;
+            let (token, kind): (&[u8], _) = match self.bytes.get(start)? {
+                b'"' => {
+                    self.pos += 1;
+                    self.skip_string();
+                    continue;
+                }
+                b'N' => (b"NaN", TokenKind::NaN),
+                _ if start > 0 && self.bytes[start - 1] == b'-' => {
+                    (b"Infinity", TokenKind::NegInfinity)
+                }
+                _ => (b"Infinity", TokenKind::PosInfinity),
+            };
+            let matched = self.bytes[start..]
+                .iter()
+                .zip(token)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if matched == token.len() {
+                self.pos = start + matched;
+                return Some(kind);
+            }
+            self.pos = start + matched + 1;
+        }
+    }
+}
+
+impl<'a> Tokens<'a> {
+    fn skip_string(&mut self) {
+        while let Some(rest) = self.bytes.get(self.pos..) {
+            self.pos += Backend::Auto.skip(rest, true);
+            match self.bytes.get(self.pos) {
+                Some(b'\\') => self.pos += 2,
+                Some(_) => {
+                    self.pos += 1;
+                    return;
+                }
+                None => return,
+            }
+        }
+    }
+}
+
+/// Checks if a document contains tokens that the translation would rewrite.
+///
+/// This scans the document without modifying it, so it can be used as a
+/// cheap check to decide if a document needs to be translated at all.  Only
+/// the exact spellings `NaN`, `Infinity` and `-Infinity` outside of strings
+/// are found.
+///
+/// ```
+/// # use python_json_read_adapter::contains_python_tokens;
+/// assert!(contains_python_tokens(br#"{"x": NaN}"#));
+/// assert!(!contains_python_tokens(br#"{"x": "NaN"}"#));
+/// ```
+pub fn contains_python_tokens(bytes: &[u8]) -> bool {
+    Tokens { bytes, pos: 0 }.next().is_some()
+}
+
+/// Checks if a document contains a specific kind of token.
+///
+/// This works like `contains_python_tokens` but only looks for one kind.
+/// Big integers are not rewritten and thus never found.
+///
+/// ```
+/// # use python_json_read_adapter::{contains_python_token, TokenKind};
+/// let json = br#"[NaN, -Infinity]"#;
+/// assert!(contains_python_token(json, TokenKind::NegInfinity));
+/// assert!(!contains_python_token(json, TokenKind::PosInfinity));
+/// ```
+pub fn contains_python_token(bytes: &[u8], kind: TokenKind) -> bool {
+    Tokens { bytes, pos: 0 }.any(|found| found == kind)
+}
+
+#[test]
+fn test_contains_python_tokens() {
+    let check = |json: &[u8]| {
+        let mut copy = json.to_vec();
+        // truncated documents fail to verify but still count their tokens
+        let mut translator = crate::Translator::new();
+        translator.feed(&mut copy).unwrap();
+        let stats = translator.stats();
+        assert_eq!(
+            contains_python_tokens(json),
+            stats.total() > 0,
+            "{:?}",
+            json
+        );
+        for &kind in &[
+            TokenKind::NaN,
+            TokenKind::PosInfinity,
+            TokenKind::NegInfinity,
+        ] {
+            assert_eq!(contains_python_token(json, kind), stats.get(kind) > 0);
+        }
+    };
+    check(b"[1, 2, 3]");
+    check(b"[\"NaN\\\" Infinity\", 1]");
+    check(b"[\"\\\\\", NaN]");
+    check(b"-Infinity");
+    check(b"Infinity");
+    check(b"[Infin");
+    check(b"\"unterminated NaN");
+    check(b"[Na, NaN]");
+    check(b"[N\"NaN\"]");
+    check(b"[In\"x\", 1]");
+    check(b"[Infinit\"y\", Infinity\"]");
+    check(b"[NaNaN, NN, Nx\"-Infinity\"]");
+    check(b"[\"a\", Na\"\", \"NaN\"]");
+    check(b"[N\\\"NaN\"]");
+    check(br#"{"a": [1, 2, {"b": "c"}], "d": "Infinity", "e": -Infinity, "f": 1e5}"#);
+    let mut long = vec![b' '; 1000];
+    long.extend_from_slice(b"NaN");
+    check(&long);
+}
@@ -0,0 +1,249 @@
+use crate::error::ErrorKind;
+use crate::options::Options;
+use crate::translator::Translator;
+
+/// Budgets above this are not exercised to keep the self test cheap.
+const MAX_TESTED_BUDGET: usize = 4096;
+
+/// A document with its translation and, if it differs, the translation with
+/// case insensitive matching.
+type Case = (&'static [u8], &'static [u8], Option<&'static [u8]>);
+
+const CASES: &[Case] = &[
+    (
+        br#"{"nan":NaN,"inf":Infinity,"-inf":-Infinity}"#,
+        br#"{"nan":0.0,"inf":0.0     ,"-inf":-0.0     }"#,
+        None,
+    ),
+    (
+        br#"["NaN","\"Infinity","\\",NaN]"#,
+        br#"["NaN","\"Infinity","\\",0.0]"#,
+        None,
+    ),
+    (
+        br#"[1.5e3,-2,true,null,"x"]"#,
+        br#"[1.5e3,-2,true,null,"x"]"#,
+        None,
+    ),
+    // with case folding `inf` is complete and the `i` of `ior` starts a token.
+    // The leading value keeps `Options::skip_prefix` from skipping the `[`.
+    (
+        br#"[1,Inferior,Nax,Nen]"#,
+        br#"[1,0.0erior,0.x,0en]"#,
+        Some(br#"[1,0.0er0or,0.x,0en]"#),
+    ),
+    (b"", b"", None),
+];
+
+/// A single case of a self test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestCase {
+    /// The input document.
+    pub input: Vec<u8>,
+    /// The expected translation or the kind of the expected error.
+    pub expected: Result<Vec<u8>, ErrorKind>,
+    /// The result of translating the input in a single chunk.
+    pub actual: Result<Vec<u8>, ErrorKind>,
+    /// The result of translating the input one byte at a time.
+    pub streamed: Result<Vec<u8>, ErrorKind>,
+}
+
+impl SelfTestCase {
+    fn run(
+        input: Vec<u8>,
+        expected: Result<Vec<u8>, ErrorKind>,
+        options: &Options,
+    ) -> SelfTestCase {
+        SelfTestCase {
+            actual: translate_chunked(&input, input.len().max(1), options),
+            streamed: translate_chunked(&input, 1, options),
+            input,
+            expected,
+        }
+    }
+
+    /// Returns `true` if both translations produced the expected result.
+    pub fn passed(&self) -> bool {
+        self.actual == self.expected && self.streamed == self.expected
+    }
+}
+
+/// The result of `Options::self_test`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// All cases that were run.
+    pub cases: Vec<SelfTestCase>,
+}
+
+impl SelfTestReport {
+    /// Returns `true` if all cases passed.
+    pub fn passed(&self) -> bool {
+        self.cases.iter().all(SelfTestCase::passed)
+    }
+
+    /// Returns the cases that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCase> {
+        self.cases.iter().filter(|case| !case.passed())
+    }
+}
+
+pub(crate) fn run(options: &Options) -> SelfTestReport {
+    let mut cases = Vec::new();
+    for &(input, expected, caseless) in CASES {
+        let expected = match caseless {
+            Some(caseless) if options.case_insensitive => caseless,
+            _ => expected,
+        };
+        let expected = expect(input, Ok(expected.to_vec()), options);
+        cases.push(SelfTestCase::run(input.to_vec(), expected, options));
+    }
+
+    match options.max_replacements {
+        Some(max) if max <= MAX_TESTED_BUDGET => {
+            let within = nan_array(max);
+            let expected = within.replace("NaN", "0.0").into_bytes();
+            let expected = expect(within.as_bytes(), Ok(expected), options);
+            cases.push(SelfTestCase::run(within.into_bytes(), expected, options));
+            let over = nan_array(max + 1);
+            let expected = expect(over.as_bytes(), Err(ErrorKind::BudgetExceeded), options);
+            cases.push(SelfTestCase::run(over.into_bytes(), expected, options));
+        }
+        _ => {}
+    }
+
+    let truncated = b"[1, -Infin";
+    let expected = expect(truncated, Err(ErrorKind::UnexpectedEof), options);
+    cases.push(SelfTestCase::run(truncated.to_vec(), expected, options));
+
+    SelfTestReport { cases }
+}
+
+fn translate_chunked(
+    input: &[u8],
+    chunk_size: usize,
+    options: &Options,
+) -> Result<Vec<u8>, ErrorKind> {
+    let mut buf = input.to_vec();
+    let mut translator = Translator::with_options(options.clone());
+    buf.chunks_mut(chunk_size)
+        .try_for_each(|chunk| translator.feed(chunk))
+        .and_then(|_| translator.finish())
+        .map_err(|err| err.kind())?;
+    Ok(buf)
+}
+
+/// Adjusts the expected result of a document to the rejecting options.
+///
+/// Rejection wins over the budget as it happens at the start of the first
+/// token.  The documents contain no big integers.
+fn expect(
+    input: &[u8],
+    expected: Result<Vec<u8>, ErrorKind>,
+    options: &Options,
+) -> Result<Vec<u8>, ErrorKind> {
+    if options.reject && starts_token(input, options.case_insensitive) {
+        return Err(ErrorKind::RejectedToken);
+    }
+    match options.max_replacements {
+        Some(max) if expected.is_ok() && count_tokens(input, options) > max => {
+            Err(ErrorKind::BudgetExceeded)
+        }
+        _ => expected,
+    }
+}
+
+/// Checks if a token starts outside of a string.
+///
+/// With case folding a lowercase `n` only starts a token if an `a` follows,
+/// like in the translator.
+fn starts_token(input: &[u8], caseless: bool) -> bool {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, &b) in input.iter().enumerate() {
+        match (quoted, escaped, b) {
+            (true, true, _) => escaped = false,
+            (true, false, b'\\') => escaped = true,
+            (_, _, b'"') => quoted = !quoted,
+            (false, _, b'N') | (false, _, b'I') => return true,
+            (false, _, b'i') if caseless => return true,
+            (false, _, b'n') if caseless => {
+                if matches!(input.get(idx + 1), Some(b'a') | Some(b'A')) {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+fn count_tokens(input: &[u8], options: &Options) -> usize {
+    let mut buf = input.to_vec();
+    let mut translator = Translator::with_options(
+        Options::new()
+            .case_insensitive(options.case_insensitive)
+            .plus_infinity(options.plus_infinity),
+    );
+    let _ = translator.feed(&mut buf);
+    translator.stats().total()
+}
+
+fn nan_array(count: usize) -> String {
+    format!("[{}]", vec!["NaN"; count].join(","))
+}
+
+#[test]
+fn test_self_test() {
+    let report = Options::new().self_test();
+    assert!(
+        report.passed(),
+        "{:?}",
+        report.failures().collect::<Vec<_>>()
+    );
+
+    for max in 0..4 {
+        let report = Options::new().max_replacements(max).self_test();
+        assert!(
+            report.passed(),
+            "{:?}",
+            report.failures().collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn test_self_test_options() {
+    use crate::backend::Backend;
+
+    let all = [
+        Options::new().backend(Backend::Scalar),
+        Options::new().skip_prefix(16),
+        Options::new().repair_surrogates(true),
+        Options::new().case_insensitive(true),
+        Options::new().plus_infinity(true),
+        Options::new().reject_binary(true),
+        Options::new().count_big_ints(true),
+        Options::new().reject(true),
+        Options::new().reject(true).case_insensitive(true),
+    ];
+    for options in &all {
+        for max in [None, Some(0), Some(1), Some(3)].iter() {
+            let options = match *max {
+                Some(max) => options.clone().max_replacements(max),
+                None => options.clone(),
+            };
+            let report = options.self_test();
+            assert!(
+                report.passed(),
+                "{:?} {:?}",
+                options,
+                report.failures().collect::<Vec<_>>()
+            );
+        }
+    }
+    let report = Options::new().reject(true).self_test();
+    assert!(report
+        .cases
+        .iter()
+        .any(|case| case.expected == Err(ErrorKind::RejectedToken)));
+}
@@ -0,0 +1,1015 @@
+use std::io;
+
+use serde_json::ser::{CharEscape, CompactFormatter, Formatter};
+use serde_json::Value;
+use serde_self::ser::{self, Serialize};
+
+/// A formatter that writes non-finite floats as Python tokens.
+///
+/// All output other than non-finite floats is produced by the wrapped
+/// formatter, so this can be combined with `PrettyFormatter` or a custom
+/// formatter.
+///
+/// Note that `serde_json::Serializer` writes `null` for non-finite floats
+/// without ever consulting the formatter.  To get `NaN` and `Infinity` into
+/// the output this formatter has to be used with a `PythonSerializer`.
+///
+/// ```
+/// # extern crate serde_self as serde;
+/// # use python_json_read_adapter::{PythonFormatter, PythonSerializer};
+/// use serde_json::ser::PrettyFormatter;
+/// use serde::Serialize;
+///
+/// let mut rv = Vec::new();
+/// let formatter = PythonFormatter::new(PrettyFormatter::with_indent(b" "));
+/// let mut ser = PythonSerializer::with_formatter(&mut rv, formatter);
+/// vec![f64::NAN].serialize(&mut ser).unwrap();
+/// assert_eq!(rv, b"[\n NaN\n]");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PythonFormatter<F = CompactFormatter> {
+    inner: F,
+}
+
+impl<F: Formatter> PythonFormatter<F> {
+    /// Wraps a formatter.
+    pub fn new(inner: F) -> PythonFormatter<F> {
+        PythonFormatter { inner }
+    }
+
+    /// Returns the wrapped formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: Formatter> PythonFormatter<F> {
+    fn write_non_finite<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        value: f64,
+    ) -> io::Result<()> {
+        writer.write_all(if value.is_nan() {
+            b"NaN"
+        } else if value > 0.0 {
+            b"Infinity"
+        } else {
+            b"-Infinity"
+        })
+    }
+}
+
+/// A formatter that writes non-finite floats as `null`.
+///
+/// This matches `simplejson`'s `ignore_nan=True` and produces standard JSON.
+/// Like `PythonFormatter` it wraps another formatter and needs to be used
+/// with a `PythonSerializer`.
+///
+/// ```
+/// # extern crate serde_self as serde;
+/// # use python_json_read_adapter::{NullFormatter, PythonSerializer};
+/// use serde_json::ser::CompactFormatter;
+/// use serde::Serialize;
+///
+/// let mut rv = Vec::new();
+/// let formatter = NullFormatter::new(CompactFormatter);
+/// let mut ser = PythonSerializer::with_formatter(&mut rv, formatter);
+/// vec![1.0, f64::NAN, f64::INFINITY].serialize(&mut ser).unwrap();
+/// assert_eq!(rv, b"[1.0,null,null]");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NullFormatter<F = CompactFormatter> {
+    inner: F,
+}
+
+impl<F: Formatter> NullFormatter<F> {
+    /// Wraps a formatter.
+    pub fn new(inner: F) -> NullFormatter<F> {
+        NullFormatter { inner }
+    }
+
+    /// Returns the wrapped formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn write_non_finite<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        _value: f64,
+    ) -> io::Result<()> {
+        self.inner.write_null(writer)
+    }
+}
+
+macro_rules! forward_formatter {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[inline]
+            fn $name<W: ?Sized + io::Write>(&mut self, writer: &mut W $(, $arg: $ty)*) -> io::Result<()> {
+                self.inner.$name(writer $(, $arg)*)
+            }
+        )*
+    };
+}
+
+macro_rules! impl_non_finite_formatter {
+    ($ty:ident) => {
+        impl<F: Formatter> Formatter for $ty<F> {
+            forward_formatter! {
+                write_null();
+                write_bool(value: bool);
+                write_i8(value: i8);
+                write_i16(value: i16);
+                write_i32(value: i32);
+                write_i64(value: i64);
+                write_i128(value: i128);
+                write_u8(value: u8);
+                write_u16(value: u16);
+                write_u32(value: u32);
+                write_u64(value: u64);
+                write_u128(value: u128);
+                write_number_str(value: &str);
+                begin_string();
+                end_string();
+                write_string_fragment(fragment: &str);
+                write_char_escape(char_escape: CharEscape);
+                begin_array();
+                end_array();
+                begin_array_value(first: bool);
+                end_array_value();
+                begin_object();
+                end_object();
+                begin_object_key(first: bool);
+                end_object_key();
+                begin_object_value();
+                end_object_value();
+                write_raw_fragment(fragment: &str);
+            }
+
+            fn write_f32<W: ?Sized + io::Write>(
+                &mut self,
+                writer: &mut W,
+                value: f32,
+            ) -> io::Result<()> {
+                if value.is_finite() {
+                    self.inner.write_f32(writer, value)
+                } else {
+                    self.write_non_finite(writer, value.into())
+                }
+            }
+
+            fn write_f64<W: ?Sized + io::Write>(
+                &mut self,
+                writer: &mut W,
+                value: f64,
+            ) -> io::Result<()> {
+                if value.is_finite() {
+                    self.inner.write_f64(writer, value)
+                } else {
+                    self.write_non_finite(writer, value)
+                }
+            }
+        }
+    };
+}
+
+impl_non_finite_formatter!(PythonFormatter);
+impl_non_finite_formatter!(NullFormatter);
+
+/// A JSON serializer that hands every float to the formatter.
+///
+/// `serde_json::Serializer` writes `null` for non-finite floats without ever
+/// calling into the formatter.  This serializer produces the same output but
+/// lets the formatter decide how to write non-finite floats.
+pub struct PythonSerializer<W, F = PythonFormatter> {
+    writer: W,
+    formatter: F,
+}
+
+impl<W: io::Write> PythonSerializer<W> {
+    /// Creates a serializer that writes compact Python flavoured JSON.
+    pub fn new(writer: W) -> PythonSerializer<W> {
+        PythonSerializer::with_formatter(writer, PythonFormatter::default())
+    }
+}
+
+impl<W: io::Write, F: Formatter> PythonSerializer<W, F> {
+    /// Creates a serializer with a custom formatter.
+    pub fn with_formatter(writer: W, formatter: F) -> PythonSerializer<W, F> {
+        PythonSerializer { writer, formatter }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwraps the serializer and returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn io_error(err: io::Error) -> serde_json::Error {
+    serde_json::Error::io(err)
+}
+
+fn key_must_be_a_string() -> serde_json::Error {
+    ser::Error::custom("key must be a string")
+}
+
+fn format_escaped_str<W, F>(writer: &mut W, formatter: &mut F, value: &str) -> io::Result<()>
+where
+    W: ?Sized + io::Write,
+    F: Formatter,
+{
+    formatter.begin_string(writer)?;
+    let mut start = 0;
+    for (idx, &byte) in value.as_bytes().iter().enumerate() {
+        let escape = match byte {
+            b'"' => CharEscape::Quote,
+            b'\\' => CharEscape::ReverseSolidus,
+            b'\x08' => CharEscape::Backspace,
+            b'\x0c' => CharEscape::FormFeed,
+            b'\n' => CharEscape::LineFeed,
+            b'\r' => CharEscape::CarriageReturn,
+            b'\t' => CharEscape::Tab,
+            0x00..=0x1f => CharEscape::AsciiControl(byte),
+            _ => continue,
+        };
+        if start < idx {
+            formatter.write_string_fragment(writer, &value[start..idx])?;
+        }
+        formatter.write_char_escape(writer, escape)?;
+        start = idx + 1;
+    }
+    if start < value.len() {
+        formatter.write_string_fragment(writer, &value[start..])?;
+    }
+    formatter.end_string(writer)
+}
+
+macro_rules! serialize_number {
+    ($($name:ident($ty:ty) => $write:ident;)*) => {
+        $(
+            fn $name(self, value: $ty) -> serde_json::Result<()> {
+                self.formatter
+                    .$write(&mut self.writer, value)
+                    .map_err(io_error)
+            }
+        )*
+    };
+}
+
+impl<'a, W: io::Write, F: Formatter> ser::Serializer for &'a mut PythonSerializer<W, F> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Compound<'a, W, F>;
+    type SerializeTuple = Compound<'a, W, F>;
+    type SerializeTupleStruct = Compound<'a, W, F>;
+    type SerializeTupleVariant = Compound<'a, W, F>;
+    type SerializeMap = Compound<'a, W, F>;
+    type SerializeStruct = Compound<'a, W, F>;
+    type SerializeStructVariant = Compound<'a, W, F>;
+
+    serialize_number! {
+        serialize_bool(bool) => write_bool;
+        serialize_i8(i8) => write_i8;
+        serialize_i16(i16) => write_i16;
+        serialize_i32(i32) => write_i32;
+        serialize_i64(i64) => write_i64;
+        serialize_i128(i128) => write_i128;
+        serialize_u8(u8) => write_u8;
+        serialize_u16(u16) => write_u16;
+        serialize_u32(u32) => write_u32;
+        serialize_u64(u64) => write_u64;
+        serialize_u128(u128) => write_u128;
+        serialize_f32(f32) => write_f32;
+        serialize_f64(f64) => write_f64;
+    }
+
+    fn serialize_char(self, value: char) -> serde_json::Result<()> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> serde_json::Result<()> {
+        format_escaped_str(&mut self.writer, &mut self.formatter, value).map_err(io_error)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> serde_json::Result<()> {
+        use serde_self::ser::SerializeSeq;
+        let mut seq = self.serialize_seq(Some(value.len()))?;
+        for byte in value {
+            seq.serialize_element(byte)?;
+        }
+        seq.end()
+    }
+
+    fn serialize_none(self) -> serde_json::Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> serde_json::Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> serde_json::Result<()> {
+        self.formatter
+            .write_null(&mut self.writer)
+            .map_err(io_error)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> serde_json::Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> serde_json::Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end_variant()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> serde_json::Result<Compound<'a, W, F>> {
+        self.formatter
+            .begin_array(&mut self.writer)
+            .map_err(io_error)?;
+        Ok(Compound::new(self, Formatter::end_array))
+    }
+
+    fn serialize_tuple(self, len: usize) -> serde_json::Result<Compound<'a, W, F>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> serde_json::Result<Compound<'a, W, F>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> serde_json::Result<Compound<'a, W, F>> {
+        self.begin_variant(variant)?;
+        let mut compound = self.serialize_seq(Some(len))?;
+        compound.variant = true;
+        Ok(compound)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> serde_json::Result<Compound<'a, W, F>> {
+        self.formatter
+            .begin_object(&mut self.writer)
+            .map_err(io_error)?;
+        Ok(Compound::new(self, Formatter::end_object))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> serde_json::Result<Compound<'a, W, F>> {
+        // with arbitrary_precision numbers and with raw_value raw values
+        // serialize as a magic struct holding their original text.  The
+        // features can be enabled on serde_json by any crate in the build,
+        // so like serde_json the names are matched unconditionally.
+        let raw = match name {
+            NUMBER_TOKEN => Some(Raw::Number),
+            RAW_VALUE_TOKEN => Some(Raw::Value),
+            _ => None,
+        };
+        if raw.is_some() {
+            let mut compound = Compound::new(self, |_, _| Ok(()));
+            compound.raw = raw;
+            return Ok(compound);
+        }
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> serde_json::Result<Compound<'a, W, F>> {
+        self.begin_variant(variant)?;
+        let mut compound = self.serialize_map(Some(len))?;
+        compound.variant = true;
+        Ok(compound)
+    }
+}
+
+impl<W: io::Write, F: Formatter> PythonSerializer<W, F> {
+    fn begin_variant(&mut self, variant: &str) -> serde_json::Result<()> {
+        let w = &mut self.writer;
+        let f = &mut self.formatter;
+        f.begin_object(w)
+            .and_then(|_| f.begin_object_key(w, true))
+            .and_then(|_| format_escaped_str(w, f, variant))
+            .and_then(|_| f.end_object_key(w))
+            .and_then(|_| f.begin_object_value(w))
+            .map_err(io_error)
+    }
+
+    fn end_variant(&mut self) -> serde_json::Result<()> {
+        let w = &mut self.writer;
+        let f = &mut self.formatter;
+        f.end_object_value(w)
+            .and_then(|_| f.end_object(w))
+            .map_err(io_error)
+    }
+}
+
+/// The name of the struct `serde_json::Number` serializes as with the
+/// `arbitrary_precision` feature.
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// The name of the struct `serde_json::value::RawValue` serializes as with
+/// the `raw_value` feature.
+const RAW_VALUE_TOKEN: &str = "$serde_json::private::RawValue";
+
+/// The magic structs whose single field is written verbatim.
+#[derive(Copy, Clone)]
+enum Raw {
+    Number,
+    Value,
+}
+
+type EndFn<W, F> = fn(&mut F, &mut W) -> io::Result<()>;
+
+#[doc(hidden)]
+pub struct Compound<'a, W, F> {
+    ser: &'a mut PythonSerializer<W, F>,
+    first: bool,
+    variant: bool,
+    raw: Option<Raw>,
+    end: EndFn<W, F>,
+}
+
+impl<'a, W: io::Write, F: Formatter> Compound<'a, W, F> {
+    fn new(ser: &'a mut PythonSerializer<W, F>, end: EndFn<W, F>) -> Compound<'a, W, F> {
+        Compound {
+            ser,
+            first: true,
+            variant: false,
+            raw: None,
+            end,
+        }
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> serde_json::Result<()> {
+        let first = std::mem::replace(&mut self.first, false);
+        self.ser
+            .formatter
+            .begin_array_value(&mut self.ser.writer, first)
+            .map_err(io_error)?;
+        value.serialize(&mut *self.ser)?;
+        self.ser
+            .formatter
+            .end_array_value(&mut self.ser.writer)
+            .map_err(io_error)
+    }
+
+    fn key<T: ?Sized + Serialize>(&mut self, key: &T) -> serde_json::Result<()> {
+        let first = std::mem::replace(&mut self.first, false);
+        self.ser
+            .formatter
+            .begin_object_key(&mut self.ser.writer, first)
+            .map_err(io_error)?;
+        key.serialize(MapKeySerializer {
+            ser: &mut *self.ser,
+        })?;
+        self.ser
+            .formatter
+            .end_object_key(&mut self.ser.writer)
+            .map_err(io_error)
+    }
+
+    fn value<T: ?Sized + Serialize>(&mut self, value: &T) -> serde_json::Result<()> {
+        self.ser
+            .formatter
+            .begin_object_value(&mut self.ser.writer)
+            .map_err(io_error)?;
+        value.serialize(&mut *self.ser)?;
+        self.ser
+            .formatter
+            .end_object_value(&mut self.ser.writer)
+            .map_err(io_error)
+    }
+
+    fn number_str<T: ?Sized + Serialize>(&mut self, value: &T) -> serde_json::Result<()> {
+        match value.serialize(serde_json::value::Serializer)? {
+            Value::String(number) => self
+                .ser
+                .formatter
+                .write_number_str(&mut self.ser.writer, &number)
+                .map_err(io_error),
+            _ => Err(ser::Error::custom("invalid number")),
+        }
+    }
+
+    fn raw_fragment<T: ?Sized + Serialize>(&mut self, value: &T) -> serde_json::Result<()> {
+        match value.serialize(serde_json::value::Serializer)? {
+            Value::String(fragment) => self
+                .ser
+                .formatter
+                .write_raw_fragment(&mut self.ser.writer, &fragment)
+                .map_err(io_error),
+            _ => Err(ser::Error::custom("invalid raw value")),
+        }
+    }
+
+    fn finish(self) -> serde_json::Result<()> {
+        (self.end)(&mut self.ser.formatter, &mut self.ser.writer).map_err(io_error)?;
+        if self.variant {
+            self.ser.end_variant()?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_compound {
+    ($($trait:ident::$method:ident => $via:ident;)*) => {
+        $(
+            impl<'a, W: io::Write, F: Formatter> ser::$trait for Compound<'a, W, F> {
+                type Ok = ();
+                type Error = serde_json::Error;
+
+                fn $method<T: ?Sized + Serialize>(&mut self, value: &T) -> serde_json::Result<()> {
+                    self.$via(value)
+                }
+
+                fn end(self) -> serde_json::Result<()> {
+                    self.finish()
+                }
+            }
+        )*
+    };
+}
+
+impl_compound! {
+    SerializeSeq::serialize_element => element;
+    SerializeTuple::serialize_element => element;
+    SerializeTupleStruct::serialize_field => element;
+    SerializeTupleVariant::serialize_field => element;
+}
+
+impl<'a, W: io::Write, F: Formatter> ser::SerializeMap for Compound<'a, W, F> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> serde_json::Result<()> {
+        self.key(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> serde_json::Result<()> {
+        self.value(value)
+    }
+
+    fn end(self) -> serde_json::Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write, F: Formatter> ser::SerializeStruct for Compound<'a, W, F> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        match self.raw {
+            Some(Raw::Number) => return self.number_str(value),
+            Some(Raw::Value) => return self.raw_fragment(value),
+            None => {}
+        }
+        self.key(key)?;
+        self.value(value)
+    }
+
+    fn end(self) -> serde_json::Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a, W: io::Write, F: Formatter> ser::SerializeStructVariant for Compound<'a, W, F> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        self.key(key)?;
+        self.value(value)
+    }
+
+    fn end(self) -> serde_json::Result<()> {
+        self.finish()
+    }
+}
+
+/// Serializes map keys, which JSON requires to be strings.
+struct MapKeySerializer<'a, W, F> {
+    ser: &'a mut PythonSerializer<W, F>,
+}
+
+macro_rules! serialize_key_number {
+    ($($name:ident($ty:ty) => $write:ident;)*) => {
+        $(
+            fn $name(self, value: $ty) -> serde_json::Result<()> {
+                let w = &mut self.ser.writer;
+                let f = &mut self.ser.formatter;
+                f.begin_string(w)
+                    .and_then(|_| f.$write(w, value))
+                    .and_then(|_| f.end_string(w))
+                    .map_err(io_error)
+            }
+        )*
+    };
+}
+
+impl<'a, W: io::Write, F: Formatter> ser::Serializer for MapKeySerializer<'a, W, F> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = ser::Impossible<(), serde_json::Error>;
+    type SerializeTuple = ser::Impossible<(), serde_json::Error>;
+    type SerializeTupleStruct = ser::Impossible<(), serde_json::Error>;
+    type SerializeTupleVariant = ser::Impossible<(), serde_json::Error>;
+    type SerializeMap = ser::Impossible<(), serde_json::Error>;
+    type SerializeStruct = ser::Impossible<(), serde_json::Error>;
+    type SerializeStructVariant = ser::Impossible<(), serde_json::Error>;
+
+    serialize_key_number! {
+        serialize_bool(bool) => write_bool;
+        serialize_i8(i8) => write_i8;
+        serialize_i16(i16) => write_i16;
+        serialize_i32(i32) => write_i32;
+        serialize_i64(i64) => write_i64;
+        serialize_i128(i128) => write_i128;
+        serialize_u8(u8) => write_u8;
+        serialize_u16(u16) => write_u16;
+        serialize_u32(u32) => write_u32;
+        serialize_u64(u64) => write_u64;
+        serialize_u128(u128) => write_u128;
+        serialize_f32(f32) => write_f32;
+        serialize_f64(f64) => write_f64;
+    }
+
+    fn serialize_char(self, value: char) -> serde_json::Result<()> {
+        self.ser.serialize_char(value)
+    }
+
+    fn serialize_str(self, value: &str) -> serde_json::Result<()> {
+        self.ser.serialize_str(value)
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> serde_json::Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> serde_json::Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> serde_json::Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> serde_json::Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> serde_json::Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> serde_json::Result<()> {
+        self.ser.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> serde_json::Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> serde_json::Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> serde_json::Result<Self::SerializeSeq> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> serde_json::Result<Self::SerializeTuple> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> serde_json::Result<Self::SerializeTupleStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> serde_json::Result<Self::SerializeTupleVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> serde_json::Result<Self::SerializeMap> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> serde_json::Result<Self::SerializeStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> serde_json::Result<Self::SerializeStructVariant> {
+        Err(key_must_be_a_string())
+    }
+}
+
+/// Serialize the given value as Python flavoured JSON into the IO stream.
+///
+/// Unlike `serde_json` this writes non-finite floats as `NaN`, `Infinity`
+/// and `-Infinity` the way Python's `json` module does.
+pub fn to_writer_python<W, T>(writer: W, value: &T) -> serde_json::Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    value.serialize(&mut PythonSerializer::new(writer))
+}
+
+/// Serialize the given value as Python flavoured JSON into a byte vector.
+pub fn to_vec_python<T>(value: &T) -> serde_json::Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut rv = Vec::with_capacity(128);
+    to_writer_python(&mut rv, value)?;
+    Ok(rv)
+}
+
+/// Serialize the given value as Python flavoured JSON into a string.
+pub fn to_string_python<T>(value: &T) -> serde_json::Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let rv = to_vec_python(value)?;
+    // SAFETY: the serializer only ever writes valid UTF-8
+    Ok(unsafe { String::from_utf8_unchecked(rv) })
+}
+
+/// Writes newline-delimited Python flavoured JSON.
+///
+/// Every record is serialized compactly on a line of its own with
+/// non-finite floats written as Python tokens, which is what
+/// `json.loads` on every line of a file expects.
+///
+/// ```
+/// # use python_json_read_adapter::PythonNdjsonWriter;
+/// let mut writer = PythonNdjsonWriter::new(Vec::new());
+/// writer.write(&[1.0, f64::NAN]).unwrap();
+/// writer.write(&f64::NEG_INFINITY).unwrap();
+/// assert_eq!(writer.into_inner(), b"[1.0,NaN]\n-Infinity\n");
+/// ```
+#[derive(Debug)]
+pub struct PythonNdjsonWriter<W> {
+    writer: W,
+}
+
+impl<W: io::Write> PythonNdjsonWriter<W> {
+    /// Creates a writer that writes records to the given writer.
+    pub fn new(writer: W) -> PythonNdjsonWriter<W> {
+        PythonNdjsonWriter { writer }
+    }
+
+    /// Writes a single record followed by a newline.
+    ///
+    /// If serialization fails a partial line may have been written.
+    pub fn write<T>(&mut self, value: &T) -> serde_json::Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        to_writer_python(&mut self.writer, value)?;
+        self.writer.write_all(b"\n").map_err(io_error)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwraps the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[test]
+fn test_to_string_python() {
+    let values = vec![f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1.5];
+    assert_eq!(
+        to_string_python(&values).unwrap(),
+        "[NaN,Infinity,-Infinity,1.5]"
+    );
+    assert_eq!(to_string_python(&f32::NEG_INFINITY).unwrap(), "-Infinity");
+}
+
+#[test]
+fn test_to_vec_python_matches_serde_json() {
+    let mut map = std::collections::BTreeMap::new();
+    map.insert(1, ("a\"\n\u{1}", Some(2.5f32), None::<bool>, vec![true]));
+    map.insert(2, ("☃", None, Some(false), vec![]));
+    assert_eq!(
+        to_vec_python(&map).unwrap(),
+        serde_json::to_vec(&map).unwrap()
+    );
+}
+
+#[test]
+fn test_python_roundtrip() {
+    let mut json = to_vec_python(&[f64::INFINITY, f64::NAN]).unwrap();
+    let rv: Vec<f64> = crate::from_slice(&mut json).unwrap();
+    assert_eq!(rv, vec![0.0, 0.0]);
+}
+
+#[test]
+fn test_python_formatter_pretty() {
+    let mut ser = PythonSerializer::with_formatter(
+        Vec::new(),
+        PythonFormatter::new(serde_json::ser::PrettyFormatter::new()),
+    );
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("a", vec![f32::INFINITY]);
+    map.insert("b", vec![]);
+    map.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(ser.into_inner()).unwrap(),
+        "{\n  \"a\": [\n    Infinity\n  ],\n  \"b\": []\n}"
+    );
+}
+
+#[test]
+fn test_null_formatter_keys() {
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("x", f32::NAN);
+    let mut ser =
+        PythonSerializer::with_formatter(Vec::new(), NullFormatter::<CompactFormatter>::default());
+    map.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), b"{\"x\":null}");
+}
+
+#[test]
+fn test_python_ndjson_writer() {
+    let mut writer = PythonNdjsonWriter::new(Vec::new());
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("a\nb", vec![f64::INFINITY]);
+    writer.write(&map).unwrap();
+    writer.write("x").unwrap();
+    assert!(writer
+        .write(&std::collections::BTreeMap::from([(vec![1], 2)]))
+        .is_err());
+    writer.flush().unwrap();
+    let out = writer.into_inner();
+    let mut lines = out.split(|&b| b == b'\n');
+    assert_eq!(lines.next().unwrap(), br#"{"a\nb":[Infinity]}"#);
+    assert_eq!(lines.next().unwrap(), br#""x""#);
+}
+
+#[cfg(feature = "raw_value")]
+#[test]
+fn test_python_raw_value() {
+    use serde_json::value::RawValue;
+    use serde_self::ser::SerializeStruct;
+
+    struct Event {
+        payload: Box<RawValue>,
+        score: f64,
+    }
+
+    impl Serialize for Event {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct("Event", 2)?;
+            s.serialize_field("payload", &self.payload)?;
+            s.serialize_field("score", &self.score)?;
+            s.end()
+        }
+    }
+
+    let raw = RawValue::from_string("[1, 2]".into()).unwrap();
+    assert_eq!(to_string_python(&raw).unwrap(), "[1, 2]");
+    let event = Event {
+        payload: RawValue::from_string(r#"{"x": 1.50}"#.into()).unwrap(),
+        score: f64::NEG_INFINITY,
+    };
+    assert_eq!(
+        to_string_python(&event).unwrap(),
+        r#"{"payload":{"x": 1.50},"score":-Infinity}"#
+    );
+}
+
+#[test]
+fn test_python_magic_structs() {
+    use serde_self::ser::SerializeStruct;
+
+    // stands in for the types serde_json serializes with features that
+    // another crate may have enabled
+    struct Magic(&'static str, &'static str);
+
+    impl Serialize for Magic {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct(self.0, 1)?;
+            s.serialize_field(self.0, self.1)?;
+            s.end()
+        }
+    }
+
+    assert_eq!(
+        to_string_python(&Magic(RAW_VALUE_TOKEN, "[1, 2]")).unwrap(),
+        "[1, 2]"
+    );
+    assert_eq!(
+        to_string_python(&vec![Magic(NUMBER_TOKEN, "1.50")]).unwrap(),
+        "[1.50]"
+    );
+}
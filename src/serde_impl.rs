@@ -1,41 +1,647 @@
-use std::io;
+use std::io::{self, Read};
 
-use serde_json;
 use serde_self::de;
 
-use crate::{translate_slice, JsonCompatRead};
+use crate::error::{Error, ErrorKind};
+use crate::{translate_slice_with_options, JsonCompatRead, Options};
+
+/// The error for input that is empty or only contains whitespace.
+fn empty_document(len: usize) -> serde_json::Error {
+    serde_json::Error::io(Error::new(ErrorKind::EmptyDocument, len as u64).into())
+}
+
+/// Translates a whole document in place.
+///
+/// Like `from_reader` this fails on truncated tokens such as `Infin`.
+fn translate_checked(v: &mut [u8], options: &Options) -> serde_json::Result<()> {
+    translate_slice_with_options(v, options)
+        .map(|_| ())
+        .map_err(|err| serde_json::Error::io(err.into()))
+}
+
+fn check_blank(v: &[u8]) -> serde_json::Result<()> {
+    if is_blank(v) {
+        Err(empty_document(v.len()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads until the first byte that is not whitespace and parses the rest.
+///
+/// If there is no such byte the number of bytes read is returned as error.
+fn parse_non_blank<R, T, F>(mut rdr: R, parse: F) -> serde_json::Result<Result<T, usize>>
+where
+    R: io::Read,
+    F: FnOnce(io::Chain<io::Cursor<&[u8]>, R>) -> serde_json::Result<T>,
+{
+    let mut prefix = [0; 256];
+    let mut blank = 0;
+    loop {
+        let read = match rdr.read(&mut prefix) {
+            Ok(read) => read,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(serde_json::Error::io(err)),
+        };
+        if read == 0 {
+            return Ok(Err(blank));
+        }
+        if !is_blank(&prefix[..read]) {
+            return parse(io::Cursor::new(&prefix[..read]).chain(rdr)).map(Ok);
+        }
+        blank += read;
+    }
+}
 
 /// Deserialize an instance of type `T` from an IO stream of JSON.
+///
+/// If the stream is empty or only contains whitespace an IO error is
+/// returned that wraps an `Error` of the kind `ErrorKind::EmptyDocument`.
 pub fn from_reader<R, T>(rdr: R) -> serde_json::Result<T>
 where
     R: io::Read,
     T: de::DeserializeOwned,
 {
-    serde_json::from_reader(JsonCompatRead::wrap(rdr))
+    parse_non_blank(rdr, |rdr| {
+        serde_json::from_reader(JsonCompatRead::wrap(rdr).check_eof(true))
+    })?
+    .map_err(empty_document)
 }
 
 /// Deserialize an instance of type `T` from bytes of JSON text.
 ///
 /// Note that this needs to take a mutable reference to the bytes because
-/// it performs some modification in place before deserializing.
+/// it performs some modification in place before deserializing.  Like
+/// `from_reader` this fails with `ErrorKind::EmptyDocument` if the bytes are
+/// empty or only contain whitespace.
 pub fn from_slice<'a, T>(v: &'a mut [u8]) -> serde_json::Result<T>
 where
     T: de::Deserialize<'a>,
 {
-    translate_slice(v);
+    check_blank(v)?;
+    translate_checked(v, &Options::new())?;
+    serde_json::from_slice(v)
+}
+
+/// Deserialize an instance of type `T` from an IO stream of JSON.
+///
+/// This works like `from_reader` but translates with the given options.  If
+/// the options reject the document an IO error is returned that wraps the
+/// translation `Error`.
+pub fn from_reader_with_options<R, T>(rdr: R, options: &Options) -> serde_json::Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    parse_non_blank(rdr, |rdr| {
+        serde_json::from_reader(JsonCompatRead::with_options(rdr, options.clone()).check_eof(true))
+    })?
+    .map_err(empty_document)
+}
+
+/// Deserialize an instance of type `T` from an IO stream of JSON.
+///
+/// This reads the whole stream into `buf`, translates it in place and then
+/// parses it as a slice, which is a lot faster than `from_reader` for
+/// documents that fit into memory.  `buf` is cleared first so it can be
+/// reused for many documents, and the result can borrow from it.
+///
+/// ```
+/// # use python_json_read_adapter::from_reader_buffered;
+/// let mut buf = Vec::new();
+/// let values: Vec<f64> = from_reader_buffered(&b"[NaN]"[..], &mut buf).unwrap();
+/// assert_eq!(values, vec![0.0]);
+/// let values: Vec<f64> = from_reader_buffered(&b"[1.5, -Infinity]"[..], &mut buf).unwrap();
+/// assert_eq!(values, vec![1.5, -0.0]);
+/// assert!(values[1].is_sign_negative());
+/// ```
+pub fn from_reader_buffered<'a, R, T>(mut rdr: R, buf: &'a mut Vec<u8>) -> serde_json::Result<T>
+where
+    R: io::Read,
+    T: de::Deserialize<'a>,
+{
+    buf.clear();
+    rdr.read_to_end(buf).map_err(serde_json::Error::io)?;
+    check_blank(buf)?;
+    translate_checked(buf, &Options::new())?;
+    serde_json::from_slice(buf)
+}
+
+/// Deserialize an instance of type `T` from bytes of JSON text.
+///
+/// This works like `from_slice` but translates with the given options.  If
+/// the options reject the document an IO error is returned that wraps the
+/// translation `Error`.
+pub fn from_slice_with_options<'a, T>(v: &'a mut [u8], options: &Options) -> serde_json::Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    check_blank(v)?;
+    translate_checked(v, options)?;
     serde_json::from_slice(v)
 }
 
+/// Deserialize an instance of type `T` from a string of JSON text.
+///
+/// This works like `from_slice` but for strings.  The translation only ever
+/// replaces ASCII bytes with other ASCII bytes so the string stays valid
+/// UTF-8.
+pub fn from_str<'a, T>(v: &'a mut str) -> serde_json::Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    check_blank(v.as_bytes())?;
+    // SAFETY: the translation only replaces ASCII bytes with ASCII bytes
+    translate_checked(unsafe { v.as_bytes_mut() }, &Options::new())?;
+    serde_json::from_str(v)
+}
+
+fn is_blank(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .all(|&b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+}
+
+/// Deserialize an instance of type `T` from an IO stream of JSON.
+///
+/// This works like `from_reader` but returns `None` if the stream is empty
+/// or only contains whitespace.
+pub fn from_reader_opt<R, T>(rdr: R) -> serde_json::Result<Option<T>>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let rv = parse_non_blank(rdr, |rdr| {
+        serde_json::from_reader(JsonCompatRead::wrap(rdr).check_eof(true))
+    })?;
+    Ok(rv.ok())
+}
+
+/// Deserialize an instance of type `T` from bytes of JSON text.
+///
+/// This works like `from_slice` but returns `None` if the bytes are empty or
+/// only contain whitespace.
+pub fn from_slice_opt<'a, T>(v: &'a mut [u8]) -> serde_json::Result<Option<T>>
+where
+    T: de::Deserialize<'a>,
+{
+    if is_blank(v) {
+        Ok(None)
+    } else {
+        from_slice(v).map(Some)
+    }
+}
+
+/// Deserialize an instance of type `T` from an IO stream of JSON.
+///
+/// This works like `from_reader` but returns `T::default()` if the stream is
+/// empty or only contains whitespace.
+pub fn from_reader_or_default<R, T>(rdr: R) -> serde_json::Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned + Default,
+{
+    from_reader_opt(rdr).map(Option::unwrap_or_default)
+}
+
+/// Deserialize an instance of type `T` from bytes of JSON text.
+///
+/// This works like `from_slice` but returns `T::default()` if the bytes are
+/// empty or only contain whitespace.
+pub fn from_slice_or_default<'a, T>(v: &'a mut [u8]) -> serde_json::Result<T>
+where
+    T: de::Deserialize<'a> + Default,
+{
+    from_slice_opt(v).map(Option::unwrap_or_default)
+}
+
+fn deserialize_seed<'de, R, S>(
+    mut de: serde_json::Deserializer<R>,
+    seed: S,
+) -> serde_json::Result<S::Value>
+where
+    R: serde_json::de::Read<'de>,
+    S: de::DeserializeSeed<'de>,
+{
+    let rv = seed.deserialize(&mut de)?;
+    de.end()?;
+    Ok(rv)
+}
+
+/// Deserialize a value from an IO stream of JSON with a seed.
+///
+/// This works like `from_reader` but for stateful deserialization through
+/// `serde::de::DeserializeSeed`.
+pub fn from_reader_seed<'de, R, S>(rdr: R, seed: S) -> serde_json::Result<S::Value>
+where
+    R: io::Read,
+    S: de::DeserializeSeed<'de>,
+{
+    parse_non_blank(rdr, |rdr| {
+        let rdr = JsonCompatRead::wrap(rdr).check_eof(true);
+        deserialize_seed(serde_json::Deserializer::from_reader(rdr), seed)
+    })?
+    .map_err(empty_document)
+}
+
+/// Deserialize a value from bytes of JSON text with a seed.
+///
+/// This works like `from_slice` but for stateful deserialization through
+/// `serde::de::DeserializeSeed`.
+pub fn from_slice_seed<'a, S>(v: &'a mut [u8], seed: S) -> serde_json::Result<S::Value>
+where
+    S: de::DeserializeSeed<'a>,
+{
+    check_blank(v)?;
+    translate_checked(v, &Options::new())?;
+    deserialize_seed(serde_json::Deserializer::from_slice(v), seed)
+}
+
+#[cfg(feature = "serde_path_to_error")]
+fn deserialize_with_path<'de, R, T>(
+    mut de: serde_json::Deserializer<R>,
+) -> Result<T, serde_path_to_error::Error<serde_json::Error>>
+where
+    R: serde_json::de::Read<'de>,
+    T: de::Deserialize<'de>,
+{
+    let rv = serde_path_to_error::deserialize(&mut de)?;
+    de.end().map_err(|err| {
+        serde_path_to_error::Error::new(serde_path_to_error::Track::new().path(), err)
+    })?;
+    Ok(rv)
+}
+
+/// Deserialize an instance of type `T` from an IO stream of JSON and report
+/// the path of the value that failed.
+///
+/// This works like `from_reader` but wraps errors in a
+/// `serde_path_to_error::Error` which carries the path to the value that
+/// could not be deserialized, for instance `data[17].metrics.p99`.
+#[cfg(feature = "serde_path_to_error")]
+pub fn from_reader_with_path<R, T>(
+    rdr: R,
+) -> Result<T, serde_path_to_error::Error<serde_json::Error>>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let rdr = JsonCompatRead::wrap(rdr).check_eof(true);
+    deserialize_with_path(serde_json::Deserializer::from_reader(rdr))
+}
+
+/// Deserialize an instance of type `T` from bytes of JSON text and report
+/// the path of the value that failed.
+///
+/// This works like `from_slice` but wraps errors in a
+/// `serde_path_to_error::Error`.
+#[cfg(feature = "serde_path_to_error")]
+pub fn from_slice_with_path<'a, T>(
+    v: &'a mut [u8],
+) -> Result<T, serde_path_to_error::Error<serde_json::Error>>
+where
+    T: de::Deserialize<'a>,
+{
+    translate_checked(v, &Options::new()).map_err(|err| {
+        serde_path_to_error::Error::new(serde_path_to_error::Track::new().path(), err)
+    })?;
+    deserialize_with_path(serde_json::Deserializer::from_slice(v))
+}
+
+/// Returns an iterator over a stream of JSON documents.
+///
+/// The documents may be separated by whitespace or nothing at all, as
+/// supported by `serde_json::StreamDeserializer`, and are translated on the
+/// fly.
+///
+/// ```
+/// # use python_json_read_adapter::iter_values;
+/// let stream = &b"[NaN] [1]\n{\"x\": Infinity}"[..];
+/// let values = iter_values::<_, serde_json::Value>(stream)
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(values.len(), 3);
+/// ```
+pub fn iter_values<R, T>(
+    rdr: R,
+) -> serde_json::StreamDeserializer<'static, serde_json::de::IoRead<JsonCompatRead<R>>, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    serde_json::Deserializer::from_reader(JsonCompatRead::wrap(rdr).check_eof(true)).into_iter()
+}
+
+/// An iterator over a stream of JSON documents and the offsets where they end.
+///
+/// Created by `iter_values_with_offsets` and `iter_slice_values_with_offsets`.
+pub struct ValuesWithOffsets<'de, R, T> {
+    inner: serde_json::StreamDeserializer<'de, R, T>,
+    error: Option<serde_json::Error>,
+}
+
+impl<'de, R, T> Iterator for ValuesWithOffsets<'de, R, T>
+where
+    R: serde_json::de::Read<'de>,
+    T: de::Deserialize<'de>,
+{
+    type Item = serde_json::Result<(T, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        let rv = self.inner.next()?;
+        Some(rv.map(|value| (value, self.inner.byte_offset())))
+    }
+}
+
+/// Returns an iterator over a stream of JSON documents with their end offsets.
+///
+/// This works like `iter_values` but also yields the offset after the last
+/// byte of every document.  As the translation does not move any bytes the
+/// offsets point into the original stream.  This is useful for streams
+/// produced by calling `json.dump` in a loop.
+///
+/// ```
+/// # use python_json_read_adapter::iter_values_with_offsets;
+/// let stream = &b"[NaN]{\"x\": 1} 42"[..];
+/// let offsets = iter_values_with_offsets::<_, serde_json::Value>(stream)
+///     .map(|rv| rv.map(|(_, offset)| offset))
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(offsets, vec![5, 13, 16]);
+/// ```
+pub fn iter_values_with_offsets<R, T>(
+    rdr: R,
+) -> ValuesWithOffsets<'static, serde_json::de::IoRead<JsonCompatRead<R>>, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    ValuesWithOffsets {
+        inner: iter_values(rdr),
+        error: None,
+    }
+}
+
+/// Returns an iterator over the JSON documents in a slice with their end
+/// offsets.
+///
+/// The whole slice is translated in place up front, so the documents can
+/// borrow from it.  If the translation fails, for instance because the slice
+/// ends in the middle of a token, the iterator only yields that error.
+pub fn iter_slice_values_with_offsets<'a, T>(
+    v: &'a mut [u8],
+) -> ValuesWithOffsets<'a, serde_json::de::SliceRead<'a>, T>
+where
+    T: de::Deserialize<'a>,
+{
+    match translate_checked(v, &Options::new()) {
+        Ok(()) => ValuesWithOffsets {
+            inner: serde_json::Deserializer::from_slice(v).into_iter(),
+            error: None,
+        },
+        Err(err) => ValuesWithOffsets {
+            inner: serde_json::Deserializer::from_slice(&[]).into_iter(),
+            error: Some(err),
+        },
+    }
+}
+
 #[test]
 fn test_deserialize() {
     let mut json = br#"[Infinity, -Infinity, NaN]"#.to_vec();
     let rv: serde_json::Value = from_slice(&mut json[..]).unwrap();
+    assert_eq!(rv, serde_json::json!([0.0, -0.0, 0.0]));
+}
+
+#[test]
+fn test_deserialize_buffered() {
+    let mut buf = b"leftovers".to_vec();
+    let rv: Vec<&str> = from_reader_buffered(&br#"["NaN", "a"]"#[..], &mut buf).unwrap();
+    assert_eq!(rv, vec!["NaN", "a"]);
+    let rv: serde_json::Value = from_reader_buffered(&b"{\"x\": NaN}"[..], &mut buf).unwrap();
+    assert_eq!(rv, serde_json::json!({"x": 0.0}));
+    assert!(from_reader_buffered::<_, f64>(&b"Infin"[..], &mut buf).is_err());
+}
+
+#[test]
+fn test_deserialize_empty() {
+    let rv: Option<Vec<f64>> = from_slice_opt(&mut b" \n\t ".to_vec()).unwrap();
+    assert_eq!(rv, None);
+    let rv: Option<Vec<f64>> = from_slice_opt(&mut b" [NaN] ".to_vec()).unwrap();
+    assert_eq!(rv, Some(vec![0.0]));
+    let rv: Vec<f64> = from_slice_or_default(&mut Vec::new()).unwrap();
+    assert_eq!(rv, Vec::<f64>::new());
+
+    let rv: Option<Vec<f64>> = from_reader_opt(&b""[..]).unwrap();
+    assert_eq!(rv, None);
+    let mut json = vec![b' '; 1000];
+    json.extend_from_slice(b"[Infinity]");
+    let rv: Option<Vec<f64>> = from_reader_opt(&json[..]).unwrap();
+    assert_eq!(rv, Some(vec![0.0]));
+    let rv: Vec<f64> = from_reader_or_default(&b"\r\n"[..]).unwrap();
+    assert_eq!(rv, Vec::<f64>::new());
+    assert!(from_reader_opt::<_, Vec<f64>>(&b"  [1,"[..]).is_err());
+}
+
+#[test]
+fn test_deserialize_empty_error() {
+    let kind = |err: serde_json::Error| {
+        assert!(err.is_io());
+        let err = io::Error::from(err);
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = err.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+        (err.kind(), err.offset())
+    };
+    let empty = Some(ErrorKind::EmptyDocument);
+    assert_eq!(
+        kind(from_slice::<f64>(&mut b" \n".to_vec()).unwrap_err()),
+        (ErrorKind::EmptyDocument, Some(2))
+    );
+    let mut blank = vec![b' '; 1000];
+    assert_eq!(
+        kind(from_reader::<_, f64>(&blank[..]).unwrap_err()),
+        (ErrorKind::EmptyDocument, Some(1000))
+    );
+    assert_eq!(
+        Some(kind(from_str::<f64>(&mut String::new()).unwrap_err()).0),
+        empty
+    );
+    let options = Options::new();
+    assert_eq!(
+        Some(kind(from_slice_with_options::<f64>(&mut blank, &options).unwrap_err()).0),
+        empty
+    );
+    assert_eq!(
+        Some(kind(from_reader_with_options::<_, f64>(&b"\t"[..], &options).unwrap_err()).0),
+        empty
+    );
+    let mut buf = Vec::new();
+    assert_eq!(
+        Some(kind(from_reader_buffered::<_, f64>(&b""[..], &mut buf).unwrap_err()).0),
+        empty
+    );
+    assert_eq!(from_reader::<_, f64>(&b"  1.5 "[..]).unwrap(), 1.5);
+}
+
+#[test]
+fn test_deserialize_truncated() {
+    assert!(from_reader::<_, f64>(&b"Infin"[..]).is_err());
+    assert_eq!(from_reader::<_, f64>(&b"Infinity"[..]).unwrap(), 0.0);
+    assert!(from_slice::<f64>(&mut b"Infin".to_vec()).is_err());
+    assert!(from_slice::<Vec<f64>>(&mut b"[NaN, -Infin".to_vec()).is_err());
+    assert!(from_str::<f64>(&mut "Na".to_string()).is_err());
+    let options = Options::new().case_insensitive(true);
+    assert!(from_slice_with_options::<f64>(&mut b"-infin".to_vec(), &options).is_err());
+    let mut json = b"[1] Infin".to_vec();
+    let mut iter = iter_slice_values_with_offsets::<serde_json::Value>(&mut json);
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_deserialize_str() {
+    let mut json = r#"{"x": NaN, "y": "Infinity ☃", "z": -Infinity}"#.to_string();
+    let rv: serde_json::Value = from_str(&mut json).unwrap();
+    assert_eq!(
+        rv,
+        serde_json::json!({"x": 0.0, "y": "Infinity ☃", "z": -0.0})
+    );
+    let mut json = r#"["borrowed"]"#.to_string();
+    let rv: Vec<&str> = from_str(&mut json).unwrap();
+    assert_eq!(rv, vec!["borrowed"]);
+}
+
+#[test]
+fn test_deserialize_with_options() {
+    let options = Options::new().max_replacements(1);
+    let rv: Vec<f64> = from_slice_with_options(&mut b"[NaN, 1]".to_vec(), &options).unwrap();
+    assert_eq!(rv, vec![0.0, 1.0]);
+    let err =
+        from_slice_with_options::<Vec<f64>>(&mut b"[NaN, NaN]".to_vec(), &options).unwrap_err();
+    assert!(err.is_io());
+    let rv: Vec<f64> = from_reader_with_options(&b"[NaN, 1]"[..], &options).unwrap();
+    assert_eq!(rv, vec![0.0, 1.0]);
+    let err = from_reader_with_options::<_, Vec<f64>>(&b"[NaN, NaN]"[..], &options).unwrap_err();
+    assert!(err.is_io());
+
+    let json = br#"["\ud800", "\ud83d\ude00", "\udc00"]"#;
+    let options = Options::new().repair_surrogates(true);
+    let rv: Vec<String> = from_reader_with_options(&json[..], &options).unwrap();
+    assert_eq!(rv, vec!["\u{fffd}", "\u{1f600}", "\u{fffd}"]);
+    let rv: Vec<String> = from_slice_with_options(&mut json.to_vec(), &options).unwrap();
+    assert_eq!(rv, vec!["\u{fffd}", "\u{1f600}", "\u{fffd}"]);
+
+    let options = Options::new().case_insensitive(true);
+    let rv: Vec<Option<f64>> = from_reader_with_options(&b"[nan,null,-inf]"[..], &options).unwrap();
+    assert_eq!(rv, vec![Some(0.0), None, Some(-0.0)]);
+}
+
+#[test]
+fn test_iter_values() {
+    let stream = crate::test_support::FragmentingReader::new(
+        &b"[NaN, 1] -Infinity\n\n{\"a\": \"NaN\"}[Infin"[..],
+        3,
+    );
+    let mut iter = iter_values::<_, serde_json::Value>(stream);
+    assert_eq!(iter.next().unwrap().unwrap(), serde_json::json!([0.0, 1]));
+    assert_eq!(iter.next().unwrap().unwrap(), serde_json::json!(-0.0));
+    assert_eq!(
+        iter.next().unwrap().unwrap(),
+        serde_json::json!({"a": "NaN"})
+    );
+    assert!(iter.next().unwrap().is_err());
+}
+
+#[test]
+fn test_deserialize_seed() {
+    struct Interning<'a>(&'a mut Vec<String>);
+
+    impl<'de, 'a> de::DeserializeSeed<'de> for Interning<'a> {
+        type Value = Vec<usize>;
+
+        fn deserialize<D: de::Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Vec<usize>, D::Error> {
+            let items: Vec<String> = de::Deserialize::deserialize(deserializer)?;
+            Ok(items
+                .into_iter()
+                .map(|item| match self.0.iter().position(|x| *x == item) {
+                    Some(idx) => idx,
+                    None => {
+                        self.0.push(item);
+                        self.0.len() - 1
+                    }
+                })
+                .collect())
+        }
+    }
+
+    let mut strings = Vec::new();
+    let rv = from_slice_seed(&mut br#"["a", "b", "a"]"#.to_vec(), Interning(&mut strings)).unwrap();
+    assert_eq!(rv, vec![0, 1, 0]);
+    let rv = from_reader_seed(&br#"["b", "NaN"]"#[..], Interning(&mut strings)).unwrap();
+    assert_eq!(rv, vec![1, 2]);
+    assert_eq!(strings, vec!["a", "b", "NaN"]);
+    assert!(from_reader_seed(&b"[NaN]"[..], Interning(&mut strings)).is_err());
+    assert!(from_slice_seed(&mut b"[] []".to_vec(), Interning(&mut strings)).is_err());
+}
+
+#[cfg(feature = "serde_path_to_error")]
+#[test]
+fn test_deserialize_with_path() {
+    use std::collections::BTreeMap;
+    type Doc = BTreeMap<String, Vec<BTreeMap<String, f64>>>;
+    let mut json = br#"{"data": [{"p99": NaN}, {"p99": "slow"}]}"#.to_vec();
+    let err = from_slice_with_path::<Doc>(&mut json).unwrap_err();
+    assert_eq!(err.path().to_string(), "data[1].p99");
+
+    let err =
+        from_reader_with_path::<_, Doc>(&br#"{"data": [{"p99": Infinity}, 1]}"#[..]).unwrap_err();
+    assert_eq!(err.path().to_string(), "data[1]");
+    assert!(from_reader_with_path::<_, Doc>(&br#"{"data": []} x"#[..]).is_err());
+}
+
+#[cfg(feature = "arbitrary_precision")]
+#[test]
+fn test_arbitrary_precision() {
+    let mut json = br#"[123456789012345678901234567890, 0.10000000000000000001, NaN]"#.to_vec();
+    let rv: serde_json::Value = from_slice(&mut json).unwrap();
+    assert_eq!(
+        rv.to_string(),
+        "[123456789012345678901234567890,0.10000000000000000001,0.0]"
+    );
+    assert_eq!(
+        crate::to_string_python(&rv).unwrap(),
+        "[123456789012345678901234567890,0.10000000000000000001,0.0]"
+    );
+}
+
+#[test]
+fn test_iter_values_with_offsets() {
+    let json = b"{\"a\": NaN}\n[Infinity] \"s\"\n";
+    let rv = iter_values_with_offsets::<_, serde_json::Value>(&json[..])
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
     assert_eq!(
         rv,
-        serde_json::Value::Array(vec![
-            serde_json::Value::Number(serde_json::Number::from_f64(0.0).unwrap()),
-            serde_json::Value::Number(serde_json::Number::from_f64(0.0).unwrap()),
-            serde_json::Value::Number(serde_json::Number::from_f64(0.0).unwrap()),
-        ])
+        vec![
+            (serde_json::json!({"a": 0.0}), 10),
+            (serde_json::json!([0.0]), 21),
+            (serde_json::json!("s"), 25),
+        ]
     );
+    let mut json = json.to_vec();
+    let rv = iter_slice_values_with_offsets::<serde_json::Value>(&mut json)
+        .map(|rv| rv.unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(rv, vec![10, 21, 25]);
+
+    let mut json = b"[\"a\"] [1,".to_vec();
+    let mut iter = iter_slice_values_with_offsets::<Vec<&str>>(&mut json);
+    assert_eq!(iter.next().unwrap().unwrap(), (vec!["a"], 5));
+    assert!(iter.next().unwrap().is_err());
 }
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use serde_json::Value;
+use serde_self::de;
+
+use crate::canonical::parse_translated;
+use crate::de::from_slice_lossless;
+use crate::diff::push_pointer_segment;
+use crate::value::PyValue;
+
+/// A difference between the translating and the lenient parse.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Divergence {
+    /// The JSON pointer of the value that differs.
+    pub path: String,
+    /// A description of the difference.
+    pub message: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match *value {
+        Value::Array(_) => "array".into(),
+        Value::Object(_) => "object".into(),
+        ref other => other.to_string(),
+    }
+}
+
+fn describe_lenient(value: &PyValue) -> String {
+    match *value {
+        PyValue::Array(_) => "array".into(),
+        PyValue::Object(_) => "object".into(),
+        ref other => other.to_string(),
+    }
+}
+
+fn compare(lenient: &PyValue, translated: &Value, path: &str, rv: &mut Vec<Divergence>) {
+    let same = match (lenient, translated) {
+        (PyValue::Null, Value::Null) => true,
+        (PyValue::Bool(a), Value::Bool(b)) => a == b,
+        (PyValue::Number(a), Value::Number(b)) => a == b,
+        (PyValue::NonFinite(a), Value::Number(b)) => b
+            .as_f64()
+            .is_some_and(|b| b == 0.0 && b.is_sign_negative() == (*a < 0.0)),
+        (PyValue::String(a), Value::String(b)) => a == b,
+        (PyValue::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (idx, (a, b)) in a.iter().zip(b).enumerate() {
+                compare(a, b, &push_pointer_segment(path, &idx.to_string()), rv);
+            }
+            true
+        }
+        (PyValue::Object(a), Value::Object(b)) if a.len() == b.len() => {
+            for (key, a) in a {
+                let path = push_pointer_segment(path, key);
+                match b.get(key) {
+                    Some(b) => compare(a, b, &path, rv),
+                    None => rv.push(Divergence {
+                        path,
+                        message: "missing in translated document".into(),
+                    }),
+                }
+            }
+            true
+        }
+        _ => false,
+    };
+    if !same {
+        rv.push(Divergence {
+            path: path.to_string(),
+            message: format!(
+                "translated {}, lenient {}",
+                describe(translated),
+                describe_lenient(lenient)
+            ),
+        });
+    }
+}
+
+/// Compares the translating parse of a document with a lenient parse.
+///
+/// The document is parsed twice: once by translating it and handing it to
+/// `serde_json`, and once by the lenient parser behind `from_slice_lossless`
+/// which understands `NaN` and `Infinity` natively.  Values that only differ
+/// because a non-finite float became `0.0` are considered equal, everything
+/// else is reported.  This is useful to gain confidence when migrating from
+/// other fixups to this crate.
+///
+/// ```
+/// # use python_json_read_adapter::shadow_compare;
+/// assert!(shadow_compare(br#"{"x": [NaN, -Infinity, 1]}"#).is_empty());
+/// ```
+pub fn shadow_compare(bytes: &[u8]) -> Vec<Divergence> {
+    let mut rv = Vec::new();
+    match (
+        parse_translated(bytes),
+        from_slice_lossless::<PyValue>(bytes),
+    ) {
+        (Ok(translated), Ok(lenient)) => compare(&lenient, &translated, "", &mut rv),
+        (Ok(_), Err(err)) => rv.push(Divergence {
+            path: String::new(),
+            message: format!("only the lenient parse failed: {}", err),
+        }),
+        (Err(err), Ok(_)) => rv.push(Divergence {
+            path: String::new(),
+            message: format!("only the translated parse failed: {}", err),
+        }),
+        (Err(_), Err(_)) => {}
+    }
+    rv
+}
+
+/// Deserialize an instance of type `T` from bytes of JSON text in shadow
+/// mode.
+///
+/// This works like `from_slice` but first runs `shadow_compare` on the
+/// untranslated bytes and reports every divergence to the callback.  The
+/// result is always the one of the translating parse.
+pub fn from_slice_shadowed<'a, T, F>(v: &'a mut [u8], mut on_divergence: F) -> serde_json::Result<T>
+where
+    T: de::Deserialize<'a>,
+    F: FnMut(&Divergence),
+{
+    for divergence in shadow_compare(v) {
+        on_divergence(&divergence);
+    }
+    crate::from_slice(v)
+}
+
+#[test]
+fn test_shadow_compare() {
+    assert!(shadow_compare(br#"[1, 2.5, "NaN", {"a": Infinity}, null, true]"#).is_empty());
+    // documents that neither parser accepts do not diverge
+    assert!(shadow_compare(b"[Nax]").is_empty());
+    // the translation turns `-Infin` into `-0.0` but it is not a valid token
+    let rv = shadow_compare(b"[1, -Infin]");
+    assert_eq!(rv.len(), 1);
+    assert!(rv[0].message.starts_with("only the lenient parse failed"));
+}
+
+#[test]
+fn test_shadow_compare_values() {
+    let lenient: PyValue = from_slice_lossless(br#"{"a": [1, NaN], "b": "x"}"#).unwrap();
+    let translated = serde_json::json!({"a": [2, -0.0], "b": "x"});
+    let mut rv = Vec::new();
+    compare(&lenient, &translated, "", &mut rv);
+    assert_eq!(
+        rv.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+        vec![
+            "/a/0: translated 2, lenient 1",
+            "/a/1: translated -0.0, lenient NaN"
+        ]
+    );
+}
+
+#[test]
+fn test_from_slice_shadowed() {
+    let mut divergences = Vec::new();
+    let mut json = b"[NaN, 1]".to_vec();
+    let rv: Vec<f64> = from_slice_shadowed(&mut json, |d| divergences.push(d.clone())).unwrap();
+    assert_eq!(rv, vec![0.0, 1.0]);
+    assert!(divergences.is_empty());
+}
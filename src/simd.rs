@@ -0,0 +1,36 @@
+use serde_self::de;
+
+use crate::translate_slice;
+
+/// Deserialize an instance of type `T` from bytes of JSON text with
+/// `simd-json`.
+///
+/// This works like `from_slice` but parses with `simd-json` which is a lot
+/// faster on large documents.  Note that `simd-json` uses the slice as
+/// scratch space while parsing, so unlike with `from_slice` the slice does
+/// not hold the translated document afterwards.
+///
+/// ```
+/// # use python_json_read_adapter::from_slice_simd;
+/// let mut json = b"[1.5, NaN, -Infinity]".to_vec();
+/// let rv: Vec<f64> = from_slice_simd(&mut json).unwrap();
+/// assert_eq!(rv, vec![1.5, 0.0, -0.0]);
+/// ```
+pub fn from_slice_simd<'a, T>(v: &'a mut [u8]) -> simd_json::Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    translate_slice(v);
+    simd_json::serde::from_slice(v)
+}
+
+#[test]
+fn test_from_slice_simd() {
+    let json = br#"{"a": [NaN, Infinity, -Infinity], "b": "NaN \"Infinity\"", "c": null}"#;
+    let expected: serde_json::Value = crate::from_slice(&mut json.to_vec()).unwrap();
+    let rv: serde_json::Value = from_slice_simd(&mut json.to_vec()).unwrap();
+    assert_eq!(rv, expected);
+    let rv: Vec<f64> = from_slice_simd(&mut b"[-Infinity]".to_vec()).unwrap();
+    assert!(rv[0].is_sign_negative());
+    assert!(from_slice_simd::<Vec<f64>>(&mut b"[Nax]".to_vec()).is_err());
+}
@@ -0,0 +1,70 @@
+//! Byte classification tables.
+//!
+//! The scalar and SWAR skip backends and the locale fixups consult these
+//! tables instead of matching on byte literals, and a test checks the needle
+//! lists of the SIMD backend against them.  The `memchr` backend, the
+//! translator state machine and the other scanners match on byte literals.
+
+/// `{`, `}`, `[`, `]`, `:` and `,`.
+pub const STRUCTURAL: u8 = 1 << 0;
+/// ASCII digits `0` to `9`.
+pub const DIGIT: u8 = 1 << 1;
+/// Leading bytes of the JSON literals `true`, `false` and `null`.
+pub const LITERAL_START: u8 = 1 << 2;
+/// Leading bytes of the non-finite tokens `NaN` and `Infinity`.
+pub const NON_FINITE_START: u8 = 1 << 3;
+/// The string delimiter `"`.
+pub const QUOTE: u8 = 1 << 4;
+/// The escape character `\`.
+pub const ESCAPE: u8 = 1 << 5;
+/// JSON whitespace.
+pub const WHITESPACE: u8 = 1 << 6;
+/// The sign characters `-` and `+`.
+pub const SIGN: u8 = 1 << 7;
+
+/// Bytes that require the state machine while outside of a string.
+pub const INITIAL_INTERESTING: u8 = NON_FINITE_START | QUOTE;
+/// Bytes that require the state machine while inside of a string.
+pub const QUOTED_INTERESTING: u8 = QUOTE | ESCAPE;
+
+/// Maps every byte to its class bits.
+pub static CLASSES: [u8; 256] = build_classes();
+
+const fn build_classes() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut b = 0;
+    while b < 256 {
+        let c = b as u8;
+        table[b] = match c {
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => STRUCTURAL,
+            b'0'..=b'9' => DIGIT,
+            b't' | b'f' | b'n' => LITERAL_START,
+            b'N' | b'I' => NON_FINITE_START,
+            b'"' => QUOTE,
+            b'\\' => ESCAPE,
+            b' ' | b'\t' | b'\n' | b'\r' => WHITESPACE,
+            b'-' | b'+' => SIGN,
+            _ => 0,
+        };
+        b += 1;
+    }
+    table
+}
+
+/// Checks if a byte belongs to any of the given classes.
+#[inline(always)]
+pub fn is(b: u8, classes: u8) -> bool {
+    CLASSES[b as usize] & classes != 0
+}
+
+#[test]
+fn test_classes() {
+    assert!(is(b'N', INITIAL_INTERESTING));
+    assert!(is(b'"', INITIAL_INTERESTING));
+    assert!(!is(b'n', INITIAL_INTERESTING));
+    assert!(is(b'\\', QUOTED_INTERESTING));
+    assert!(!is(b'N', QUOTED_INTERESTING));
+    assert!(is(b',', STRUCTURAL));
+    assert!(is(b'7', DIGIT));
+    assert!(!is(0xff, !0));
+}
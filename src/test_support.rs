@@ -0,0 +1,146 @@
+//! Utilities for testing integrations of the read adapter.
+//!
+//! Bugs in readers typically only show up when tokens are split across
+//! reads.  The helpers in this module make it easy to exercise every possible
+//! chunking of a document.
+use std::io::{self, Read};
+
+use crate::read::JsonCompatRead;
+use crate::translator::{translate_slice, Translator};
+
+/// A reader that returns data in small fragments.
+///
+/// The size of the fragments cycles through the configured sizes.  This is
+/// useful to test that readers correctly carry state across reads.
+#[derive(Debug)]
+pub struct FragmentingReader<R> {
+    reader: R,
+    sizes: Vec<usize>,
+    index: usize,
+}
+
+impl<R: Read> FragmentingReader<R> {
+    /// Creates a reader returning fragments of 1 to `max_chunk` bytes.
+    pub fn new(reader: R, max_chunk: usize) -> FragmentingReader<R> {
+        FragmentingReader::with_chunk_sizes(reader, (1..=max_chunk.max(1)).collect())
+    }
+
+    /// Creates a reader cycling through the given fragment sizes.
+    ///
+    /// Sizes of zero are ignored.
+    pub fn with_chunk_sizes(reader: R, sizes: Vec<usize>) -> FragmentingReader<R> {
+        let mut sizes: Vec<usize> = sizes.into_iter().filter(|&x| x > 0).collect();
+        if sizes.is_empty() {
+            sizes.push(1);
+        }
+        FragmentingReader {
+            reader,
+            sizes,
+            index: 0,
+        }
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for FragmentingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.sizes[self.index % self.sizes.len()];
+        self.index = self.index.wrapping_add(1);
+        let len = size.min(buf.len());
+        self.reader.read(&mut buf[..len])
+    }
+}
+
+/// Reads a reader to the end with reads of at most `max_chunk` bytes.
+///
+/// Unlike `read_to_end` this never hands a large buffer to the reader so that
+/// fast paths are not taken.
+pub fn read_fragmented<R: Read>(mut reader: R, max_chunk: usize) -> io::Result<Vec<u8>> {
+    let mut rv = Vec::new();
+    let mut buf = vec![0; max_chunk.max(1)];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(rv),
+            Ok(read) => rv.extend_from_slice(&buf[..read]),
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Asserts that a reader produced by `wrap` turns `input` into `expected`.
+///
+/// The input is fed through `FragmentingReader`s with fragment sizes of up
+/// to 16 bytes and read back with reads of up to 16 bytes.
+pub fn assert_read_translates<'a, F, R>(input: &'a [u8], expected: &[u8], mut wrap: F)
+where
+    F: FnMut(FragmentingReader<&'a [u8]>) -> R,
+    R: Read,
+{
+    for max_chunk in 1..=16 {
+        for max_read in 1..=16 {
+            let rdr = wrap(FragmentingReader::new(input, max_chunk));
+            let rv = read_fragmented(rdr, max_read).expect("read failed");
+            assert!(
+                rv == expected,
+                "translation mismatch (fragments: {}, reads: {})\n  input: {}\n    got: {}\n   want: {}",
+                max_chunk,
+                max_read,
+                String::from_utf8_lossy(input),
+                String::from_utf8_lossy(&rv),
+                String::from_utf8_lossy(expected),
+            );
+        }
+    }
+}
+
+/// Asserts that `input` is translated into `expected` by all entry points.
+///
+/// This checks `translate_slice`, a `Translator` fed with every possible
+/// chunk size and `JsonCompatRead` with many different read patterns.
+pub fn assert_translates(input: &[u8], expected: &[u8]) {
+    let mut buf = input.to_vec();
+    translate_slice(&mut buf);
+    assert_eq!(
+        String::from_utf8_lossy(&buf),
+        String::from_utf8_lossy(expected),
+        "translate_slice mismatch"
+    );
+
+    for chunk_size in 1..=input.len().max(1) {
+        let mut buf = input.to_vec();
+        let mut translator = Translator::new();
+        for chunk in buf.chunks_mut(chunk_size) {
+            translator.feed(chunk).expect("translation failed");
+        }
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            String::from_utf8_lossy(expected),
+            "translator mismatch (chunk size: {})",
+            chunk_size
+        );
+    }
+
+    assert_read_translates(input, expected, JsonCompatRead::wrap);
+}
+
+/// Runs `assert_translates` over a corpus of input and output pairs.
+pub fn assert_corpus(corpus: &[(&[u8], &[u8])]) {
+    for &(input, expected) in corpus {
+        assert_translates(input, expected);
+    }
+}
+
+#[test]
+fn test_fragmenting_reader() {
+    let mut rdr = FragmentingReader::with_chunk_sizes(&b"abcdef"[..], vec![1, 0, 2]);
+    let mut buf = [0; 8];
+    assert_eq!(rdr.read(&mut buf).unwrap(), 1);
+    assert_eq!(rdr.read(&mut buf).unwrap(), 2);
+    assert_eq!(rdr.read(&mut buf).unwrap(), 1);
+    assert_eq!(&buf[..1], b"d");
+}
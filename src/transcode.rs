@@ -0,0 +1,140 @@
+use std::io;
+
+use crate::read::JsonCompatRead;
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+fn read_lossless<R: io::Read>(mut rdr: R) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    rdr.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Streams a Python JSON document from a reader to a writer as strict JSON.
+///
+/// The document is translated while it's read and re-emitted through
+/// `serde_transcode` without ever building the full document in memory, so
+/// this can sanitize files of any size.  The output is minified.
+///
+/// This does not work together with the `arbitrary_precision` feature as
+/// `serde_transcode` re-emits the internal representation `serde_json` uses
+/// for numbers in that case as objects.
+///
+/// ```
+/// # #[cfg(not(feature = "arbitrary_precision"))] {
+/// # use python_json_read_adapter::transcode_to_strict_json;
+/// let mut rv = Vec::new();
+/// transcode_to_strict_json(&b"{\"x\": [NaN, -Infinity]}"[..], &mut rv).unwrap();
+/// assert_eq!(rv, b"{\"x\":[0.0,-0.0]}");
+/// # }
+/// ```
+pub fn transcode_to_strict_json<R, W>(rdr: R, writer: W) -> serde_json::Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let mut de = serde_json::Deserializer::from_reader(JsonCompatRead::wrap(rdr).check_eof(true));
+    let mut ser = serde_json::Serializer::new(writer);
+    serde_transcode::transcode(&mut de, &mut ser)?;
+    de.end()
+}
+
+/// Transcodes a Python JSON document from a reader to CBOR.
+///
+/// Unlike the other functions in this crate this does not replace `NaN` and
+/// `Infinity` with `0.0` but parses them with the lossless parser behind
+/// `from_slice_lossless`.  CBOR represents non-finite floats natively so
+/// they reach the consumers of the output unchanged.  The input is read into
+/// memory, the output is streamed to the writer.
+#[cfg(feature = "cbor")]
+pub fn transcode_to_cbor<R, W>(rdr: R, writer: W) -> io::Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let buf = read_lossless(rdr)?;
+    let mut de = crate::Deserializer::from_slice(&buf);
+    ciborium::into_writer(&serde_transcode::Transcoder::new(&mut de), writer).map_err(|err| {
+        match err {
+            ciborium::ser::Error::Io(err) => err,
+            ciborium::ser::Error::Value(msg) => io::Error::new(io::ErrorKind::InvalidData, msg),
+        }
+    })?;
+    Ok(de.end()?)
+}
+
+/// Transcodes a Python JSON document from a reader to MessagePack.
+///
+/// This works like `transcode_to_cbor` but writes MessagePack, which also
+/// supports non-finite floats.
+#[cfg(feature = "msgpack")]
+pub fn transcode_to_msgpack<R, W>(rdr: R, writer: W) -> io::Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let buf = read_lossless(rdr)?;
+    let mut de = crate::Deserializer::from_slice(&buf);
+    let mut ser = rmp_serde::Serializer::new(writer);
+    serde_transcode::transcode(&mut de, &mut ser).map_err(|err| match err {
+        rmp_serde::encode::Error::InvalidValueWrite(_) => io::Error::other(err),
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    })?;
+    Ok(de.end()?)
+}
+
+#[cfg(not(feature = "arbitrary_precision"))]
+#[test]
+fn test_transcode_to_strict_json() {
+    let json = b"{\n  \"a\": [NaN, Infinity, -Infinity],\n  \"b\": \"NaN\"\n}";
+    let mut rv = Vec::new();
+    transcode_to_strict_json(
+        crate::test_support::FragmentingReader::new(&json[..], 3),
+        &mut rv,
+    )
+    .unwrap();
+    assert_eq!(rv, br#"{"a":[0.0,0.0,-0.0],"b":"NaN"}"#);
+    assert!(transcode_to_strict_json(&b"[1] 2"[..], &mut Vec::new()).is_err());
+    assert!(transcode_to_strict_json(&b"[Infin"[..], &mut Vec::new()).is_err());
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_transcode_to_cbor() {
+    use ciborium::Value;
+
+    let mut rv = Vec::new();
+    transcode_to_cbor(&br#"{"a": [NaN, -Infinity, 1], "b": "NaN"}"#[..], &mut rv).unwrap();
+    let value: Value = ciborium::from_reader(&rv[..]).unwrap();
+    let map = value.as_map().unwrap();
+    assert_eq!(map[0].0, Value::Text("a".into()));
+    let items = map[0].1.as_array().unwrap();
+    assert!(items[0].as_float().unwrap().is_nan());
+    assert_eq!(items[1], Value::Float(f64::NEG_INFINITY));
+    assert_eq!(items[2], Value::Integer(1.into()));
+    assert_eq!(map[1].1, Value::Text("NaN".into()));
+
+    let err = transcode_to_cbor(&b"[1] 2"[..], &mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(transcode_to_cbor(&b"[Infin]"[..], &mut Vec::new()).is_err());
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn test_transcode_to_msgpack() {
+    let mut rv = Vec::new();
+    transcode_to_msgpack(&br#"{"a": [NaN, -Infinity, 1], "b": "NaN"}"#[..], &mut rv).unwrap();
+    let value: std::collections::BTreeMap<String, serde_json::Value> =
+        rmp_serde::from_slice(&rv).unwrap();
+    assert_eq!(value["b"], "NaN");
+    let items: Vec<f64> = rmp_serde::from_slice(&{
+        let mut rv = Vec::new();
+        transcode_to_msgpack(&b"[NaN, -Infinity, Infinity, 2]"[..], &mut rv).unwrap();
+        rv
+    })
+    .unwrap();
+    assert!(items[0].is_nan());
+    assert_eq!(&items[1..], &[f64::NEG_INFINITY, f64::INFINITY, 2.0]);
+
+    let err = transcode_to_msgpack(&b"[1] 2"[..], &mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
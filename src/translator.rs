@@ -0,0 +1,1703 @@
+// the translator must never panic, no matter the input or how it is chunked.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::indexing_slicing,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::arithmetic_side_effects
+    )
+)]
+
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use crate::error::{Error, ErrorKind};
+use crate::options::Options;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Initial,
+    Quoted,
+    QuotedEscape,
+    NaN0,
+    NaN1,
+    Infinity0,
+    Infinity1,
+    Infinity2,
+    Infinity3,
+    Infinity4,
+    Infinity5,
+    Infinity6,
+}
+
+/// The kind of a token that was rewritten.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// `NaN`
+    NaN,
+    /// `Infinity`
+    PosInfinity,
+    /// `-Infinity`
+    NegInfinity,
+    /// An integer that does not fit into 64 bits.
+    ///
+    /// Big integers are never rewritten, this kind is only reported by
+    /// `Error::token_kind` and counted in `TranslateStats::big_int`.
+    BigInt,
+}
+
+impl TokenKind {
+    /// Returns how the token is spelled in a document.
+    pub(crate) fn spelling(self) -> &'static str {
+        match self {
+            TokenKind::NaN => "NaN",
+            TokenKind::PosInfinity => "Infinity",
+            TokenKind::NegInfinity => "-Infinity",
+            TokenKind::BigInt => "big integer",
+        }
+    }
+
+    /// Returns the name of the kind for use in metrics.
+    #[cfg(feature = "metrics")]
+    fn label(self) -> &'static str {
+        match self {
+            TokenKind::NaN => "nan",
+            TokenKind::PosInfinity => "pos_inf",
+            TokenKind::NegInfinity => "neg_inf",
+            TokenKind::BigInt => "big_int",
+        }
+    }
+}
+
+/// A token that was rewritten.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Replacement {
+    /// The kind of token.
+    pub kind: TokenKind,
+    /// The offset of the first byte of the token.
+    pub start: usize,
+    /// The offset after the last byte of the token.
+    pub end: usize,
+}
+
+impl Replacement {
+    /// Returns the byte range of the token.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// Counts of the tokens that were rewritten.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TranslateStats {
+    /// The number of `NaN` tokens.
+    pub nan: usize,
+    /// The number of `Infinity` tokens.
+    pub pos_inf: usize,
+    /// The number of `-Infinity` tokens.
+    pub neg_inf: usize,
+    /// The number of integers that do not fit into 64 bits.
+    ///
+    /// These are not rewritten, but most parsers turn them into lossy floats.
+    /// They are only counted with `Options::count_big_ints`.
+    pub big_int: usize,
+}
+
+impl TranslateStats {
+    /// Returns the count for a single kind of token.
+    pub fn get(&self, kind: TokenKind) -> usize {
+        match kind {
+            TokenKind::NaN => self.nan,
+            TokenKind::PosInfinity => self.pos_inf,
+            TokenKind::NegInfinity => self.neg_inf,
+            TokenKind::BigInt => self.big_int,
+        }
+    }
+
+    /// Returns the total number of rewritten tokens.
+    ///
+    /// Big integers are not included as they are not rewritten.
+    pub fn total(&self) -> usize {
+        self.nan
+            .saturating_add(self.pos_inf)
+            .saturating_add(self.neg_inf)
+    }
+
+    /// Adds the counts of another translation.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge(&mut self, other: &TranslateStats) {
+        self.nan = self.nan.saturating_add(other.nan);
+        self.pos_inf = self.pos_inf.saturating_add(other.pos_inf);
+        self.neg_inf = self.neg_inf.saturating_add(other.neg_inf);
+        self.big_int = self.big_int.saturating_add(other.big_int);
+    }
+
+    fn record(&mut self, kind: TokenKind) {
+        let count = match kind {
+            TokenKind::NaN => &mut self.nan,
+            TokenKind::PosInfinity => &mut self.pos_inf,
+            TokenKind::NegInfinity => &mut self.neg_inf,
+            TokenKind::BigInt => &mut self.big_int,
+        };
+        *count = count.saturating_add(1);
+    }
+}
+
+/// The digits of the integer that is currently being read.
+#[derive(Copy, Clone, Debug, Default)]
+struct IntRun {
+    start: u64,
+    digits: [u8; 20],
+    len: usize,
+    negative: bool,
+    // false if the number turned out to have a fraction or exponent
+    integer: bool,
+}
+
+impl IntRun {
+    /// Feeds the byte at `offset` outside of strings, returns the start of
+    /// a big integer once it ended.
+    fn push(&mut self, byte: u8, prev: u8, offset: u64) -> Option<u64> {
+        if self.len == 0 {
+            if byte.is_ascii_digit() && !prev.is_ascii_alphanumeric() && prev != b'.' {
+                *self = IntRun {
+                    start: if prev == b'-' {
+                        offset.saturating_sub(1)
+                    } else {
+                        offset
+                    },
+                    negative: prev == b'-',
+                    integer: true,
+                    ..IntRun::default()
+                };
+                self.push_digit(byte);
+            }
+            return None;
+        }
+        match byte {
+            b'0'..=b'9' => self.push_digit(byte),
+            b'.' | b'e' | b'E' => self.integer = false,
+            b'+' | b'-' if !self.integer => {}
+            _ => {
+                let run = std::mem::take(self);
+                return Some(run.start).filter(|_| run.is_big());
+            }
+        }
+        None
+    }
+
+    fn push_digit(&mut self, byte: u8) {
+        if let Some(digit) = self.digits.get_mut(self.len) {
+            *digit = byte;
+        }
+        self.len = self.len.saturating_add(1);
+    }
+
+    fn is_big(&self) -> bool {
+        let max: &[u8] = if self.negative {
+            b"9223372036854775808"
+        } else {
+            b"18446744073709551615"
+        };
+        self.integer
+            && (self.len > max.len()
+                || (self.len == max.len() && self.digits.get(..max.len()) > Some(max)))
+    }
+}
+
+/// A resumable translator for Python JSON compat tokens.
+///
+/// This exposes the state machine that powers `JsonCompatRead` and
+/// `translate_slice` for users that do their own buffering.  The document is
+/// fed chunk by chunk in order and each chunk is translated in place.  Tokens
+/// that span multiple chunks are handled correctly.
+///
+/// ```
+/// # use python_json_read_adapter::Translator;
+/// let mut translator = Translator::new();
+/// let mut first = *b"[NaN, -Inf";
+/// let mut second = *b"inity]";
+/// translator.feed(&mut first).unwrap();
+/// translator.feed(&mut second).unwrap();
+/// translator.finish().unwrap();
+/// assert_eq!(&first, b"[0.0, -0.0");
+/// assert_eq!(&second, b"     ]");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Translator {
+    options: Options,
+    state: State,
+    offset: u64,
+    last_byte: u8,
+    negative: bool,
+    token_start: u64,
+    stats: TranslateStats,
+    error: Option<Error>,
+    prefix: Vec<u8>,
+    in_prefix: bool,
+    skip_low_surrogate: bool,
+    int_run: IntRun,
+}
+
+impl Default for Translator {
+    fn default() -> Translator {
+        Translator::new()
+    }
+}
+
+impl Translator {
+    /// Creates a translator for a new document.
+    pub fn new() -> Translator {
+        Translator::with_options(Options::default())
+    }
+
+    /// Creates a translator for a new document with the given options.
+    pub fn with_options(options: Options) -> Translator {
+        Translator {
+            in_prefix: options.skip_prefix.is_some(),
+            options,
+            state: State::Initial,
+            offset: 0,
+            last_byte: 0,
+            negative: false,
+            token_start: 0,
+            stats: TranslateStats::default(),
+            error: None,
+            prefix: Vec::new(),
+            skip_low_surrogate: false,
+            int_run: IntRun::default(),
+        }
+    }
+
+    /// Creates a translator that continues a document at `offset`.
+    ///
+    /// This is only valid between tokens, `last_byte` is the byte in front
+    /// of `offset`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn resume(offset: u64, quoted: bool, last_byte: u8) -> Translator {
+        let mut rv = Translator::new();
+        rv.offset = offset;
+        rv.state = if quoted {
+            State::Quoted
+        } else {
+            State::Initial
+        };
+        rv.last_byte = last_byte;
+        rv
+    }
+
+    /// Translates the next chunk of the document in place.
+    ///
+    /// This can only fail if the options impose restrictions on the
+    /// document.  Once an error was returned the translator stops translating
+    /// and keeps returning the same error.  The part of the chunk after the
+    /// error is left untouched.
+    ///
+    /// With `Options::repair_surrogates` an escape that is cut off by the end
+    /// of the chunk is assumed to start a valid surrogate pair, so a lone
+    /// surrogate split across two chunks is not repaired.  Chunks of a
+    /// document with lone surrogates have to go through `feed_partial`
+    /// instead.  With `Options::case_insensitive` an `n` at the end of the
+    /// chunk is assumed to start `null` and with `Options::plus_infinity` a
+    /// `+` at the end of the chunk is left alone.
+    pub fn feed(&mut self, bytes: &mut [u8]) -> Result<(), Error> {
+        self.feed_with(bytes, |_, _| {})
+    }
+
+    /// Translates the next chunk and reports every rewritten token.
+    ///
+    /// This works like `feed` but invokes the callback with the kind and the
+    /// offset of the first byte of every token once it was completely seen.
+    /// The token may have started in an earlier chunk.
+    pub fn feed_with<F>(&mut self, bytes: &mut [u8], mut on_replacement: F) -> Result<(), Error>
+    where
+        F: FnMut(TokenKind, u64),
+    {
+        self.feed_impl(
+            bytes,
+            &mut |kind, range| on_replacement(kind, range.start),
+            false,
+        )
+        .map(|_| ())
+    }
+
+    /// Translates as much of the next chunk as can be decided on.
+    ///
+    /// With `Options::repair_surrogates` an escape at the end of a chunk can
+    /// only be repaired once the bytes after it are known, the same goes for
+    /// telling `nan` from `null` with `Options::case_insensitive` and signs
+    /// with `Options::plus_infinity`.  Instead of
+    /// guessing like `feed` does, this stops in front of such an escape and
+    /// returns the number of bytes that were translated.  The remaining
+    /// bytes have to be fed again at the start of the next chunk.  At the
+    /// end of the document the rest has to be passed to `feed`.  Without the
+    /// option the whole chunk is always translated.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{Options, Translator};
+    /// let mut translator = Translator::with_options(Options::new().repair_surrogates(true));
+    /// let mut first = *br#"["\ud800"#;
+    /// assert_eq!(translator.feed_partial(&mut first).unwrap(), 2);
+    /// let mut second = *br#"\ud800", NaN]"#;
+    /// translator.feed(&mut second).unwrap();
+    /// assert_eq!(&second, br#"\uFFFD", 0.0]"#);
+    /// ```
+    pub fn feed_partial(&mut self, bytes: &mut [u8]) -> Result<usize, Error> {
+        self.feed_impl(bytes, &mut |_, _| {}, true)
+    }
+
+    pub(crate) fn feed_impl(
+        &mut self,
+        bytes: &mut [u8],
+        on_replacement: &mut dyn FnMut(TokenKind, Range<u64>),
+        partial: bool,
+    ) -> Result<usize, Error> {
+        if let Some(ref err) = self.error {
+            return Err(err.clone());
+        }
+        let skipped = match self.skip_prefix(bytes, partial) {
+            Ok(skipped) => skipped,
+            Err(err) => {
+                self.error = Some(err.clone());
+                return Err(err);
+            }
+        };
+        if self.in_prefix {
+            // the rest of the chunk is needed to tell where the document starts
+            return Ok(skipped);
+        }
+        let bytes = bytes.get_mut(skipped..).unwrap_or_default();
+        #[cfg(fuzzing)]
+        let original = bytes.to_vec();
+        let rv = self.translate(bytes, on_replacement, partial);
+        #[cfg(fuzzing)]
+        check_translation(&original, bytes);
+        match rv {
+            Ok(translated) => Ok(skipped.saturating_add(translated)),
+            Err(err) => {
+                self.error = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Translates the next chunk of the document stored in a ring buffer.
+    ///
+    /// The chunk consists of the `len` bytes starting at `start` and wraps
+    /// around at the end of `ring`, so data that is received through a
+    /// shared memory ring buffer can be translated without copying it into a
+    /// linear buffer first.  `start` is taken modulo the size of the ring so
+    /// that free running positions can be passed directly, and `len` is
+    /// capped at the size of the ring.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::Translator;
+    /// let mut ring = *b"nity]    [NaN, -Infi";
+    /// let mut translator = Translator::new();
+    /// translator.feed_ring(&mut ring, 9, 16).unwrap();
+    /// assert_eq!(&ring, b"    ]    [0.0, -0.0 ");
+    /// ```
+    pub fn feed_ring(&mut self, ring: &mut [u8], start: usize, len: usize) -> Result<(), Error> {
+        let start = start.checked_rem(ring.len()).unwrap_or(0);
+        let len = len.min(ring.len());
+        let (head, tail) = ring.split_at_mut(start);
+        let first_len = len.min(tail.len());
+        let (first, _) = tail.split_at_mut(first_len);
+        self.feed(first)?;
+        let (second, _) = head.split_at_mut(len.saturating_sub(first_len).min(head.len()));
+        self.feed(second)
+    }
+
+    /// Returns the number of bytes translated so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the counts of the tokens that were rewritten so far.
+    ///
+    /// Tokens are counted once they were completely seen.  An integer at
+    /// the very end of the document counts as complete.
+    pub fn stats(&self) -> TranslateStats {
+        let mut stats = self.stats;
+        if self.int_run.is_big() {
+            stats.big_int = stats.big_int.saturating_add(1);
+        }
+        stats
+    }
+
+    /// Returns the bytes that were skipped in front of the document.
+    ///
+    /// This is only ever non-empty with `Options::skip_prefix`.  The bytes
+    /// include the whitespace right before the document.
+    pub fn skipped_prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// Checks if the translator is currently in the middle of a token.
+    pub fn in_token(&self) -> bool {
+        match self.state {
+            State::Initial | State::Quoted | State::QuotedEscape => false,
+            State::Infinity2 => !self.options.case_insensitive,
+            _ => true,
+        }
+    }
+
+    /// Finishes the document.
+    ///
+    /// This fails if the document ended in the middle of a token that was
+    /// being rewritten (for instance `Infin`) which usually indicates that
+    /// the document was truncated.
+    pub fn finish(self) -> Result<(), Error> {
+        self.verify()
+    }
+
+    /// Checks if the document could end at the current position.
+    ///
+    /// This is the non-consuming version of `finish`.
+    pub fn verify(&self) -> Result<(), Error> {
+        if self.options.reject && self.int_run.is_big() {
+            Err(Error::rejected(self.int_run.start, TokenKind::BigInt))
+        } else if self.in_token() {
+            Err(Error::new(ErrorKind::UnexpectedEof, self.offset))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Translator {
+    /// Translates a chunk and returns the number of translated bytes.
+    ///
+    /// This is only ever less than the length of the chunk if `partial` is
+    /// set or the budget was exceeded.
+    fn translate(
+        &mut self,
+        bytes: &mut [u8],
+        on_replacement: &mut dyn FnMut(TokenKind, Range<u64>),
+        partial: bool,
+    ) -> Result<usize, Error> {
+        let mut state = self.state;
+        let mut prev = self.last_byte;
+        let base = self.offset;
+        let mut rv = Ok(());
+        let mut consumed = bytes.len();
+        let backend = self.options.backend.resolve(bytes.len());
+        let caseless = self.options.case_insensitive;
+        let plus = self.options.plus_infinity;
+        let binary = self.options.reject_binary;
+        let big_ints = self.options.count_big_ints;
+        let reject = self.options.reject;
+        // the backends only know about the exact spellings of the tokens so
+        // they cannot skip outside of strings when ignoring case, looking for
+        // signs, checking for binary data or looking at integers.
+        let skip_initial = !caseless && !plus && !binary && !big_ints && !reject;
+        let mut idx = 0;
+        while idx < bytes.len() {
+            if state == State::Quoted || (state == State::Initial && skip_initial) {
+                let rest = bytes.get(idx..).unwrap_or_default();
+                let skipped = backend.skip(rest, state == State::Quoted);
+                if skipped > 0 {
+                    if state == State::Initial {
+                        prev = rest.get(skipped.saturating_sub(1)).copied().unwrap_or(prev);
+                    }
+                    idx = idx.saturating_add(skipped);
+                    continue;
+                }
+            }
+            if state == State::Quoted
+                && self.options.repair_surrogates
+                && bytes.get(idx) == Some(&b'\\')
+            {
+                let escape = bytes.get_mut(idx..).unwrap_or_default();
+                match self.repair_surrogate(escape, partial) {
+                    Some(0) => {}
+                    Some(len) => {
+                        idx = idx.saturating_add(len);
+                        continue;
+                    }
+                    None => {
+                        consumed = idx;
+                        break;
+                    }
+                }
+            }
+            if plus && state == State::Initial && bytes.get(idx) == Some(&b'+') {
+                let signed = match bytes.get(idx.saturating_add(1)) {
+                    Some(&next) => next == b'I' || (caseless && next == b'i'),
+                    None if partial => {
+                        consumed = idx;
+                        break;
+                    }
+                    None => false,
+                };
+                if let Some(c) = bytes.get_mut(idx).filter(|_| signed) {
+                    *c = b' ';
+                }
+                prev = b'+';
+                idx = idx.saturating_add(1);
+                continue;
+            }
+            if caseless && state == State::Initial && bytes.get(idx) == Some(&b'n') {
+                // `nan` and `null` can only be told apart by the next byte
+                match bytes.get(idx.saturating_add(1)) {
+                    Some(b'a') | Some(b'A') => {}
+                    None if partial => {
+                        consumed = idx;
+                        break;
+                    }
+                    _ => {
+                        prev = b'n';
+                        idx = idx.saturating_add(1);
+                        continue;
+                    }
+                }
+            }
+            if binary
+                && state != State::Quoted
+                && state != State::QuotedEscape
+                && matches!(bytes.get(idx), Some(&b) if b == 0 || b >= 0x80)
+            {
+                rv = Err(Error::new(
+                    ErrorKind::BinaryData,
+                    base.saturating_add(idx as u64),
+                ));
+                consumed = idx;
+                break;
+            }
+            let c = match bytes.get_mut(idx) {
+                Some(c) => c,
+                None => break,
+            };
+            let byte = if caseless { fold_case(state, *c) } else { *c };
+            if reject && state == State::Initial && (byte == b'N' || byte == b'I') {
+                let kind = match byte {
+                    b'N' => TokenKind::NaN,
+                    _ if prev == b'-' => TokenKind::NegInfinity,
+                    _ => TokenKind::PosInfinity,
+                };
+                // like for big integers the offset points at the sign
+                let sign = if kind == TokenKind::NegInfinity { 1 } else { 0 };
+                let offset = base.saturating_add(idx as u64).saturating_sub(sign);
+                rv = Err(Error::rejected(offset, kind));
+                consumed = idx;
+                break;
+            }
+            let offset = base.saturating_add(idx as u64);
+            if (big_ints || reject) && state == State::Initial {
+                if let Some(start) = self.int_run.push(*c, prev, offset) {
+                    if reject {
+                        rv = Err(Error::rejected(start, TokenKind::BigInt));
+                        consumed = idx;
+                        break;
+                    }
+                    self.stats.big_int = self.stats.big_int.saturating_add(1);
+                }
+            }
+            let next = match (state, byte) {
+                (State::Initial, b'N') => (State::NaN0, b'0'),
+                (State::NaN0, b'a') => (State::NaN1, b'.'),
+                (State::NaN1, b'N') => (State::Initial, b'0'),
+                (State::Initial, b'I') => (State::Infinity0, b'0'),
+                (State::Infinity0, b'n') => (State::Infinity1, b'.'),
+                (State::Infinity1, b'f') => (State::Infinity2, b'0'),
+                (State::Infinity2, b'i') => (State::Infinity3, b' '),
+                (State::Infinity3, b'n') => (State::Infinity4, b' '),
+                (State::Infinity4, b'i') => (State::Infinity5, b' '),
+                (State::Infinity5, b't') => (State::Infinity6, b' '),
+                (State::Infinity6, b'y') => (State::Initial, b' '),
+                (State::Initial, b'"') => (State::Quoted, b'"'),
+                (State::Quoted, b'\\') => (State::QuotedEscape, b'\\'),
+                (State::QuotedEscape, c) => (State::Quoted, c),
+                (State::Quoted, b'"') => {
+                    self.skip_low_surrogate = false;
+                    (State::Initial, b'"')
+                }
+                (State::Quoted, c) | (State::Initial, c) => (state, c),
+                (_, c) => (State::Initial, c),
+            };
+            let completed = match (state, byte) {
+                (State::Initial, b'N') | (State::Initial, b'I') => {
+                    self.negative = byte == b'I' && prev == b'-';
+                    self.token_start = base.saturating_add(idx as u64);
+                    None
+                }
+                (State::NaN1, b'N') => Some(TokenKind::NaN),
+                (State::Infinity6, b'y') if self.negative => Some(TokenKind::NegInfinity),
+                (State::Infinity6, b'y') => Some(TokenKind::PosInfinity),
+                // with case folding `inf` is a complete token
+                (State::Infinity2, b) if caseless && b != b'i' && self.negative => {
+                    Some(TokenKind::NegInfinity)
+                }
+                (State::Infinity2, b) if caseless && b != b'i' => Some(TokenKind::PosInfinity),
+                _ => None,
+            };
+            // a caseless `inf` is only complete at the byte after it
+            let end = base
+                .saturating_add(idx as u64)
+                .saturating_add(if state == State::Infinity2 { 0 } else { 1 });
+            state = next.0;
+            prev = *c;
+            *c = next.1;
+            if let Some(kind) = completed {
+                self.stats.record(kind);
+                #[cfg(feature = "log")]
+                log::warn!(
+                    "rewrote {} at offset {} of Python JSON document",
+                    kind.spelling(),
+                    self.token_start
+                );
+                #[cfg(feature = "metrics")]
+                metrics::counter!("python_json_replacements_total", "kind" => kind.label())
+                    .increment(1);
+                on_replacement(kind, self.token_start..end);
+                if let Err(err) = self.check_budget() {
+                    rv = Err(err);
+                    consumed = idx.saturating_add(1);
+                    break;
+                }
+            }
+            idx = idx.saturating_add(1);
+        }
+        self.state = state;
+        self.last_byte = prev;
+        self.offset = base.saturating_add(consumed as u64);
+        rv.map(|_| consumed)
+    }
+
+    /// Repairs a lone surrogate escape at the start of `bytes`.
+    ///
+    /// Returns the number of bytes that were handled, zero if the escape is
+    /// to be processed like any other and `None` if more bytes are needed to
+    /// decide.
+    fn repair_surrogate(&mut self, bytes: &mut [u8], partial: bool) -> Option<usize> {
+        let skip_low = std::mem::replace(&mut self.skip_low_surrogate, false);
+        let replace = |bytes: &mut [u8]| {
+            if let Some(escape) = bytes.get_mut(..6) {
+                escape.copy_from_slice(b"\\uFFFD");
+            }
+            Some(6)
+        };
+        match classify_escape(bytes) {
+            Escape::Incomplete if partial => None,
+            Escape::Incomplete => {
+                // assume the escape is a leading surrogate and keep the one
+                // that might follow in the next chunk.
+                self.skip_low_surrogate = true;
+                Some(0)
+            }
+            Escape::Other => Some(0),
+            Escape::Low if skip_low => Some(6),
+            Escape::Low => replace(bytes),
+            Escape::High => match classify_escape(bytes.get(6..).unwrap_or_default()) {
+                Escape::Low => Some(12),
+                Escape::Incomplete if partial => None,
+                Escape::Incomplete => {
+                    self.skip_low_surrogate = true;
+                    Some(6)
+                }
+                Escape::High | Escape::Other => replace(bytes),
+            },
+        }
+    }
+
+    /// Blanks out junk in front of the document and returns its length.
+    fn skip_prefix(&mut self, bytes: &mut [u8], partial: bool) -> Result<usize, Error> {
+        let max = match self.options.skip_prefix {
+            Some(max) if self.in_prefix => max,
+            _ => return Ok(0),
+        };
+        for idx in 0..bytes.len() {
+            let rest = bytes.get(idx..).unwrap_or_default();
+            match opens_document(rest) {
+                Some(true) => {
+                    self.in_prefix = false;
+                    self.offset = self.offset.saturating_add(idx as u64);
+                    return Ok(idx);
+                }
+                // the chunk ends before the bracket can be told from a log
+                // prefix.  Without more bytes it's taken as document start.
+                None if partial => {
+                    self.offset = self.offset.saturating_add(idx as u64);
+                    return Ok(idx);
+                }
+                None => {
+                    self.in_prefix = false;
+                    self.offset = self.offset.saturating_add(idx as u64);
+                    return Ok(idx);
+                }
+                Some(false) => {}
+            }
+            if self.prefix.len() >= max {
+                self.offset = self.offset.saturating_add(idx as u64);
+                return Err(Error::new(ErrorKind::PrefixTooLong, self.offset));
+            }
+            if let Some(c) = bytes.get_mut(idx) {
+                self.prefix.push(*c);
+                *c = b' ';
+            }
+        }
+        self.offset = self.offset.saturating_add(bytes.len() as u64);
+        Ok(bytes.len())
+    }
+
+    fn check_budget(&self) -> Result<(), Error> {
+        match self.options.max_replacements {
+            Some(max) if self.stats.total() > max => {
+                Err(Error::new(ErrorKind::BudgetExceeded, self.token_start))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Checks if the bytes start with a `{` or `[` that opens a document.
+///
+/// The bracket has to be followed by something that can start a value, so
+/// that log prefixes like `[2024-01-01 INFO]` are skipped.  A bare value
+/// in an array has to be followed by `,` or `]`.  Returns `None` if the
+/// bytes end before this can be told.
+fn opens_document(bytes: &[u8]) -> Option<bool> {
+    let is_space = |b: &u8| matches!(*b, b' ' | b'\t' | b'\n' | b'\r');
+    let is_word = |b: &u8| b.is_ascii_alphanumeric() || matches!(*b, b'-' | b'+' | b'.');
+    let (&open, rest) = bytes.split_first()?;
+    if open != b'{' && open != b'[' {
+        return Some(false);
+    }
+    let rest = rest.get(rest.iter().position(|b| !is_space(b))?..)?;
+    let first = *rest.first()?;
+    if open == b'{' {
+        return Some(first == b'"' || first == b'}');
+    }
+    if matches!(first, b'[' | b'{' | b'"' | b']') {
+        return Some(true);
+    }
+    let end = rest.iter().position(|b| !is_word(b))?;
+    let word = rest.get(..end).unwrap_or_default();
+    let after = rest.get(end..).unwrap_or_default();
+    let next = *after.iter().find(|b| !is_space(b))?;
+    let scalar = matches!(word, b"true" | b"false" | b"null")
+        || std::str::from_utf8(word).is_ok_and(|word| word.parse::<f64>().is_ok());
+    Some(scalar && (next == b',' || next == b']'))
+}
+
+/// Maps a byte to the spelling the state machine expects if it only
+/// differs in case.
+fn fold_case(state: State, byte: u8) -> u8 {
+    let expected: &[u8] = match state {
+        State::Initial => b"NI",
+        State::NaN0 => b"a",
+        State::NaN1 => b"N",
+        State::Infinity0 | State::Infinity3 => b"n",
+        State::Infinity1 => b"f",
+        State::Infinity2 | State::Infinity4 => b"i",
+        State::Infinity5 => b"t",
+        State::Infinity6 => b"y",
+        State::Quoted | State::QuotedEscape => b"",
+    };
+    expected
+        .iter()
+        .copied()
+        .find(|c| c.eq_ignore_ascii_case(&byte))
+        .unwrap_or(byte)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Escape {
+    /// The bytes end before it's known what the escape is.
+    Incomplete,
+    /// Anything but a surrogate escape.
+    Other,
+    /// A `\uD800` to `\uDBFF` escape.
+    High,
+    /// A `\uDC00` to `\uDFFF` escape.
+    Low,
+}
+
+/// Classifies the escape at the start of `bytes`.
+fn classify_escape(bytes: &[u8]) -> Escape {
+    let mut kind = Escape::Other;
+    for idx in 0..6 {
+        let byte = match bytes.get(idx) {
+            Some(&byte) => byte,
+            None => return Escape::Incomplete,
+        };
+        let valid = match idx {
+            0 => byte == b'\\',
+            1 => byte == b'u',
+            2 => byte == b'd' || byte == b'D',
+            3 => {
+                kind = match byte {
+                    b'8'..=b'9' | b'a'..=b'b' | b'A'..=b'B' => Escape::High,
+                    b'c'..=b'f' | b'C'..=b'F' => Escape::Low,
+                    _ => Escape::Other,
+                };
+                kind != Escape::Other
+            }
+            _ => byte.is_ascii_hexdigit(),
+        };
+        if !valid {
+            return Escape::Other;
+        }
+    }
+    kind
+}
+
+/// Verifies the invariants of a translation under fuzzing.
+///
+/// Only bytes of the non-finite tokens may be rewritten, and they may only
+/// be rewritten into bytes that form a valid JSON number.
+#[cfg(fuzzing)]
+#[allow(clippy::panic)]
+fn check_translation(original: &[u8], translated: &[u8]) {
+    if original.len() != translated.len() {
+        panic!("translation changed the length");
+    }
+    for (idx, (&a, &b)) in original.iter().zip(translated).enumerate() {
+        let token = b"naninfity+".contains(&a.to_ascii_lowercase()) && b"0. ".contains(&b);
+        let surrogate = a.is_ascii_hexdigit() && b"FD".contains(&b);
+        if a != b && !token && !surrogate {
+            panic!("invalid rewrite at offset {}: {:?} -> {:?}", idx, a, b);
+        }
+    }
+}
+
+/// The size of the chunks `feed_document` translates.
+const DOCUMENT_CHUNK: usize = 64 * 1024;
+
+/// The number of original bytes in front of a chunk kept for error snippets.
+const SNIPPET_BYTES: usize = 256;
+
+/// Translates a complete document in chunks.
+///
+/// After every chunk `inspect` is called with the offset of the chunk, its
+/// original bytes and its translation.  The chunks are fed as partial chunks
+/// so the result is the same as for a single chunk.  Like
+/// `Translator::finish` this fails if the document ends in the middle of a
+/// token.
+pub(crate) fn feed_document<F>(
+    translator: &mut Translator,
+    bytes: &mut [u8],
+    on_replacement: &mut dyn FnMut(TokenKind, Range<u64>),
+    mut inspect: F,
+) -> Result<(), Error>
+where
+    F: FnMut(usize, &[u8], &[u8]),
+{
+    let mut original = Vec::new();
+    let mut pos = 0usize;
+    loop {
+        let end = pos.saturating_add(DOCUMENT_CHUNK).min(bytes.len());
+        let last = end == bytes.len();
+        let chunk = bytes.get_mut(pos..end).unwrap_or_default();
+        original.clear();
+        original.extend_from_slice(chunk);
+        let rv = if last {
+            translator
+                .feed_impl(chunk, on_replacement, false)
+                .and_then(|_| translator.verify())
+                .map(|_| chunk.len())
+        } else {
+            // a chunk that cannot be decided on at all is taken as it is
+            match translator.feed_impl(chunk, on_replacement, true) {
+                Ok(0) => translator.feed_impl(chunk, on_replacement, false),
+                rv => rv,
+            }
+        };
+        inspect(pos, &original, chunk);
+        match rv {
+            Ok(_) if last => return Ok(()),
+            Ok(consumed) => pos = pos.saturating_add(consumed),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Puts original bytes back into a copy of a translated document.
+fn restore_source(bytes: &[u8], originals: &[(usize, &[u8])]) -> Vec<u8> {
+    let mut rv = bytes.to_vec();
+    for &(start, original) in originals {
+        if let Some(region) = rv.get_mut(start..start.saturating_add(original.len())) {
+            region.copy_from_slice(original);
+        }
+    }
+    rv
+}
+
+/// Translates a complete document.
+///
+/// Errors carry a snippet of the original document.  With the `tracing`
+/// feature this is wrapped in a span and reports the outcome as an event.
+fn translate_document(
+    translator: &mut Translator,
+    bytes: &mut [u8],
+    on_replacement: &mut dyn FnMut(TokenKind, Range<u64>),
+) -> Result<(), Error> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("translate", size = bytes.len()).entered();
+    #[cfg(feature = "tracing")]
+    let start = std::time::Instant::now();
+    // the original bytes of the current chunk and a few bytes in front of it
+    let mut window = Vec::new();
+    let mut window_start = 0usize;
+    let rv = feed_document(
+        translator,
+        bytes,
+        on_replacement,
+        |pos: usize, original: &[u8], _: &[u8]| {
+            window.truncate(pos.saturating_sub(window_start));
+            let stale = window.len().saturating_sub(SNIPPET_BYTES);
+            window.drain(..stale);
+            window_start = pos.saturating_sub(window.len());
+            window.extend_from_slice(original);
+        },
+    )
+    .map_err(|err| {
+        let originals = [
+            (0, translator.skipped_prefix()),
+            (window_start, &window[..]),
+        ];
+        err.with_source(&restore_source(bytes, &originals))
+    });
+    #[cfg(feature = "tracing")]
+    trace_document(translator, start.elapsed(), rv.as_ref().err());
+    rv
+}
+
+/// Reports a translated document to `tracing`.
+#[cfg(feature = "tracing")]
+pub(crate) fn trace_document(
+    translator: &Translator,
+    duration: std::time::Duration,
+    error: Option<&Error>,
+) {
+    let stats = translator.stats();
+    tracing::debug!(
+        size = translator.offset(),
+        nan = stats.nan,
+        pos_inf = stats.pos_inf,
+        neg_inf = stats.neg_inf,
+        duration_us = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX),
+        error = error.map(tracing::field::display),
+        "translated Python JSON document"
+    );
+}
+
+/// Translates a slice in place.
+///
+/// This works the same as the `JsonCompatRead` struct but instead converts a
+/// slice in place.  This is useful when working with JSON in slices.
+pub fn translate_slice(bytes: &mut [u8]) {
+    // without options the translation can only fail on a truncated token
+    let _ = translate_document(&mut Translator::new(), bytes, &mut |_, _| {});
+}
+
+/// Translates a slice in place and counts the Python quirks in it.
+///
+/// This works like `translate_slice` but returns the counts of the
+/// rewritten tokens and of the integers that do not fit into 64 bits.
+///
+/// ```
+/// # use python_json_read_adapter::translate_slice_with_stats;
+/// let mut json = b"[NaN, 18446744073709551616, -Infinity]".to_vec();
+/// let stats = translate_slice_with_stats(&mut json);
+/// assert_eq!((stats.nan, stats.neg_inf, stats.big_int), (1, 1, 1));
+/// ```
+pub fn translate_slice_with_stats(bytes: &mut [u8]) -> TranslateStats {
+    let mut translator = Translator::with_options(Options::new().count_big_ints(true));
+    // without limits the translation can only fail on a truncated token
+    let _ = translate_document(&mut translator, bytes, &mut |_, _| {});
+    translator.stats()
+}
+
+/// Translates a slice in place and reports every rewritten token.
+///
+/// Signs are not part of the ranges.
+/// If the options reject the document, the error is returned and the
+/// replacements up to that point are lost.
+///
+/// ```
+/// # use python_json_read_adapter::{translate_slice_with_report, Options, TokenKind};
+/// let mut json = br#"{"a": NaN, "b": -Infinity}"#.to_vec();
+/// let report = translate_slice_with_report(&mut json, &Options::new()).unwrap();
+/// assert_eq!(report[1].kind, TokenKind::NegInfinity);
+/// assert_eq!(report[1].range(), 17..25);
+/// ```
+pub fn translate_slice_with_report(
+    bytes: &mut [u8],
+    options: &Options,
+) -> Result<Vec<Replacement>, Error> {
+    let mut rv = Vec::new();
+    let mut translator = Translator::with_options(options.clone());
+    translate_document(&mut translator, bytes, &mut |kind, range| {
+        rv.push(Replacement {
+            kind,
+            start: usize::try_from(range.start).unwrap_or(usize::MAX),
+            end: usize::try_from(range.end).unwrap_or(usize::MAX),
+        })
+    })?;
+    Ok(rv)
+}
+
+/// Translates a slice in place with the given options.
+///
+/// On success the counts of the rewritten tokens are returned.  If the
+/// options reject the document or it ends in the middle of a token, the
+/// slice is only partially translated.
+pub fn translate_slice_with_options(
+    bytes: &mut [u8],
+    options: &Options,
+) -> Result<TranslateStats, Error> {
+    let mut translator = Translator::with_options(options.clone());
+    translate_document(&mut translator, bytes, &mut |_, _| {})?;
+    Ok(translator.stats())
+}
+
+#[test]
+fn test_translate_slice() {
+    let mut json = br#"{"nan":"nan","Infinity":"-Infinity","other":NaN}"#.to_vec();
+    translate_slice(&mut json[..]);
+    assert_eq!(
+        &json[..],
+        &b"{\"nan\":\"nan\",\"Infinity\":\"-Infinity\",\"other\":0.0}"[..]
+    );
+}
+
+#[test]
+fn test_translator_chunks() {
+    let mut translator = Translator::new();
+    let mut json = br#"{"nan":NaN,"inf":Infinity,"s":"\"NaN"}"#.to_vec();
+    for chunk in json.chunks_mut(3) {
+        translator.feed(chunk).unwrap();
+    }
+    assert_eq!(translator.offset(), 38);
+    translator.finish().unwrap();
+    assert_eq!(&json[..], &br#"{"nan":0.0,"inf":0.0     ,"s":"\"NaN"}"#[..]);
+}
+
+#[test]
+fn test_translator_stats() {
+    let mut translator = Translator::new();
+    let mut json = br#"[NaN,-Infinity,Infinity,"NaN",-"#.to_vec();
+    translator.feed(&mut json).unwrap();
+    translator.feed(&mut b"Infinity,NaN,Inf]".to_vec()).unwrap();
+    assert_eq!(
+        translator.stats(),
+        TranslateStats {
+            nan: 2,
+            pos_inf: 1,
+            neg_inf: 2,
+            big_int: 0,
+        }
+    );
+    assert_eq!(translator.stats().total(), 5);
+    assert_eq!(translator.stats().get(TokenKind::NegInfinity), 2);
+}
+
+#[test]
+fn test_translator_feed_with() {
+    let mut translator = Translator::new();
+    let mut events = Vec::new();
+    let mut json = br#"[NaN, "NaN", -Inf"#.to_vec();
+    translator
+        .feed_with(&mut json, |kind, offset| events.push((kind, offset)))
+        .unwrap();
+    translator
+        .feed_with(&mut b"inity]".to_vec(), |kind, offset| {
+            events.push((kind, offset))
+        })
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![(TokenKind::NaN, 1), (TokenKind::NegInfinity, 14)]
+    );
+}
+
+#[test]
+fn test_translator_feed_ring() {
+    let mut translator = Translator::new();
+    let mut ring = *b"    [NaN";
+    translator.feed_ring(&mut ring, 4, 4).unwrap();
+    assert_eq!(&ring, b"    [0.0");
+    ring[..6].copy_from_slice(b", Infi");
+    translator.feed_ring(&mut ring, 8, 6).unwrap();
+    assert_eq!(&ring, b", 0.0 .0");
+    ring[6..].copy_from_slice(b"ni");
+    ring[..3].copy_from_slice(b"ty]");
+    translator.feed_ring(&mut ring, 14, 5).unwrap();
+    assert_eq!(&ring, b"  ].0   ");
+    assert_eq!(translator.offset(), 15);
+    assert_eq!(translator.stats().total(), 2);
+
+    let mut ring = *b"NaN";
+    Translator::new().feed_ring(&mut ring, 7, 10).unwrap();
+    assert_eq!(&ring, b"Na0");
+    Translator::new().feed_ring(&mut [], 3, 3).unwrap();
+}
+
+#[test]
+fn test_translate_document_snippet() {
+    let snippet = |json: &[u8], options: Options| {
+        let mut json = json.to_vec();
+        let err = translate_slice_with_options(&mut json, &options).unwrap_err();
+        (err.line(), err.column(), err.to_string())
+    };
+    let (_, column, rendered) = snippet(b"[NaN, Infinity]", Options::new().max_replacements(1));
+    assert_eq!(column, Some(7));
+    assert!(rendered.contains("1 | [NaN, Infinity]"), "{}", rendered);
+    let (_, _, rendered) = snippet(b"[nan, Infin", Options::new().case_insensitive(true));
+    assert!(rendered.contains("1 | [nan, Infin"), "{}", rendered);
+    let options = Options::new().skip_prefix(8).max_replacements(0);
+    let (line, column, rendered) = snippet(b")]}'\n[NaN]", options);
+    assert_eq!((line, column), (Some(2), Some(2)));
+    assert!(rendered.contains("2 | [NaN]"), "{}", rendered);
+
+    // the window of original bytes follows the chunks
+    let mut json = b"[".to_vec();
+    while json.len() < 3 * DOCUMENT_CHUNK {
+        json.extend_from_slice(b"NaN, ");
+    }
+    json.extend_from_slice(b"Infin");
+    let (_, _, rendered) = snippet(&json, Options::new());
+    assert!(rendered.contains("NaN, NaN, Infin\n"), "{}", rendered);
+}
+
+#[test]
+fn test_translator_budget() {
+    let options = Options::new().max_replacements(2);
+    let mut json = b"[NaN, Infinity, -Infinity, NaN]".to_vec();
+    let err = translate_slice_with_options(&mut json, &options).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BudgetExceeded);
+    assert_eq!(err.offset(), Some(17));
+    assert_eq!(&json[..], b"[0.0, 0.0     , -0.0     , NaN]");
+
+    let mut translator = Translator::with_options(options.clone());
+    translator.feed(&mut b"[NaN, NaN, Na".to_vec()).unwrap();
+    let mut rest = b"N, NaN]".to_vec();
+    assert!(translator.feed(&mut rest).is_err());
+    assert_eq!(translator.offset(), 14);
+    assert_eq!(&rest[..], b"0, NaN]");
+    assert!(translator.feed(&mut b"[]".to_vec()).is_err());
+
+    let mut json = b"[NaN, NaN]".to_vec();
+    let stats = translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(stats.nan, 2);
+}
+
+#[test]
+fn test_translate_slice_with_stats() {
+    let mut json = br#"[18446744073709551615, 18446744073709551616, -9223372036854775808,
+        -9223372036854775809, 1e100000000000000000000, 1.00000000000000000000001,
+        "123456789012345678901234", 123456789012345678901234, 0.5E-123456789012345678901,
+        x123456789012345678901234, 100000000000000000000"#
+        .to_vec();
+    let stats = translate_slice_with_stats(&mut json);
+    assert_eq!(stats.big_int, 4);
+
+    let mut translator = Translator::with_options(Options::new().count_big_ints(true));
+    translator.feed(&mut b"[NaN, 1234567890".to_vec()).unwrap();
+    translator.feed(&mut b"12345678901".to_vec()).unwrap();
+    assert_eq!(translator.stats().big_int, 1);
+    translator.feed(&mut b".5]".to_vec()).unwrap();
+    assert_eq!(translator.stats().big_int, 0);
+    assert_eq!(translator.stats().nan, 1);
+    assert_eq!(
+        translate_slice_with_stats(&mut b"-99999999999999999999".to_vec()).big_int,
+        1
+    );
+}
+
+#[test]
+fn test_translate_slice_with_report() {
+    let mut json = br#"[NaN, "NaN", -Infinity, +Infinity, inf]"#.to_vec();
+    let options = Options::new().plus_infinity(true).case_insensitive(true);
+    let report = translate_slice_with_report(&mut json, &options).unwrap();
+    let ranges: Vec<_> = report.iter().map(|r| (r.kind, r.range())).collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (TokenKind::NaN, 1..4),
+            (TokenKind::NegInfinity, 14..22),
+            (TokenKind::PosInfinity, 25..33),
+            (TokenKind::PosInfinity, 35..38),
+        ]
+    );
+    for r in &report {
+        assert!(json[r.range()].iter().all(|b| b"0. ".contains(b)));
+    }
+
+    let options = Options::new().max_replacements(1);
+    assert!(translate_slice_with_report(&mut b"[NaN, NaN]".to_vec(), &options).is_err());
+}
+
+#[test]
+fn test_translator_reject() {
+    let options = Options::new().reject(true);
+    let err = |json: &[u8]| {
+        let mut json = json.to_vec();
+        let err = translate_slice_with_options(&mut json, &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::RejectedToken);
+        assert_eq!(err.detail(), err.token_kind().map(TokenKind::spelling));
+        (err.offset().unwrap(), err.token_kind().unwrap())
+    };
+    assert_eq!(err(b"[1, NaN]"), (4, TokenKind::NaN));
+    assert_eq!(err(b"[\"NaN\", Infinity]"), (8, TokenKind::PosInfinity));
+    assert_eq!(err(b"[-Infinity]"), (1, TokenKind::NegInfinity));
+    assert_eq!(err(b"[-9223372036854775809]"), (1, TokenKind::BigInt));
+    assert_eq!(
+        err(b"{\"a\": 123456789012345678901}"),
+        (6, TokenKind::BigInt)
+    );
+
+    let mut json = b"[1.5, 18446744073709551615, \"NaN\", -1e999]".to_vec();
+    translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(&json[..], b"[1.5, 18446744073709551615, \"NaN\", -1e999]");
+
+    let mut translator = Translator::with_options(options);
+    let mut json = b"[0, N".to_vec();
+    assert!(translator.feed(&mut json).is_err());
+    assert_eq!(&json[..], b"[0, N");
+    let mut translator = Translator::with_options(Options::new().reject(true));
+    translator
+        .feed(&mut b"99999999999999999999".to_vec())
+        .unwrap();
+    assert_eq!(
+        translator.verify().unwrap_err().kind(),
+        ErrorKind::RejectedToken
+    );
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn test_translator_log() {
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct Logger;
+
+    impl log::Log for Logger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            if record.level() == log::Level::Warn {
+                RECORDS.lock().unwrap().push(record.args().to_string());
+            }
+        }
+        fn flush(&self) {}
+    }
+
+    log::set_logger(&Logger).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+    let mut json = vec![b' '; 12345];
+    json.extend_from_slice(b"-Infinity");
+    translate_slice(&mut json);
+    assert!(RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|msg| msg == "rewrote -Infinity at offset 12346 of Python JSON document"));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_translate_tracing() {
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata};
+
+    #[derive(Default)]
+    struct Fields(Vec<String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes) -> Id {
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+            self.0.lock().unwrap().push(format!(
+                "span {} {}",
+                span.metadata().name(),
+                fields.0.join(" ")
+            ));
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let fields: Vec<_> = fields
+                .0
+                .into_iter()
+                .filter(|field| !field.starts_with("duration_us"))
+                .collect();
+            self.0.lock().unwrap().push(fields.join(" "));
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let recorder = Recorder::default();
+    let records = recorder.0.clone();
+    tracing::subscriber::with_default(recorder, || {
+        translate_slice(&mut b"[NaN, -Infinity, NaN]".to_vec());
+        let mut rdr = crate::JsonCompatRead::wrap(&b"[Infinity]"[..]);
+        std::io::Read::read_to_end(&mut rdr, &mut Vec::new()).unwrap();
+    });
+    assert_eq!(
+        *records.lock().unwrap(),
+        vec![
+            "span translate size=21",
+            "message=translated Python JSON document size=21 nan=2 pos_inf=0 neg_inf=1",
+            "message=translated Python JSON document size=10 nan=0 pos_inf=1 neg_inf=0",
+        ]
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_translate_metrics() {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, SharedString, Unit};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<BTreeMap<String, Arc<AtomicU64>>>);
+
+    impl metrics::Recorder for Recorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let labels: Vec<_> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            Counter::from_arc(self.0.lock().unwrap().entry(name).or_default().clone())
+        }
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    let recorder = Recorder::default();
+    metrics::with_local_recorder(&recorder, || {
+        translate_slice(&mut b"[NaN, -Infinity, NaN, \"NaN\"]".to_vec());
+    });
+    let counts: Vec<_> = recorder
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, count)| (name.clone(), count.load(Ordering::SeqCst)))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            ("python_json_replacements_total{kind=nan}".to_string(), 2),
+            (
+                "python_json_replacements_total{kind=neg_inf}".to_string(),
+                1
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_translator_reject_binary() {
+    let options = Options::new().reject_binary(true);
+    let mut json = "[\"\u{0}\u{e9}\", NaN, \"\\\"\u{1f600}\"]"
+        .as_bytes()
+        .to_vec();
+    translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(
+        &json[..],
+        "[\"\u{0}\u{e9}\", 0.0, \"\\\"\u{1f600}\"]".as_bytes()
+    );
+
+    let mut json = b"[NaN, \x89PNG]".to_vec();
+    let err = translate_slice_with_options(&mut json, &options).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BinaryData);
+    assert_eq!(err.offset(), Some(6));
+
+    let mut translator = Translator::with_options(options);
+    translator.feed(&mut b"[Na".to_vec()).unwrap();
+    let err = translator.feed(&mut b"\x00".to_vec()).unwrap_err();
+    assert_eq!(err.offset(), Some(3));
+    assert!(translate_slice_with_options(&mut b"[\x00]".to_vec(), &Options::new()).is_ok());
+}
+
+#[test]
+fn test_translator_finish_truncated() {
+    let mut translator = Translator::new();
+    translator.feed(&mut b"[1, -Infin".to_vec()).unwrap();
+    assert!(translator.in_token());
+    let err = translator.finish().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(err.offset(), Some(10));
+
+    let mut translator = Translator::new();
+    translator.feed(&mut b"[\"NaN".to_vec()).unwrap();
+    assert!(translator.finish().is_ok());
+}
+
+#[test]
+fn test_translate_never_panics() {
+    // a cheap deterministic fuzzer: random documents made of the interesting
+    // bytes are fed with every chunk size and must keep their length.
+    let alphabet = b"NaInfity\"\\- 0.,[]{}:";
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..200 {
+        let mut doc = Vec::new();
+        for _ in 0..(seed % 40) {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            doc.push(alphabet[(seed % alphabet.len() as u64) as usize]);
+        }
+        for chunk_size in 1..=doc.len().max(1) {
+            let mut buf = doc.clone();
+            let mut translator = Translator::new();
+            for chunk in buf.chunks_mut(chunk_size) {
+                translator.feed(chunk).unwrap();
+            }
+            assert_eq!(buf.len(), doc.len());
+            assert_eq!(translator.offset(), doc.len() as u64);
+            let _ = translator.finish();
+        }
+    }
+}
+
+#[test]
+fn test_translate_negative_nan() {
+    // the sign stays and makes for a valid negative zero
+    let mut json = b"[-NaN, NaN]".to_vec();
+    let stats = translate_slice_with_options(&mut json, &Options::new()).unwrap();
+    assert_eq!(&json[..], b"[-0.0, 0.0]");
+    assert_eq!(stats.nan, 2);
+}
+
+#[test]
+fn test_translate_slice_invalid() {
+    let mut json = br#"Inferior,Nax,null,"Infinity",Nen"#.to_vec();
+    translate_slice(&mut json[..]);
+    println!("{}", String::from_utf8_lossy(&json));
+    assert_eq!(&json[..], &br#"0.0erior,0.x,null,"Infinity",0en"#[..]);
+}
+
+#[test]
+fn test_translate_backends() {
+    use crate::backend::Backend;
+    let doc = br#"{"some long key": [1, 2, NaN], "s": "NaN \" Infinity", "x": -Infinity}"#;
+    let mut expected = doc.to_vec();
+    translate_slice(&mut expected);
+    let backends = [
+        Backend::Scalar,
+        Backend::Swar,
+        #[cfg(feature = "memchr")]
+        Backend::Memchr,
+        #[cfg(feature = "simd")]
+        Backend::Simd,
+    ];
+    for &backend in &backends {
+        let options = Options::new().backend(backend);
+        for chunk_size in 1..=doc.len() {
+            let mut json = doc.to_vec();
+            let mut translator = Translator::with_options(options.clone());
+            for chunk in json.chunks_mut(chunk_size) {
+                translator.feed(chunk).unwrap();
+            }
+            assert_eq!(json, expected);
+            assert_eq!(translator.stats().total(), 2);
+        }
+    }
+}
+
+#[test]
+fn test_translator_skip_prefix() {
+    let mut translator = Translator::with_options(Options::new().skip_prefix(32));
+    let mut json = b"\xef\xbb\xbf2024-01-01 INFO: [NaN, {}]".to_vec();
+    for chunk in json.chunks_mut(4) {
+        translator.feed(chunk).unwrap();
+    }
+    translator.verify().unwrap();
+    assert_eq!(&json[..], &b"                    [0.0, {}]"[..]);
+    assert_eq!(
+        translator.skipped_prefix(),
+        b"\xef\xbb\xbf2024-01-01 INFO: "
+    );
+    assert_eq!(translator.stats().nan, 1);
+
+    let mut translator = Translator::with_options(Options::new().skip_prefix(3));
+    let err = translator.feed(&mut b"while(1);{}".to_vec()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PrefixTooLong);
+    assert_eq!(err.offset(), Some(3));
+    assert_eq!(translator.feed(&mut []).unwrap_err(), err);
+
+    // brackets in log prefixes are skipped
+    let options = Options::new().skip_prefix(64);
+    let mut json = br#"[2024-01-01 INFO] [worker-1] {"a": NaN, "b": [1, 2]}"#.to_vec();
+    translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(
+        &json[..],
+        &br#"                             {"a": 0.0, "b": [1, 2]}"#[..]
+    );
+    let mut json = b"[INFO] [ NaN ]".to_vec();
+    translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(&json[..], b"       [ 0.0 ]");
+    let mut json = b"[pid 3] {}".to_vec();
+    translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(&json[..], b"        {}");
+
+    // partial chunks stop in front of brackets that cannot be told yet
+    let mut translator = Translator::with_options(options);
+    let mut json = b"[2024 [NaN".to_vec();
+    assert_eq!(translator.feed_partial(&mut json).unwrap(), 6);
+    assert_eq!(&json[..], b"      [NaN");
+    let mut json = b"[NaN]".to_vec();
+    assert_eq!(translator.feed_partial(&mut json).unwrap(), 5);
+    assert_eq!(&json[..], b"[0.0]");
+
+    // without the option nothing is skipped
+    let mut json = b")]}'{}".to_vec();
+    translate_slice(&mut json);
+    assert_eq!(&json[..], b")]}'{}");
+}
+
+#[test]
+fn test_translator_repair_surrogates() {
+    let options = Options::new().repair_surrogates(true);
+    let doc = br#"["\ud800", "\ud83d\ude00x", "\uDC00\uD800\uD800", "\\ud800", "\u00e9", NaN]"#;
+    let expected =
+        br#"["\uFFFD", "\ud83d\ude00x", "\uFFFD\uFFFD\uFFFD", "\\ud800", "\u00e9", 0.0]"#;
+    let mut json = doc.to_vec();
+    translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(&json[..], &expected[..]);
+
+    for chunk_size in 1..=doc.len() {
+        let mut translator = Translator::with_options(options.clone());
+        let mut rv = Vec::new();
+        let mut pending = Vec::new();
+        for chunk in doc.chunks(chunk_size) {
+            pending.extend_from_slice(chunk);
+            let translated = translator.feed_partial(&mut pending).unwrap();
+            rv.extend(pending.drain(..translated));
+        }
+        translator.feed(&mut pending).unwrap();
+        rv.extend(pending);
+        assert_eq!(&rv[..], &expected[..]);
+        assert_eq!(translator.offset(), doc.len() as u64);
+    }
+
+    // with plain feed pairs that are split up are kept
+    for split in 1..12 {
+        let mut json = br#""\ud83d\ude00""#.to_vec();
+        let mut translator = Translator::with_options(options.clone());
+        let (first, second) = json.split_at_mut(split);
+        translator.feed(first).unwrap();
+        translator.feed(second).unwrap();
+        assert_eq!(&json[..], br#""\ud83d\ude00""#);
+    }
+
+    // but a split lone surrogate cannot be told from a pair and is kept too
+    let mut json = br#""\ud800x""#.to_vec();
+    let mut translator = Translator::with_options(options.clone());
+    let (first, second) = json.split_at_mut(4);
+    translator.feed(first).unwrap();
+    translator.feed(second).unwrap();
+    assert_eq!(&json[..], br#""\ud800x""#);
+
+    let mut json = doc.to_vec();
+    translate_slice(&mut json);
+    assert_eq!(&json[..9], &doc[..9]);
+}
+
+#[test]
+fn test_translator_case_insensitive() {
+    let options = Options::new().case_insensitive(true);
+    let doc = b"[nan, NAN, -inf, inf, Inf, INFINITY, -Infinity, null, nan]";
+    let expected = b"[0.0, 0.0, -0.0, 0.0, 0.0, 0.0     , -0.0     , null, 0.0]";
+    for chunk_size in 1..=doc.len() {
+        let mut translator = Translator::with_options(options.clone());
+        let mut rv = Vec::new();
+        let mut pending = Vec::new();
+        for chunk in doc.chunks(chunk_size) {
+            pending.extend_from_slice(chunk);
+            let translated = translator.feed_partial(&mut pending).unwrap();
+            rv.extend(pending.drain(..translated));
+        }
+        translator.feed(&mut pending).unwrap();
+        rv.extend(pending);
+        assert_eq!(&rv[..], &expected[..]);
+        assert_eq!(
+            translator.stats(),
+            TranslateStats {
+                nan: 3,
+                pos_inf: 3,
+                neg_inf: 2,
+                big_int: 0,
+            }
+        );
+    }
+
+    let mut translator = Translator::with_options(options.clone());
+    translator.feed(&mut b"inf".to_vec()).unwrap();
+    assert!(translator.verify().is_ok());
+    translator.feed(&mut b" -infin".to_vec()).unwrap();
+    assert!(translator.verify().is_err());
+
+    let mut json = b"[nan, inf]".to_vec();
+    translate_slice(&mut json);
+    assert_eq!(&json[..], b"[nan, inf]");
+}
+
+#[test]
+fn test_translator_plus_infinity() {
+    let options = Options::new().plus_infinity(true);
+    let doc = b"[+Infinity, 1e+5, -Infinity, +1, +NaN]";
+    let expected = b"[ 0.0     , 1e+5, -0.0     , +1, +0.0]";
+    for chunk_size in 1..=doc.len() {
+        let mut translator = Translator::with_options(options.clone());
+        let mut rv = Vec::new();
+        let mut pending = Vec::new();
+        for chunk in doc.chunks(chunk_size) {
+            pending.extend_from_slice(chunk);
+            let translated = translator.feed_partial(&mut pending).unwrap();
+            rv.extend(pending.drain(..translated));
+        }
+        translator.feed(&mut pending).unwrap();
+        rv.extend(pending);
+        assert_eq!(&rv[..], &expected[..]);
+        assert_eq!(translator.stats().pos_inf, 1);
+    }
+
+    let mut json = b"+inf".to_vec();
+    let options = options.case_insensitive(true);
+    translate_slice_with_options(&mut json, &options).unwrap();
+    assert_eq!(&json[..], b" 0.0");
+
+    let mut json = b"+Infinity".to_vec();
+    translate_slice(&mut json);
+    assert_eq!(&json[..], b"+0.0     ");
+}
@@ -0,0 +1,474 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+use serde_json::{Number, Value};
+use serde_self::de::{self, Deserialize, MapAccess, SeqAccess, Visitor};
+use serde_self::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::de::{from_reader_lossless, from_slice_lossless};
+
+/// The key `serde_json` uses for numbers with the `arbitrary_precision`
+/// feature.
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Controls how non-finite floats are converted into `serde_json::Value`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NonFinitePolicy {
+    /// Converts non-finite floats to `null`.
+    #[default]
+    Null,
+    /// Converts non-finite floats to `0.0` and `-Infinity` to `-0.0`, which
+    /// is what the translation does.
+    Zero,
+    /// Converts non-finite floats to the strings `"NaN"`, `"Infinity"` and
+    /// `"-Infinity"`.
+    String,
+    /// Converts the infinities to the largest finite floats and `NaN` to
+    /// `null`.
+    Clamp,
+}
+
+impl NonFinitePolicy {
+    fn convert(self, value: f64) -> Value {
+        match self {
+            NonFinitePolicy::Null => Value::Null,
+            NonFinitePolicy::Zero if value < 0.0 => Value::from(-0.0),
+            NonFinitePolicy::Zero => Value::from(0.0),
+            NonFinitePolicy::String => Value::String(crate::PyFloat(value).to_string()),
+            NonFinitePolicy::Clamp if value.is_nan() => Value::Null,
+            NonFinitePolicy::Clamp if value < 0.0 => Value::from(f64::MIN),
+            NonFinitePolicy::Clamp => Value::from(f64::MAX),
+        }
+    }
+}
+
+/// The flavour of JSON a `PyValue` is written as.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// Strict JSON with non-finite floats converted according to the policy.
+    Strict(NonFinitePolicy),
+    /// Python flavoured JSON that keeps `NaN` and `Infinity`.
+    Python,
+}
+
+/// A JSON value that can hold non-finite floats.
+///
+/// `serde_json::Value` has no way to represent `NaN` and `Infinity`, so
+/// going through it is always lossy.  This is the same kind of value with an
+/// additional variant for non-finite floats.  Values are best created with
+/// `from_slice_value` or `from_reader_value`.
+///
+/// ```
+/// # use python_json_read_adapter::{from_slice_value, PyValue};
+/// let value = from_slice_value(br#"{"x": [1, NaN, -Infinity]}"#).unwrap();
+/// let items = value.get("x").and_then(PyValue::as_array).unwrap();
+/// assert!(items[1].as_f64().unwrap().is_nan());
+/// assert_eq!(value.to_string(), r#"{"x":[1,NaN,-Infinity]}"#);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PyValue {
+    /// `null`
+    #[default]
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// A finite number.
+    Number(Number),
+    /// `NaN`, `Infinity` or `-Infinity`
+    NonFinite(f64),
+    /// A string.
+    String(String),
+    /// An array.
+    Array(Vec<PyValue>),
+    /// An object with its keys sorted.
+    Object(BTreeMap<String, PyValue>),
+}
+
+impl PyValue {
+    /// Checks if the value is `null`.
+    pub fn is_null(&self) -> bool {
+        matches!(*self, PyValue::Null)
+    }
+
+    /// Returns the value of a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            PyValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a number, including non-finite ones, as float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            PyValue::Number(ref value) => value.as_f64(),
+            PyValue::NonFinite(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            PyValue::String(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the items of an array.
+    pub fn as_array(&self) -> Option<&Vec<PyValue>> {
+        match *self {
+            PyValue::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries of an object.
+    pub fn as_object(&self) -> Option<&BTreeMap<String, PyValue>> {
+        match *self {
+            PyValue::Object(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Looks up a key in an object.
+    pub fn get(&self, key: &str) -> Option<&PyValue> {
+        self.as_object().and_then(|map| map.get(key))
+    }
+
+    /// Converts the value into a `serde_json::Value`.
+    ///
+    /// Non-finite floats cannot be represented and are converted according
+    /// to the policy.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{from_slice_value, NonFinitePolicy};
+    /// let value = from_slice_value(b"[1, NaN, -Infinity]").unwrap();
+    /// assert_eq!(
+    ///     value.to_json_value(NonFinitePolicy::String),
+    ///     serde_json::json!([1, "NaN", "-Infinity"])
+    /// );
+    /// ```
+    pub fn to_json_value(&self, policy: NonFinitePolicy) -> Value {
+        match *self {
+            PyValue::Null => Value::Null,
+            PyValue::Bool(value) => Value::Bool(value),
+            PyValue::Number(ref value) => Value::Number(value.clone()),
+            PyValue::NonFinite(value) => policy.convert(value),
+            PyValue::String(ref value) => Value::String(value.clone()),
+            PyValue::Array(ref items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| item.to_json_value(policy))
+                    .collect(),
+            ),
+            PyValue::Object(ref map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.to_json_value(policy)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Writes the value as compact JSON in the given dialect.
+    ///
+    /// This makes it possible to read a document from a Python system,
+    /// modify it and hand it back either to the same system or to a strict
+    /// JSON consumer.
+    ///
+    /// ```
+    /// # use python_json_read_adapter::{from_slice_value, Dialect, NonFinitePolicy};
+    /// let value = from_slice_value(b"[1, NaN]").unwrap();
+    /// let mut rv = Vec::new();
+    /// value.to_writer_dialect(&mut rv, Dialect::Python).unwrap();
+    /// assert_eq!(rv, b"[1,NaN]");
+    /// let strict = value.to_string_dialect(Dialect::Strict(NonFinitePolicy::Null));
+    /// assert_eq!(strict.unwrap(), "[1,null]");
+    /// ```
+    pub fn to_writer_dialect<W: io::Write>(
+        &self,
+        writer: W,
+        dialect: Dialect,
+    ) -> serde_json::Result<()> {
+        match dialect {
+            Dialect::Strict(policy) => serde_json::to_writer(writer, &Strict(self, policy)),
+            Dialect::Python => crate::to_writer_python(writer, self),
+        }
+    }
+
+    /// Formats the value as compact JSON in the given dialect.
+    pub fn to_string_dialect(&self, dialect: Dialect) -> serde_json::Result<String> {
+        let mut rv = Vec::new();
+        self.to_writer_dialect(&mut rv, dialect)?;
+        // both serializers only emit valid UTF-8
+        Ok(String::from_utf8(rv).unwrap_or_default())
+    }
+
+    /// Converts a `serde_json::Value` into a value.
+    ///
+    /// This is lossless as every `serde_json::Value` can be represented.
+    pub fn from_json_value(value: Value) -> PyValue {
+        match value {
+            Value::Null => PyValue::Null,
+            Value::Bool(value) => PyValue::Bool(value),
+            Value::Number(value) => PyValue::Number(value),
+            Value::String(value) => PyValue::String(value),
+            Value::Array(items) => {
+                PyValue::Array(items.into_iter().map(PyValue::from_json_value).collect())
+            }
+            Value::Object(map) => PyValue::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, PyValue::from_json_value(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<Value> for PyValue {
+    fn from(value: Value) -> PyValue {
+        PyValue::from_json_value(value)
+    }
+}
+
+impl From<f64> for PyValue {
+    fn from(value: f64) -> PyValue {
+        match Number::from_f64(value) {
+            Some(number) => PyValue::Number(number),
+            None => PyValue::NonFinite(value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PyValue {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<PyValue, D::Error> {
+        struct PyValueVisitor;
+
+        impl<'de> Visitor<'de> for PyValueVisitor {
+            type Value = PyValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("any value")
+            }
+
+            fn visit_unit<E>(self) -> Result<PyValue, E> {
+                Ok(PyValue::Null)
+            }
+
+            fn visit_none<E>(self) -> Result<PyValue, E> {
+                Ok(PyValue::Null)
+            }
+
+            fn visit_some<D: de::Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<PyValue, D::Error> {
+                PyValue::deserialize(deserializer)
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<PyValue, E> {
+                Ok(PyValue::Bool(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<PyValue, E> {
+                Ok(PyValue::Number(value.into()))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<PyValue, E> {
+                Ok(PyValue::Number(value.into()))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<PyValue, E> {
+                Ok(PyValue::from(value))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<PyValue, E> {
+                Ok(PyValue::String(value.to_string()))
+            }
+
+            fn visit_string<E>(self, value: String) -> Result<PyValue, E> {
+                Ok(PyValue::String(value))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PyValue, A::Error> {
+                let mut rv = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    rv.push(item);
+                }
+                Ok(PyValue::Array(rv))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<PyValue, A::Error> {
+                let mut rv = BTreeMap::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    // with arbitrary_precision serde_json hands out numbers
+                    // as maps with a single magic key
+                    if rv.is_empty() && key == NUMBER_TOKEN {
+                        let number: String = map.next_value()?;
+                        return number
+                            .parse()
+                            .map(PyValue::Number)
+                            .map_err(de::Error::custom);
+                    }
+                    let value = map.next_value()?;
+                    rv.insert(key, value);
+                }
+                Ok(PyValue::Object(rv))
+            }
+        }
+
+        deserializer.deserialize_any(PyValueVisitor)
+    }
+}
+
+impl Serialize for PyValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            PyValue::Null => serializer.serialize_unit(),
+            PyValue::Bool(value) => serializer.serialize_bool(value),
+            PyValue::Number(ref value) => value.serialize(serializer),
+            PyValue::NonFinite(value) => serializer.serialize_f64(value),
+            PyValue::String(ref value) => serializer.serialize_str(value),
+            PyValue::Array(ref items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            PyValue::Object(ref map) => {
+                let mut ser = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    ser.serialize_entry(key, value)?;
+                }
+                ser.end()
+            }
+        }
+    }
+}
+
+/// Serializes a value with non-finite floats converted by a policy.
+struct Strict<'a>(&'a PyValue, NonFinitePolicy);
+
+impl Serialize for Strict<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Strict(value, policy) = *self;
+        match *value {
+            PyValue::NonFinite(value) => policy.convert(value).serialize(serializer),
+            PyValue::Array(ref items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(&Strict(item, policy))?;
+                }
+                seq.end()
+            }
+            PyValue::Object(ref map) => {
+                let mut ser = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    ser.serialize_entry(key, &Strict(value, policy))?;
+                }
+                ser.end()
+            }
+            _ => value.serialize(serializer),
+        }
+    }
+}
+
+impl fmt::Display for PyValue {
+    /// Formats the value as compact Python flavoured JSON.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let json = crate::to_string_python(self).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+/// Parses a Python JSON document into a `PyValue`.
+///
+/// `NaN` and `Infinity` are kept as `PyValue::NonFinite`.
+pub fn from_slice_value(v: &[u8]) -> serde_json::Result<PyValue> {
+    from_slice_lossless(v)
+}
+
+/// Parses a Python JSON document from an IO stream into a `PyValue`.
+pub fn from_reader_value<R: io::Read>(rdr: R) -> serde_json::Result<PyValue> {
+    from_reader_lossless(rdr)
+}
+
+#[test]
+fn test_py_value() {
+    let value = from_slice_value(br#"{"b": [true, null, -3, 2.5, "s"], "a": Infinity}"#).unwrap();
+    assert_eq!(value.get("a"), Some(&PyValue::NonFinite(f64::INFINITY)));
+    let items = value.get("b").and_then(PyValue::as_array).unwrap();
+    assert_eq!(items[0].as_bool(), Some(true));
+    assert!(items[1].is_null());
+    assert_eq!(items[2].as_f64(), Some(-3.0));
+    assert_eq!(items[4].as_str(), Some("s"));
+    assert_eq!(
+        value.to_string(),
+        r#"{"a":Infinity,"b":[true,null,-3,2.5,"s"]}"#
+    );
+    assert_eq!(
+        from_slice_value(value.to_string().as_bytes()).unwrap(),
+        value
+    );
+
+    let value = from_reader_value(&b"[NaN]"[..]).unwrap();
+    assert!(value.as_array().unwrap()[0].as_f64().unwrap().is_nan());
+    assert!(from_reader_value(&b"[NaN"[..]).is_err());
+}
+
+#[test]
+fn test_py_value_from_serde_json() {
+    let value: PyValue = serde_json::from_str(r#"{"a": [1, 1.5, {}]}"#).unwrap();
+    let items = value.get("a").and_then(PyValue::as_array).unwrap();
+    assert_eq!(items[0], PyValue::Number(1.into()));
+    assert_eq!(items[1].as_f64(), Some(1.5));
+    assert_eq!(items[2], PyValue::Object(BTreeMap::new()));
+}
+
+#[test]
+fn test_py_value_json_conversion() {
+    let value = from_slice_value(br#"{"a": [NaN, Infinity, -Infinity], "b": 1}"#).unwrap();
+    let convert = |policy| value.to_json_value(policy).to_string();
+    assert_eq!(
+        convert(NonFinitePolicy::Null),
+        r#"{"a":[null,null,null],"b":1}"#
+    );
+    assert_eq!(
+        convert(NonFinitePolicy::Zero),
+        r#"{"a":[0.0,0.0,-0.0],"b":1}"#
+    );
+    assert_eq!(
+        convert(NonFinitePolicy::String),
+        r#"{"a":["NaN","Infinity","-Infinity"],"b":1}"#
+    );
+    assert_eq!(
+        value.to_json_value(NonFinitePolicy::Clamp)["a"],
+        serde_json::json!([null, f64::MAX, f64::MIN])
+    );
+
+    let json = serde_json::json!({"x": [1.5, null, "NaN"], "y": true});
+    let value = PyValue::from(json.clone());
+    assert_eq!(value.get("y"), Some(&PyValue::Bool(true)));
+    assert_eq!(value.to_json_value(NonFinitePolicy::Null), json);
+}
+
+#[test]
+fn test_py_value_dialect() {
+    let value = from_slice_value(br#"{"a": [NaN, -Infinity, {"b": 1.5}]}"#).unwrap();
+    let dump = |dialect| value.to_string_dialect(dialect).unwrap();
+    assert_eq!(dump(Dialect::Python), r#"{"a":[NaN,-Infinity,{"b":1.5}]}"#);
+    assert_eq!(
+        dump(Dialect::Strict(NonFinitePolicy::Zero)),
+        r#"{"a":[0.0,-0.0,{"b":1.5}]}"#
+    );
+    assert_eq!(
+        dump(Dialect::Strict(NonFinitePolicy::String)),
+        r#"{"a":["NaN","-Infinity",{"b":1.5}]}"#
+    );
+    let reparsed = from_slice_value(dump(Dialect::Python).as_bytes()).unwrap();
+    assert_eq!(reparsed.to_string(), value.to_string());
+}
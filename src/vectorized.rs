@@ -0,0 +1,111 @@
+//! SIMD scanners for `Backend::Simd`.
+//!
+//! The instruction set is picked at runtime on x86_64.  Other targets and
+//! the tails of chunks go through the scalar backend.
+use crate::backend::{Backend, INITIAL_NEEDLES, QUOTED_NEEDLES};
+
+fn needles(quoted: bool) -> &'static [u8] {
+    if quoted {
+        QUOTED_NEEDLES
+    } else {
+        INITIAL_NEEDLES
+    }
+}
+
+/// Returns the number of leading bytes that need no translation.
+pub(crate) fn skip(bytes: &[u8], quoted: bool) -> usize {
+    let needles = needles(quoted);
+    let offset = skip_vectors(bytes, needles);
+    match bytes.get(offset..) {
+        Some(rest) => offset + Backend::Scalar.skip(rest, quoted),
+        None => offset,
+    }
+}
+
+/// Scans whole vectors and returns the offset of the first needle or of the
+/// tail that is too short for a vector.
+#[cfg(target_arch = "x86_64")]
+fn skip_vectors(bytes: &[u8], needles: &[u8]) -> usize {
+    if std::is_x86_feature_detected!("avx2") {
+        // SAFETY: avx2 support was checked above
+        unsafe { x86::skip_avx2(bytes, needles) }
+    } else {
+        // SAFETY: sse2 is part of the x86_64 baseline
+        unsafe { x86::skip_sse2(bytes, needles) }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn skip_vectors(_bytes: &[u8], _needles: &[u8]) -> usize {
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn skip_sse2(bytes: &[u8], needles: &[u8]) -> usize {
+        let mut offset = 0;
+        while bytes.len() - offset >= 16 {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(offset) as *const __m128i);
+            let mut hits = _mm_setzero_si128();
+            for &needle in needles {
+                let eq = _mm_cmpeq_epi8(chunk, _mm_set1_epi8(needle as i8));
+                hits = _mm_or_si128(hits, eq);
+            }
+            let mask = _mm_movemask_epi8(hits) as u32;
+            if mask != 0 {
+                return offset + mask.trailing_zeros() as usize;
+            }
+            offset += 16;
+        }
+        offset
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn skip_avx2(bytes: &[u8], needles: &[u8]) -> usize {
+        let mut offset = 0;
+        while bytes.len() - offset >= 32 {
+            let chunk = _mm256_loadu_si256(bytes.as_ptr().add(offset) as *const __m256i);
+            let mut hits = _mm256_setzero_si256();
+            for &needle in needles {
+                let eq = _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(needle as i8));
+                hits = _mm256_or_si256(hits, eq);
+            }
+            let mask = _mm256_movemask_epi8(hits) as u32;
+            if mask != 0 {
+                return offset + mask.trailing_zeros() as usize;
+            }
+            offset += 32;
+        }
+        offset + skip_sse2(&bytes[offset..], needles)
+    }
+}
+
+#[test]
+fn test_simd_agrees_with_scalar() {
+    let mut doc = br#"{"a": [1, 2, 3], "long key without anything": NaN, "#.to_vec();
+    doc.extend_from_slice(br#""a longer string that spans more than a vector \" x", "#);
+    doc.extend_from_slice(br#""z": Infinity, "y": "\\"}"#);
+    for start in 0..doc.len() {
+        for &quoted in &[false, true] {
+            let rest = &doc[start..];
+            assert_eq!(
+                skip(rest, quoted),
+                Backend::Scalar.skip(rest, quoted),
+                "{} {}",
+                start,
+                quoted
+            );
+            #[cfg(target_arch = "x86_64")]
+            {
+                let offset = unsafe { x86::skip_sse2(rest, needles(quoted)) };
+                assert_eq!(
+                    offset + Backend::Scalar.skip(&rest[offset..], quoted),
+                    Backend::Scalar.skip(rest, quoted)
+                );
+            }
+        }
+    }
+}